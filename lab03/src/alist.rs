@@ -1,109 +1,456 @@
-/* A slice-based list. This is meant to be a homegrown version of Vec,
-but in practice is much worse than Vec.
+/* An array-based list. This is meant to be a homegrown version of Vec,
+and is built much the same way: a pointer to memory from the allocator, a
+capacity, and a length. Only the first len slots hold items; the rest are
+uninitialized, which is what lets AList hold any T, not just T: Default.
 
 Much like the real Vec, the main challenge is resizing when out of capacity.
+How much room to add is up to the list's Growth. Multiplying the capacity
+means each item is copied a constant number of times on average, so add_last
+is amortized O(1). Adding a fixed amount means every few adds copy the whole
+list, so N adds take O(N^2) time; the AListAdditive benchmark shows this.
 
-Reasons why this is a terrible implementation:
- - the real Vec implementation uses Unique<T> and an Allocator, which gives
-   much finer control over memory details. However, being Grug-brained
-   developers, we don't want to deal with more unsafe than we have to,
-   so will stick with boxed slices instead.
- - Because we're using Box<[T]>, we need to fill the unused spaces with
-   something. To avoid unsound behavior, we need to restrict the usage to
-   T: Default.
- - In order to allocate a Box<[T]>, we need to use a lot of unsafe functions.
-   This has the potential to crash our programs, exspecially in the presence
-   of ZSTs and other corner cases.
+Ways this is still worse than Vec:
+ - resize always allocates new memory and copies into it, instead of asking
+   the allocator to grow the allocation in place with realloc. That's on
+   purpose, so the benchmarks show the real cost of each resize.
+ - the real Vec leans on RawVec and Unique<T> and is far more careful about
+   overflow, specialization and the allocator API. The Rustonomicon's
+   chapter on Vec covers all of that.
+
+Zero-sized types never allocate: any number of them fit in no memory, so the
+capacity is usize::MAX and the pointer is left dangling.
 */
 
-use std::mem;
+use std::{
+    alloc::{self, Layout},
+    fmt,
+    iter::FusedIterator,
+    marker::PhantomData,
+    mem::{self, ManuallyDrop},
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+    slice,
+};
+
+/// How an AList picks its new capacity when it runs out of room
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Growth {
+    /// Multiplies the capacity by a factor, which has to be more than 1
+    Multiplicative(f64),
+    /// Adds a fixed number of slots, which has to be at least 1
+    Additive(usize),
+}
+
+impl Growth {
+    // the smallest capacity a multiplicative list grows to, so the first few
+    // adds don't each resize
+    const MIN_CAPACITY: usize = 4;
+
+    // the capacity to grow to from capacity, so that there's room for needed items
+    fn grow(self, capacity: usize, needed: usize) -> usize {
+        let grown = match self {
+            Growth::Multiplicative(factor) => {
+                let grown = (capacity as f64 * factor).ceil();
+                if grown >= usize::MAX as f64 {
+                    usize::MAX
+                } else {
+                    (grown as usize).max(Self::MIN_CAPACITY)
+                }
+            }
+            Growth::Additive(step) => capacity.saturating_add(step),
+        };
+        grown.max(needed)
+    }
+
+    fn validate(self) {
+        match self {
+            Growth::Multiplicative(factor) => assert!(
+                factor > 1.0,
+                "multiplicative growth factor must be more than 1, got {}",
+                factor
+            ),
+            Growth::Additive(step) => {
+                assert!(step >= 1, "additive growth step must be at least 1")
+            }
+        }
+    }
+}
+
+impl Default for Growth {
+    fn default() -> Self {
+        Growth::Multiplicative(2.0)
+    }
+}
 
 pub struct AList<T> {
-    items: Box<[T]>,
+    ptr: NonNull<T>,
+    capacity: usize,
     len: usize,
+    growth: Growth,
+    // tells the drop checker that an AList owns its Ts
+    _owns: PhantomData<T>,
 }
 
+// an AList owns its items just like a Vec does
+unsafe impl<T: Send> Send for AList<T> {}
+unsafe impl<T: Sync> Sync for AList<T> {}
+
 #[allow(dead_code)] // so clippy doesn't complain about unused functions
-impl<T: Default> AList<T> {
+impl<T> AList<T> {
+    const IS_ZST: bool = mem::size_of::<T>() == 0;
+
     pub fn new() -> Self {
-        let len = 10;
+        Self::with_growth(Growth::default())
+    }
+
+    pub fn with_growth(growth: Growth) -> Self {
+        growth.validate();
         Self {
-            items: allocate_slice(len),
-            len,
+            ptr: NonNull::dangling(),
+            capacity: if Self::IS_ZST { usize::MAX } else { 0 },
+            len: 0,
+            growth,
+            _owns: PhantomData,
         }
     }
 
+    pub fn growth(&self) -> Growth {
+        self.growth
+    }
+
+    pub fn set_growth(&mut self, growth: Growth) {
+        growth.validate();
+        self.growth = growth;
+    }
+
+    // how many items fit before the next resize
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // moves the items into a fresh allocation with room for capacity items
     fn resize(&mut self, capacity: usize) {
-        let mut new_items = allocate_slice(capacity);
-        for i in 0..self.items.len() {
-            mem::swap(&mut self.items[i], &mut new_items[i]);
+        debug_assert!(capacity >= self.len && !Self::IS_ZST);
+        let ptr = if capacity == 0 {
+            NonNull::dangling()
+        } else {
+            let layout = Layout::array::<T>(capacity).expect("capacity overflow");
+            // Safety: layout isn't zero-sized, since T isn't and capacity > 0
+            let raw = unsafe { alloc::alloc(layout) } as *mut T;
+            let Some(ptr) = NonNull::new(raw) else {
+                alloc::handle_alloc_error(layout);
+            };
+            // Safety: both allocations have room for len items, and the
+            // first len items of the old one are initialized
+            unsafe { ptr::copy_nonoverlapping(self.ptr.as_ptr(), ptr.as_ptr(), self.len) };
+            ptr
+        };
+        // Safety: the old items were moved, so freeing their memory doesn't drop them
+        unsafe { deallocate(self.ptr, self.capacity) };
+        self.ptr = ptr;
+        self.capacity = capacity;
+    }
+
+    // makes sure there's room for additional more items, growing by the policy
+    fn grow_for(&mut self, additional: usize) {
+        let needed = self.len.checked_add(additional).expect("capacity overflow");
+        if needed > self.capacity {
+            self.resize(self.growth.grow(self.capacity, needed));
         }
-        self.items = new_items;
     }
 
-    pub fn add_last(&mut self, t: T) {
-        if self.len >= self.items.len() {
-            self.resize(self.len * 2);
+    // shrinks the capacity to the length
+    pub fn shrink_to_fit(&mut self) {
+        if !Self::IS_ZST && self.capacity > self.len {
+            self.resize(self.len);
         }
+    }
 
-        self.items[self.len] = t;
+    pub fn add_last(&mut self, t: T) {
+        self.grow_for(1);
+        // Safety: slot len is in bounds and not initialized
+        unsafe { self.ptr.as_ptr().add(self.len).write(t) };
         self.len += 1;
     }
 
     pub fn get(&self, index: usize) -> Option<&T> {
-        if index < self.len {
-            Some(&self.items[index])
-        } else {
-            None
-        }
+        self.as_slice().get(index)
     }
 
     pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
-        if index < self.len {
-            Some(&mut self.items[index])
-        } else {
-            None
-        }
+        self.as_mut_slice().get_mut(index)
     }
 
     pub fn get_last(&self) -> Option<&T> {
-        if self.len > 0 {
-            Some(&self.items[self.len - 1])
-        } else {
-            None
-        }
+        self.as_slice().last()
     }
 
     pub fn get_last_mut(&mut self) -> Option<&mut T> {
-        if self.len > 0 {
-            Some(&mut self.items[self.len - 1])
-        } else {
-            None
-        }
+        self.as_mut_slice().last_mut()
     }
 
     pub fn len(&self) -> usize {
         self.len
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     pub fn remove_last(&mut self) -> Option<T> {
         if self.len == 0 {
             return None;
         }
         self.len -= 1;
-        Some(mem::take(&mut self.items[self.len]))
+        // Safety: slot len was the last initialized one, and is no longer part
+        // of the list, so it won't be read or dropped again
+        Some(unsafe { self.ptr.as_ptr().add(self.len).read() })
+    }
+
+    // inserts t at index, shifting everything after it one to the right
+    pub fn insert(&mut self, index: usize, t: T) {
+        assert!(
+            index <= self.len,
+            "insertion index (is {}) should be <= len (is {})",
+            index,
+            self.len
+        );
+        self.grow_for(1);
+        // Safety: there's room for one more, so shifting items index..len
+        // right by one stays in bounds
+        unsafe {
+            let p = self.ptr.as_ptr().add(index);
+            ptr::copy(p, p.add(1), self.len - index);
+            p.write(t);
+        }
+        self.len += 1;
+    }
+
+    // removes and returns the item at index, shifting everything after it one to the left
+    pub fn remove(&mut self, index: usize) -> T {
+        assert!(
+            index < self.len,
+            "removal index (is {}) should be < len (is {})",
+            index,
+            self.len
+        );
+        // Safety: index is initialized, and the shift overwrites its slot
+        // with the items after it, so nothing is duplicated
+        unsafe {
+            let p = self.ptr.as_ptr().add(index);
+            let t = p.read();
+            ptr::copy(p.add(1), p, self.len - index - 1);
+            self.len -= 1;
+            t
+        }
+    }
+
+    // removes and returns the item at index, replacing it with the last item.
+    // This is O(1), but doesn't keep the order
+    pub fn swap_remove(&mut self, index: usize) -> T {
+        assert!(
+            index < self.len,
+            "swap_remove index (is {}) should be < len (is {})",
+            index,
+            self.len
+        );
+        // Safety: both slots are initialized, and the last one is no longer
+        // part of the list. ptr::copy allows index to be the last slot
+        unsafe {
+            let base = self.ptr.as_ptr();
+            let t = base.add(index).read();
+            ptr::copy(base.add(self.len - 1), base.add(index), 1);
+            self.len -= 1;
+            t
+        }
+    }
+
+    // drops everything past the first len items
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        // Safety: items len..self.len are initialized. The length is set first,
+        // so a panicking drop can't lead to them being dropped twice
+        unsafe {
+            let tail = ptr::slice_from_raw_parts_mut(self.ptr.as_ptr().add(len), self.len - len);
+            self.len = len;
+            ptr::drop_in_place(tail);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        // Safety: the first len items are initialized, and the pointer is
+        // non-null and aligned even when nothing is allocated
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        // Safety: same as as_slice, and &mut self means nothing else can see them
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+#[allow(dead_code)]
+impl<T: Clone> AList<T> {
+    // adds a clone of each item of items to the end
+    pub fn extend_from_slice(&mut self, items: &[T]) {
+        self.grow_for(items.len());
+        for t in items {
+            // Safety: grow_for made room for all of them. len goes up one at
+            // a time, so if clone panics the ones already added still get dropped
+            unsafe { self.ptr.as_ptr().add(self.len).write(t.clone()) };
+            self.len += 1;
+        }
+    }
+}
+
+/* Frees memory that held capacity Ts, without dropping any of them */
+unsafe fn deallocate<T>(ptr: NonNull<T>, capacity: usize) {
+    if mem::size_of::<T>() != 0 && capacity > 0 {
+        let layout = Layout::array::<T>(capacity).unwrap();
+        alloc::dealloc(ptr.as_ptr() as *mut u8, layout);
+    }
+}
+
+impl<T> Drop for AList<T> {
+    fn drop(&mut self) {
+        self.clear();
+        // Safety: every item was just dropped
+        unsafe { deallocate(self.ptr, self.capacity) };
+    }
+}
+
+impl<T> Deref for AList<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T> DerefMut for AList<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T> Default for AList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Clone for AList<T> {
+    fn clone(&self) -> Self {
+        let mut clone = Self::with_growth(self.growth);
+        clone.extend_from_slice(self);
+        clone
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for AList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a AList<T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut AList<T> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
     }
 }
 
-/* Allocates a fixed-length Box<[T]> */
-fn allocate_slice<T: Default>(n: usize) -> Box<[T]> {
-    unsafe {
-        let layout = std::alloc::Layout::array::<T>(n).unwrap();
-        let ptr = std::alloc::alloc(layout) as *mut T;
-        for i in 0..n {
-            std::ptr::write(ptr.add(i), T::default());
+/* An iterator that moves the items out of an AList. Items start..end haven't
+been yielded yet; dropping the iterator drops them and frees the memory. */
+pub struct IntoIter<T> {
+    ptr: NonNull<T>,
+    capacity: usize,
+    start: usize,
+    end: usize,
+    _owns: PhantomData<T>,
+}
+
+unsafe impl<T: Send> Send for IntoIter<T> {}
+unsafe impl<T: Sync> Sync for IntoIter<T> {}
+
+impl<T> IntoIterator for AList<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        // the iterator takes over the memory, so the list mustn't free it
+        let list = ManuallyDrop::new(self);
+        IntoIter {
+            ptr: list.ptr,
+            capacity: list.capacity,
+            start: 0,
+            end: list.len,
+            _owns: PhantomData,
+        }
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.start == self.end {
+            return None;
+        }
+        self.start += 1;
+        // Safety: the item hadn't been yielded, and start has moved past it
+        Some(unsafe { self.ptr.as_ptr().add(self.start - 1).read() })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.start;
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.start == self.end {
+            return None;
+        }
+        self.end -= 1;
+        // Safety: the item hadn't been yielded, and end has moved past it
+        Some(unsafe { self.ptr.as_ptr().add(self.end).read() })
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> FusedIterator for IntoIter<T> {}
+
+impl<T> Drop for IntoIter<T> {
+    fn drop(&mut self) {
+        // Safety: items start..end are the ones that haven't been moved out
+        unsafe {
+            let rest = ptr::slice_from_raw_parts_mut(
+                self.ptr.as_ptr().add(self.start),
+                self.end - self.start,
+            );
+            self.start = self.end;
+            ptr::drop_in_place(rest);
+            deallocate(self.ptr, self.capacity);
         }
-        let slice = core::slice::from_raw_parts_mut(ptr, n);
-        Box::from_raw(slice)
     }
 }
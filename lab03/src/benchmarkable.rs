@@ -1,9 +1,14 @@
-use crate::{alist::AList, sllist::SLList};
+use crate::{
+    alist::{AList, Growth},
+    sllist::SLList,
+};
 
 pub trait Benchmarkable {
     const ALG_NAME: &'static str;
     const ADD_NAME: &'static str;
     const GET_NAME: &'static str;
+    /// the largest N to time, for types whose add is too slow to reach the default
+    const MAX_N: u64 = 128000;
     fn new() -> Self;
     fn add(&mut self, value: i32);
     fn get(&self) -> Option<&i32>;
@@ -43,19 +48,69 @@ impl Benchmarkable for AList<i32> {
     }
 }
 
+/* An AList that grows by a fixed number of slots instead of doubling. Every
+resize copies the whole list, so adding N items copies about N^2 / (2 * STEP)
+of them, and the time per add_last grows with N. */
+pub struct AdditiveAList(AList<i32>);
+
+impl AdditiveAList {
+    const STEP: usize = 100;
+}
+
+impl Benchmarkable for AdditiveAList {
+    const ALG_NAME: &'static str = "AList(additive)";
+    const ADD_NAME: &'static str = "add_last";
+    const GET_NAME: &'static str = "get_last";
+    fn new() -> Self {
+        Self(AList::with_growth(Growth::Additive(Self::STEP)))
+    }
+
+    fn add(&mut self, value: i32) {
+        self.0.add_last(value)
+    }
+
+    fn get(&self) -> Option<&i32> {
+        self.0.get_last()
+    }
+}
+
 impl Benchmarkable for SLList<i32> {
     const ALG_NAME: &'static str = "SLList";
-    const ADD_NAME: &'static str = "add_first";
+    const ADD_NAME: &'static str = "add_last";
     const GET_NAME: &'static str = "get_last";
     fn new() -> Self {
         Self::new()
     }
 
     fn add(&mut self, value: i32) {
-        self.add_first(value)
+        self.add_last(value)
     }
 
     fn get(&self) -> Option<&i32> {
         self.get_last()
     }
 }
+
+/* An SLList that ignores its tail pointer and walks from the head to find the
+last node, like it did before it had one, so add_last and get_last take time
+proportional to the length. Adding N items that way takes O(N^2) time, and the
+biggest tables would take minutes to fill, so they stop at a smaller N. */
+pub struct WalkingSLList(SLList<i32>);
+
+impl Benchmarkable for WalkingSLList {
+    const ALG_NAME: &'static str = "SLList(walking)";
+    const ADD_NAME: &'static str = "add_last_walking";
+    const GET_NAME: &'static str = "get_last_walking";
+    const MAX_N: u64 = 32000;
+    fn new() -> Self {
+        Self(SLList::new())
+    }
+
+    fn add(&mut self, value: i32) {
+        self.0.add_last_walking(value)
+    }
+
+    fn get(&self) -> Option<&i32> {
+        self.0.get_last_walking()
+    }
+}
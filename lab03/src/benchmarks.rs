@@ -1,10 +1,13 @@
-use std::time::{Duration, Instant};
+use std::{
+    hint,
+    time::{Duration, Instant},
+};
 
 use crate::benchmarkable::Benchmarkable;
 
 /*
- Benchmarks the "add" and "get" methods of the Benchmarkable type, formatting the
- results in tables like this:
+ Benchmarks the "add" and "get" methods of the Benchmarkable type, for N from 1000
+ doubling up to the type's MAX_N, formatting the results in tables like this:
 
  Timing table for Vec::push
            N     time (s)        # ops  microsec/op
@@ -36,7 +39,7 @@ pub fn benchmark<T: Benchmarkable>() {
 
     let mut add_timings: Vec<Timing> = Vec::new();
 
-    for _ in 0..8 {
+    while cur_size <= T::MAX_N {
         let mut list = T::new();
         let start = Instant::now();
         for _ in 0..cur_size {
@@ -44,21 +47,26 @@ pub fn benchmark<T: Benchmarkable>() {
         }
         let add_elapsed = start.elapsed();
         add_timings.push(Timing::new(cur_size, add_elapsed, cur_size));
-        cur_size = cur_size * 2;
+        cur_size *= 2;
     }
     print_timing_table(T::ALG_NAME, T::ADD_NAME, &add_timings);
 
     let mut get_timings: Vec<Timing> = Vec::new();
     cur_size = 1000;
-    for _ in 0..8 {
+    while cur_size <= T::MAX_N {
         let mut list = T::new();
-        let start = Instant::now();
-        for _ in 0..1000 {
+        for _ in 0..cur_size {
             list.add(42);
         }
-        let add_elapsed = start.elapsed();
-        get_timings.push(Timing::new(cur_size, add_elapsed, 1000));
-        cur_size = cur_size * 2;
+        let start = Instant::now();
+        for _ in 0..10000 {
+            // black_box stops the compiler from noticing the result is unused
+            // and skipping the calls
+            hint::black_box(list.get());
+        }
+        let get_elapsed = start.elapsed();
+        get_timings.push(Timing::new(cur_size, get_elapsed, 10000));
+        cur_size *= 2;
     }
     print_timing_table(T::ALG_NAME, T::GET_NAME, &get_timings);
 }
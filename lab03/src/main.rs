@@ -6,6 +6,7 @@ mod randomized_test;
 mod sllist;
 
 use alist::AList;
+use benchmarkable::{AdditiveAList, WalkingSLList};
 use benchmarks::benchmark;
 use clap::Parser;
 use sllist::SLList;
@@ -15,7 +16,9 @@ use std::str::FromStr;
 enum Algorithm {
     Vec,
     AList,
+    AListAdditive,
     SLList,
+    SLListWalking,
 }
 
 impl FromStr for Algorithm {
@@ -25,7 +28,9 @@ impl FromStr for Algorithm {
         match s {
             "Vec" => Ok(Algorithm::Vec),
             "AList" => Ok(Algorithm::AList),
+            "AListAdditive" => Ok(Algorithm::AListAdditive),
             "SLList" => Ok(Algorithm::SLList),
+            "SLListWalking" => Ok(Algorithm::SLListWalking),
             _ => Err("Not a valid algorithm"),
         }
     }
@@ -34,7 +39,7 @@ impl FromStr for Algorithm {
 #[derive(Parser, Debug)]
 /// Benchmark various list algorithms
 struct Args {
-    /// which algorithm to use. Can be Vec, AList, or SLList.
+    /// which algorithm to use. Can be Vec, AList, AListAdditive, SLList, or SLListWalking.
     algorithm: Vec<Algorithm>,
 }
 
@@ -44,7 +49,9 @@ fn main() {
         match alg {
             Algorithm::Vec => benchmark::<Vec<i32>>(),
             Algorithm::AList => benchmark::<AList<i32>>(),
+            Algorithm::AListAdditive => benchmark::<AdditiveAList>(),
             Algorithm::SLList => benchmark::<SLList<i32>>(),
+            Algorithm::SLListWalking => benchmark::<WalkingSLList>(),
         }
     }
 }
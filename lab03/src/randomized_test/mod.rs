@@ -1,4 +1,10 @@
+use std::{collections::VecDeque, rc::Rc};
+
 use self::{alistnoresizing::AListNR, buggyalist::BAList};
+use crate::{
+    alist::{AList, Growth},
+    sllist::SLList,
+};
 
 mod alistnoresizing;
 mod buggyalist;
@@ -51,3 +57,337 @@ fn randomized_test() {
         }
     }
 }
+
+// a type without Default, which the old AList couldn't hold
+#[derive(Clone, Debug, PartialEq)]
+struct Item(String);
+
+fn random_item() -> Item {
+    Item((rand::random::<u32>() % 1000).to_string())
+}
+
+fn randomized_alist_test(growth: Growth) {
+    let mut correct: Vec<Item> = Vec::new();
+    let mut alist: AList<Item> = AList::with_growth(growth);
+
+    for _ in 0..5000 {
+        let len = correct.len();
+        match rand::random::<u32>() % 8 {
+            0 | 1 => {
+                let item = random_item();
+                correct.push(item.clone());
+                alist.add_last(item);
+            }
+            2 => assert_eq!(correct.pop(), alist.remove_last()),
+            3 => {
+                let i = rand::random::<usize>() % (len + 1);
+                let item = random_item();
+                correct.insert(i, item.clone());
+                alist.insert(i, item);
+            }
+            4 if len > 0 => {
+                let i = rand::random::<usize>() % len;
+                assert_eq!(correct.remove(i), alist.remove(i));
+            }
+            5 if len > 0 => {
+                let i = rand::random::<usize>() % len;
+                assert_eq!(correct.swap_remove(i), alist.swap_remove(i));
+            }
+            6 => {
+                let items: Vec<Item> = (0..rand::random::<usize>() % 5)
+                    .map(|_| random_item())
+                    .collect();
+                correct.extend_from_slice(&items);
+                alist.extend_from_slice(&items);
+            }
+            7 => {
+                let new_len = len.saturating_sub(rand::random::<usize>() % 3);
+                correct.truncate(new_len);
+                alist.truncate(new_len);
+            }
+            _ => {}
+        }
+        assert_eq!(correct.len(), alist.len());
+        assert!(alist.capacity() >= alist.len());
+        assert_eq!(correct.last(), alist.get_last());
+    }
+    assert_eq!(&correct[..], &alist[..]);
+    assert_eq!(correct, alist.into_iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn randomized_alist_multiplicative() {
+    randomized_alist_test(Growth::Multiplicative(1.5));
+}
+
+#[test]
+fn randomized_alist_additive() {
+    randomized_alist_test(Growth::Additive(3));
+}
+
+#[test]
+fn alist_starts_empty() {
+    let mut alist: AList<i32> = AList::new();
+    assert_eq!(alist.len(), 0);
+    assert_eq!(alist.get_last(), None);
+    assert_eq!(alist.remove_last(), None);
+    // resize used to copy past the end of the list
+    for i in 0..100 {
+        alist.add_last(i);
+    }
+    assert!(alist.iter().copied().eq(0..100));
+}
+
+#[test]
+fn alist_growth() {
+    let mut additive: AList<u8> = AList::with_growth(Growth::Additive(10));
+    let mut doubling: AList<u8> = AList::new();
+    let mut additive_capacities = Vec::new();
+    let mut doubling_capacities = Vec::new();
+    for i in 0..40 {
+        additive.add_last(i);
+        doubling.add_last(i);
+        additive_capacities.push(additive.capacity());
+        doubling_capacities.push(doubling.capacity());
+    }
+    additive_capacities.dedup();
+    doubling_capacities.dedup();
+    assert_eq!(additive_capacities, [10, 20, 30, 40]);
+    assert_eq!(doubling_capacities, [4, 8, 16, 32, 64]);
+
+    additive.extend_from_slice(&[0; 25]);
+    assert_eq!(additive.capacity(), 65);
+    additive.truncate(3);
+    additive.shrink_to_fit();
+    assert_eq!(additive.capacity(), 3);
+    assert_eq!(&additive[..], [0, 1, 2]);
+}
+
+#[test]
+#[should_panic]
+fn alist_growth_must_grow() {
+    AList::<i32>::with_growth(Growth::Multiplicative(1.0));
+}
+
+#[test]
+#[should_panic]
+fn alist_insert_out_of_bounds() {
+    let mut alist: AList<i32> = AList::new();
+    alist.add_last(1);
+    alist.insert(2, 3);
+}
+
+// every item is dropped exactly once, however it leaves the list
+#[test]
+fn alist_drops() {
+    let item = Rc::new(());
+    let mut alist = AList::new();
+    for _ in 0..100 {
+        alist.add_last(Rc::clone(&item));
+    }
+    alist.extend_from_slice(&[Rc::clone(&item), Rc::clone(&item)]);
+    assert_eq!(Rc::strong_count(&item), 103);
+    alist.remove(5);
+    alist.swap_remove(0);
+    alist.remove_last();
+    assert_eq!(Rc::strong_count(&item), 100);
+    alist.truncate(90);
+    assert_eq!(Rc::strong_count(&item), 91);
+    let copy = alist.clone();
+    assert_eq!(Rc::strong_count(&item), 181);
+    drop(copy);
+
+    let mut iter = alist.into_iter();
+    let first = iter.next();
+    let last = iter.next_back();
+    assert_eq!(iter.len(), 88);
+    drop(iter);
+    assert_eq!(Rc::strong_count(&item), 3);
+    drop((first, last));
+    assert_eq!(Rc::strong_count(&item), 1);
+}
+
+#[test]
+fn alist_zero_sized() {
+    let mut alist: AList<()> = AList::new();
+    for _ in 0..1000 {
+        alist.add_last(());
+    }
+    alist.insert(500, ());
+    assert_eq!(alist.remove(0), ());
+    alist.extend_from_slice(&[(); 10]);
+    assert_eq!(alist.len(), 1010);
+    assert_eq!(alist.capacity(), usize::MAX);
+    assert_eq!(alist.iter().count(), 1010);
+    assert_eq!(alist.into_iter().rev().count(), 1010);
+}
+
+#[test]
+fn alist_slice_methods() {
+    let mut alist: AList<i32> = AList::new();
+    alist.extend_from_slice(&[3, 1, 2]);
+    alist.sort();
+    assert_eq!(&alist[..], [1, 2, 3]);
+    assert!(alist.contains(&2));
+    for x in &mut alist {
+        *x *= 10;
+    }
+    assert_eq!(alist.iter().sum::<i32>(), 60);
+    assert_eq!(format!("{:?}", alist), "[10, 20, 30]");
+}
+
+// checks every way of reaching the ends, plus the links in between
+fn check_sllist(correct: &VecDeque<i32>, sllist: &SLList<i32>) {
+    assert_eq!(correct.len(), sllist.len());
+    assert_eq!(correct.front(), sllist.get_first());
+    assert_eq!(correct.back(), sllist.get_last());
+    assert_eq!(correct.back(), sllist.get_last_walking());
+    assert!(correct.iter().eq(sllist.iter()));
+}
+
+#[test]
+fn randomized_sllist_test() {
+    let mut correct: VecDeque<i32> = VecDeque::new();
+    let mut sllist: SLList<i32> = SLList::new();
+
+    for _ in 0..5000 {
+        let len = correct.len();
+        let num = rand::random::<i32>() % 1000;
+        match rand::random::<u32>() % 9 {
+            0 => {
+                correct.push_front(num);
+                sllist.add_first(num);
+            }
+            1 => {
+                correct.push_back(num);
+                sllist.add_last(num);
+            }
+            2 => {
+                correct.push_back(num);
+                sllist.add_last_walking(num);
+            }
+            3 => assert_eq!(correct.pop_front(), sllist.remove_first()),
+            4 => assert_eq!(correct.pop_back(), sllist.remove_last()),
+            5 => {
+                let i = rand::random::<usize>() % (len + 1);
+                correct.insert(i, num);
+                sllist.insert(i, num);
+            }
+            6 => {
+                correct.make_contiguous().reverse();
+                sllist.reverse();
+            }
+            7 => {
+                correct.make_contiguous().reverse();
+                sllist.reverse_recursive();
+            }
+            8 => {
+                if let Some(last) = correct.back_mut() {
+                    *last += 1;
+                }
+                if let Some(last) = sllist.get_last_mut() {
+                    *last += 1;
+                }
+            }
+            _ => {}
+        }
+        check_sllist(&correct, &sllist);
+    }
+}
+
+// the tail pointer has to follow the last node through every change at the ends
+#[test]
+fn sllist_tail() {
+    let mut correct = VecDeque::new();
+    let mut sllist = SLList::new();
+    sllist.add_first(1);
+    correct.push_front(1);
+    check_sllist(&correct, &sllist);
+    sllist.add_last(2);
+    correct.push_back(2);
+    check_sllist(&correct, &sllist);
+    sllist.insert(2, 3);
+    correct.push_back(3);
+    check_sllist(&correct, &sllist);
+    assert_eq!(sllist.remove_last(), Some(3));
+    correct.pop_back();
+    check_sllist(&correct, &sllist);
+    sllist.reverse();
+    correct.make_contiguous().reverse();
+    check_sllist(&correct, &sllist);
+    sllist.add_last(4);
+    correct.push_back(4);
+    check_sllist(&correct, &sllist);
+
+    // emptying the list from either end clears the tail
+    assert_eq!(sllist.remove_first(), Some(2));
+    assert_eq!(sllist.remove_first(), Some(1));
+    assert_eq!(sllist.remove_last(), Some(4));
+    assert_eq!(sllist.get_last(), None);
+    assert_eq!(sllist.remove_last(), None);
+    sllist.add_last(5);
+    assert_eq!(sllist.remove_last(), Some(5));
+    assert_eq!(sllist.remove_first(), None);
+    sllist.reverse_recursive();
+    sllist.add_last(6);
+    assert_eq!(sllist.get_first(), Some(&6));
+    assert_eq!(sllist.get_last(), Some(&6));
+}
+
+#[test]
+#[should_panic]
+fn sllist_insert_out_of_bounds() {
+    let mut sllist: SLList<i32> = SLList::new();
+    sllist.add_first(1);
+    sllist.insert(2, 3);
+}
+
+// every item is dropped exactly once, however it leaves the list
+#[test]
+fn sllist_drops() {
+    let item = Rc::new(());
+    let mut sllist = SLList::new();
+    for i in 0..100 {
+        sllist.insert(i / 2, Rc::clone(&item));
+    }
+    sllist.remove_first();
+    sllist.remove_last();
+    assert_eq!(Rc::strong_count(&item), 99);
+
+    let mut iter = sllist.into_iter();
+    let first = iter.next();
+    assert_eq!(iter.len(), 97);
+    drop(iter);
+    assert_eq!(Rc::strong_count(&item), 2);
+    drop(first);
+    assert_eq!(Rc::strong_count(&item), 1);
+}
+
+// dropping a node used to drop the rest of the list from inside its own drop,
+// which overflowed the stack long before a list this long
+#[test]
+fn sllist_drop_long_list() {
+    let mut sllist = SLList::new();
+    for i in 0..1_000_000 {
+        sllist.add_last(i);
+    }
+    sllist.reverse();
+    assert_eq!(sllist.get_first(), Some(&999_999));
+    drop(sllist);
+}
+
+#[test]
+fn sllist_iterators() {
+    let mut sllist: SLList<String> = SLList::new();
+    for word in ["b", "c", "d"] {
+        sllist.add_last(word.to_string());
+    }
+    sllist.add_first("a".to_string());
+    for word in &mut sllist {
+        word.push('!');
+    }
+    assert_eq!(sllist.iter().len(), 4);
+    assert_eq!(format!("{:?}", sllist), r#"["a!", "b!", "c!", "d!"]"#);
+    let joined: String = sllist.into_iter().collect();
+    assert_eq!(joined, "a!b!c!d!");
+}
@@ -1,97 +1,216 @@
-use std::mem;
+use std::{fmt, iter::FusedIterator, marker::PhantomData, mem, ptr::NonNull};
 
 // a list node
 struct SLNode<T> {
     item: T,
-    next: Option<Box<SLNode<T>>>,
+    next: Link<T>,
 }
 
+// Option<NonNull<_>> compiles into a nullable pointer, just like Option<Box<_>>
+type Link<T> = Option<NonNull<SLNode<T>>>;
+
 /* A singly linked list. This singly-linked list is great for its constant-time
-add_first and get_first operations.
+add_first and get_first operations, and with the tail pointer, add_last and
+get_last are constant-time too. remove_last still has to walk the list, since
+nothing points back at the node before the last one.
 
-Note that Option<Box<_>> was chosen instead of Box<Option<_>>, because the former
-can be compiled into a nullable pointer.
+The nodes used to be owned through Option<Box<_>> links, which is the safe way
+to build a linked list. But a tail pointer is a second pointer to the last
+node, and Box promises that it is the only pointer to what it holds: moving a
+Box around invalidates any raw pointer into it, so writing through the tail
+afterwards would be undefined behavior. Instead, every link is a raw pointer
+to a node made by Box::leak, and the list frees nodes itself with
+Box::from_raw. Every unsafe block relies on the same invariants:
+ - head and every next link point at a live node that only this list owns
+ - tail points at the last node, and is None exactly when head is
+ - len is the number of nodes
 
-Several of the implementations are ugly; linked lists are just hard to make
-work with ownership rules.
+add_last_walking and get_last_walking are the old O(n) versions, which walk
+from the head. They're kept so the benchmark can show them side by side.
 */
 pub struct SLList<T> {
-    head: Option<Box<SLNode<T>>>,
+    head: Link<T>,
+    tail: Link<T>,
     len: usize,
+    // tells the drop checker that an SLList owns its nodes
+    _owns: PhantomData<Box<SLNode<T>>>,
+}
+
+// an SLList owns its items just like a Vec does
+unsafe impl<T: Send> Send for SLList<T> {}
+unsafe impl<T: Sync> Sync for SLList<T> {}
+
+// makes a node that nothing owns yet
+fn new_node<T>(item: T, next: Link<T>) -> NonNull<SLNode<T>> {
+    NonNull::from(Box::leak(Box::new(SLNode { item, next })))
 }
 
 #[allow(dead_code)] // so clippy doesn't complain about unused functions
 impl<T> SLList<T> {
     pub fn new() -> SLList<T> {
-        SLList { head: None, len: 0 }
+        SLList {
+            head: None,
+            tail: None,
+            len: 0,
+            _owns: PhantomData,
+        }
     }
 
     // adds an element to the beginning of the list
     pub fn add_first(&mut self, t: T) {
-        let first = Some(Box::from(SLNode {
-            item: t,
-            next: None,
-        }));
-        // this mem::replace is necessary because we want to own old, but in order for
-        // the SLList to remain safe, old's location needs get something.
-        let old = mem::replace(&mut self.head, first);
-        // unwrap is ok here since we just placed a Some()
-        self.head.as_mut().unwrap().as_mut().next = old;
+        let node = new_node(t, self.head);
+        if self.tail.is_none() {
+            self.tail = Some(node);
+        }
+        self.head = Some(node);
         self.len += 1;
     }
 
     // gets a reference to the first item, returning None if the list is empty
     pub fn get_first(&self) -> Option<&T> {
-        match &self.head {
-            Some(n) => Some(&n.item),
-            None => None,
-        }
+        // Safety: head is live, and &self keeps the list from changing
+        self.head.map(|node| unsafe { &(*node.as_ptr()).item })
     }
 
     // gets a mutable reference to the first item, returning None if the list is empty
     pub fn get_first_mut(&mut self) -> Option<&mut T> {
-        match &mut self.head {
-            Some(n) => Some(&mut n.item),
-            None => None,
-        }
+        // Safety: head is live, and &mut self means nothing else can reach it
+        self.head.map(|node| unsafe { &mut (*node.as_ptr()).item })
     }
 
     // adds an element to the end of the list
     pub fn add_last(&mut self, t: T) {
-        let mut end = &mut self.head;
-        while let Some(n) = end {
-            end = &mut n.as_mut().next
+        let node = new_node(t, None);
+        match self.tail {
+            // Safety: tail is the live last node
+            Some(tail) => unsafe { (*tail.as_ptr()).next = Some(node) },
+            None => self.head = Some(node),
+        }
+        self.tail = Some(node);
+        self.len += 1;
+    }
+
+    // adds an element to the end of the list by walking there from the head
+    pub fn add_last_walking(&mut self, t: T) {
+        let node = new_node(t, None);
+        match self.head {
+            None => self.head = Some(node),
+            // Safety: every node reachable from head is live
+            Some(mut last) => unsafe {
+                while let Some(next) = (*last.as_ptr()).next {
+                    last = next;
+                }
+                (*last.as_ptr()).next = Some(node);
+            },
         }
-        *end = Some(Box::from(SLNode {
-            item: t,
-            next: None,
-        }));
+        self.tail = Some(node);
         self.len += 1;
     }
 
     // gets a reference to the last item, returning None if the list is empty
     pub fn get_last(&self) -> Option<&T> {
-        if let Some(node) = &self.head {
-            let mut last = node;
-            while let Some(next) = &last.next {
-                last = next;
-            }
-            Some(&last.item)
-        } else {
-            None
-        }
+        // Safety: tail is live, and &self keeps the list from changing
+        self.tail.map(|node| unsafe { &(*node.as_ptr()).item })
     }
 
     // gets a mutable reference to the last item, returning None if the list is empty
     pub fn get_last_mut(&mut self) -> Option<&mut T> {
-        if let Some(node) = &mut self.head {
-            let mut last = node;
-            while let Some(next) = &mut last.next {
-                last = next;
+        // Safety: tail is live, and &mut self means nothing else can reach it
+        self.tail.map(|node| unsafe { &mut (*node.as_ptr()).item })
+    }
+
+    // gets a reference to the last item by walking there from the head
+    pub fn get_last_walking(&self) -> Option<&T> {
+        self.iter().last()
+    }
+
+    // removes and returns the first item, returning None if the list is empty
+    pub fn remove_first(&mut self) -> Option<T> {
+        self.head.map(|node| {
+            // Safety: head is live and owned by the list, which gives it up here
+            let node = unsafe { Box::from_raw(node.as_ptr()) };
+            self.head = node.next;
+            if self.head.is_none() {
+                self.tail = None;
             }
-            Some(&mut last.item)
+            self.len -= 1;
+            node.item
+        })
+    }
+
+    // removes and returns the last item, returning None if the list is empty.
+    // This walks the list to find the node before the last one
+    pub fn remove_last(&mut self) -> Option<T> {
+        if self.len <= 1 {
+            return self.remove_first();
+        }
+        let before = self.node_at(self.len - 2);
+        // Safety: before is live and isn't the last node, so its next is the
+        // tail, which the list gives up here
+        let last = unsafe {
+            let last = (*before.as_ptr()).next.take().unwrap();
+            Box::from_raw(last.as_ptr())
+        };
+        self.tail = Some(before);
+        self.len -= 1;
+        Some(last.item)
+    }
+
+    // inserts t so that it ends up at index, panicking if index > len
+    pub fn insert(&mut self, index: usize, t: T) {
+        assert!(
+            index <= self.len,
+            "insertion index (is {}) should be <= len (is {})",
+            index,
+            self.len
+        );
+        if index == 0 {
+            self.add_first(t);
+        } else if index == self.len {
+            self.add_last(t);
         } else {
-            None
+            let before = self.node_at(index - 1);
+            // Safety: before is live, and isn't the last node, so tail stays put
+            unsafe {
+                let node = new_node(t, (*before.as_ptr()).next);
+                (*before.as_ptr()).next = Some(node);
+            }
+            self.len += 1;
+        }
+    }
+
+    // the node at index, which has to be < len
+    fn node_at(&self, index: usize) -> NonNull<SLNode<T>> {
+        debug_assert!(index < self.len);
+        let mut node = self.head.unwrap();
+        for _ in 0..index {
+            // Safety: there are len nodes, so the first index links all exist
+            node = unsafe { (*node.as_ptr()).next.unwrap() };
+        }
+        node
+    }
+
+    // reverses the list in place by turning each link around as it goes
+    pub fn reverse(&mut self) {
+        let mut reversed = None;
+        let mut rest = self.head;
+        self.tail = self.head;
+        while let Some(node) = rest {
+            // Safety: node is live, and is moved from rest onto reversed
+            unsafe { rest = mem::replace(&mut (*node.as_ptr()).next, reversed) };
+            reversed = Some(node);
+        }
+        self.head = reversed;
+    }
+
+    // reverses the list in place, recursing once per node. Rust doesn't
+    // promise tail calls, so a long enough list overflows the stack; reverse
+    // doesn't have that problem
+    pub fn reverse_recursive(&mut self) {
+        self.tail = self.head;
+        if let Some(head) = self.head {
+            // Safety: head is live, and the list's nodes are only linked from each other
+            self.head = Some(unsafe { reverse_from(head, None) });
         }
     }
 
@@ -99,4 +218,156 @@ impl<T> SLList<T> {
     pub fn len(&self) -> usize {
         self.len
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            next: self.head,
+            len: self.len,
+            _list: PhantomData,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            next: self.head,
+            len: self.len,
+            _list: PhantomData,
+        }
+    }
+}
+
+/* Points node at prev, then carries on with the node after it. Returns the
+old last node, which is the new head */
+unsafe fn reverse_from<T>(node: NonNull<SLNode<T>>, prev: Link<T>) -> NonNull<SLNode<T>> {
+    match mem::replace(&mut (*node.as_ptr()).next, prev) {
+        Some(next) => reverse_from(next, Some(node)),
+        None => node,
+    }
+}
+
+// frees the nodes one at a time. Dropping each node's next from inside its own
+// drop, like the old Option<Box<_>> links did, recurses once per node and
+// overflows the stack on a long list
+impl<T> Drop for SLList<T> {
+    fn drop(&mut self) {
+        while self.remove_first().is_some() {}
+    }
+}
+
+impl<T> Default for SLList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SLList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+pub struct Iter<'a, T> {
+    next: Link<T>,
+    len: usize,
+    _list: PhantomData<&'a SLNode<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.next.map(|node| {
+            // Safety: the list is borrowed for 'a, so its nodes live that long
+            let node = unsafe { &*node.as_ptr() };
+            self.next = node.next;
+            self.len -= 1;
+            &node.item
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+pub struct IterMut<'a, T> {
+    next: Link<T>,
+    len: usize,
+    _list: PhantomData<&'a mut SLNode<T>>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        self.next.map(|node| {
+            // Safety: the list is mutably borrowed for 'a, and each node is
+            // handed out once
+            let node = unsafe { &mut *node.as_ptr() };
+            self.next = node.next;
+            self.len -= 1;
+            &mut node.item
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+impl<T> FusedIterator for IterMut<'_, T> {}
+
+pub struct IntoIter<T>(SLList<T>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.0.remove_first()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> FusedIterator for IntoIter<T> {}
+
+impl<T> IntoIterator for SLList<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a SLList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut SLList<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
 }
@@ -0,0 +1,446 @@
+use std::{borrow::Borrow, cmp::Ordering};
+
+use crate::Map61B;
+
+type Link<K, V> = Option<Box<AVLNode<K, V>>>;
+
+struct AVLNode<K, V> {
+    key: K,
+    value: V,
+    left: Link<K, V>,
+    right: Link<K, V>,
+    /// number of nodes on the longest path from this node down
+    height: usize,
+}
+
+/*
+An AVL tree: a binary search tree where the heights of every node's two
+subtrees differ by at most one. Insertions and removals fix any node that
+breaks this with one or two rotations on the way back up, so the height stays
+below 1.44 log2(n).
+*/
+pub struct AVLMap<K, V> {
+    root: Link<K, V>,
+    size: usize,
+}
+
+fn height<K, V>(link: &Link<K, V>) -> usize {
+    link.as_ref().map_or(0, |n| n.height)
+}
+
+fn update_height<K, V>(n: &mut AVLNode<K, V>) {
+    n.height = 1 + height(&n.left).max(height(&n.right));
+}
+
+/// Returns how much taller n's left subtree is than its right subtree
+fn balance_factor<K, V>(n: &AVLNode<K, V>) -> isize {
+    height(&n.left) as isize - height(&n.right) as isize
+}
+
+fn rotate_left<K, V>(mut h: Box<AVLNode<K, V>>) -> Box<AVLNode<K, V>> {
+    let mut x = h.right.take().unwrap();
+    h.right = x.left.take();
+    update_height(&mut h);
+    x.left = Some(h);
+    update_height(&mut x);
+    x
+}
+
+fn rotate_right<K, V>(mut h: Box<AVLNode<K, V>>) -> Box<AVLNode<K, V>> {
+    let mut x = h.left.take().unwrap();
+    h.left = x.right.take();
+    update_height(&mut h);
+    x.right = Some(h);
+    update_height(&mut x);
+    x
+}
+
+/// Restores the invariant at h, assuming that its subtrees are valid AVL trees
+/// whose heights differ by at most two
+fn rebalance<K, V>(mut h: Box<AVLNode<K, V>>) -> Box<AVLNode<K, V>> {
+    update_height(&mut h);
+    let factor = balance_factor(&h);
+    if factor > 1 {
+        if balance_factor(h.left.as_ref().unwrap()) < 0 {
+            h.left = h.left.take().map(rotate_left);
+        }
+        rotate_right(h)
+    } else if factor < -1 {
+        if balance_factor(h.right.as_ref().unwrap()) > 0 {
+            h.right = h.right.take().map(rotate_right);
+        }
+        rotate_left(h)
+    } else {
+        h
+    }
+}
+
+fn insert<K: Ord, V>(h: Link<K, V>, key: K, value: V) -> (Box<AVLNode<K, V>>, Option<V>) {
+    let Some(mut h) = h else {
+        let node = AVLNode {
+            key,
+            value,
+            left: None,
+            right: None,
+            height: 1,
+        };
+        return (Box::new(node), None);
+    };
+    match key.cmp(&h.key) {
+        Ordering::Less => {
+            let (left, prev) = insert(h.left.take(), key, value);
+            h.left = Some(left);
+            (rebalance(h), prev)
+        }
+        Ordering::Equal => {
+            let prev = std::mem::replace(&mut h.value, value);
+            (h, Some(prev))
+        }
+        Ordering::Greater => {
+            let (right, prev) = insert(h.right.take(), key, value);
+            h.right = Some(right);
+            (rebalance(h), prev)
+        }
+    }
+}
+
+/// Removes the smallest node below h, returning what is left of h and the removed node
+fn remove_min<K, V>(mut h: Box<AVLNode<K, V>>) -> (Link<K, V>, Box<AVLNode<K, V>>) {
+    match h.left.take() {
+        None => (h.right.take(), h),
+        Some(left) => {
+            let (left, min) = remove_min(left);
+            h.left = left;
+            (Some(rebalance(h)), min)
+        }
+    }
+}
+
+fn remove<K, V, Q>(h: Link<K, V>, key: &Q) -> (Link<K, V>, Option<V>)
+where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+{
+    let Some(mut h) = h else {
+        return (None, None);
+    };
+    match key.cmp(h.key.borrow()) {
+        Ordering::Less => {
+            let (left, value) = remove(h.left.take(), key);
+            h.left = left;
+            (Some(rebalance(h)), value)
+        }
+        Ordering::Greater => {
+            let (right, value) = remove(h.right.take(), key);
+            h.right = right;
+            (Some(rebalance(h)), value)
+        }
+        Ordering::Equal => {
+            let replacement = match (h.left.take(), h.right.take()) {
+                (left, None) => left,
+                (None, right) => right,
+                (left, Some(right)) => {
+                    // replace h with its successor
+                    let (right, mut successor) = remove_min(right);
+                    successor.left = left;
+                    successor.right = right;
+                    Some(rebalance(successor))
+                }
+            };
+            (replacement, Some(h.value))
+        }
+    }
+}
+
+/// Checks the invariants of the subtree at link, whose keys must be in (lo, hi)
+fn check<'a, K: Ord, V>(
+    link: &'a Link<K, V>,
+    lo: Option<&'a K>,
+    hi: Option<&'a K>,
+) -> Result<(), String> {
+    let Some(n) = link else {
+        return Ok(());
+    };
+    if lo.is_some_and(|lo| n.key <= *lo) || hi.is_some_and(|hi| n.key >= *hi) {
+        return Err("keys are out of order".to_string());
+    }
+    check(&n.left, lo, Some(&n.key))?;
+    check(&n.right, Some(&n.key), hi)?;
+    if n.height != 1 + height(&n.left).max(height(&n.right)) {
+        return Err("a node's height is wrong".to_string());
+    }
+    if balance_factor(n).abs() > 1 {
+        return Err("a node's subtrees differ in height by more than one".to_string());
+    }
+    Ok(())
+}
+
+impl<K, V> AVLMap<K, V> {
+    /// Returns the number of nodes on the longest path from the root down
+    pub fn height(&self) -> usize {
+        height(&self.root)
+    }
+
+    /// Returns an iterator over the entries, in increasing order of key
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let mut iter = Iter {
+            stack: Vec::new(),
+            remaining: self.size,
+        };
+        iter.push_left(self.root.as_deref());
+        iter
+    }
+
+    /// Returns an iterator over the entries, in increasing order of key,
+    /// that allows the values to be modified
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        let mut iter = IterMut {
+            stack: Vec::new(),
+            remaining: self.size,
+        };
+        iter.push_left(self.root.as_deref_mut());
+        iter
+    }
+}
+
+impl<K: Ord, V> AVLMap<K, V> {
+    /// Checks that the tree is a valid AVL tree, for testing
+    pub fn check_invariants(&self) -> Result<(), String> {
+        check(&self.root, None, None)
+    }
+}
+
+impl<K, V> IntoIterator for AVLMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    /// Returns an iterator over the entries, in increasing order of key
+    fn into_iter(self) -> Self::IntoIter {
+        let mut storage = Vec::with_capacity(self.size);
+        let mut stack = Vec::new();
+        let mut link = self.root;
+        loop {
+            while let Some(mut node) = link {
+                link = node.left.take();
+                stack.push(node);
+            }
+            let Some(mut node) = stack.pop() else {
+                break;
+            };
+            link = node.right.take();
+            let AVLNode { key, value, .. } = *node;
+            storage.push((key, value));
+        }
+        storage.into_iter()
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    /// nodes whose left subtrees have been visited, but which haven't been visited themselves
+    stack: Vec<&'a AVLNode<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    fn push_left(&mut self, mut node: Option<&'a AVLNode<K, V>>) {
+        while let Some(n) = node {
+            self.stack.push(n);
+            node = n.left.as_deref();
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left(node.right.as_deref());
+        self.remaining -= 1;
+        Some((&node.key, &node.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// A node split into its key, value, and right subtree, so that each can be borrowed separately
+type SplitNode<'a, K, V> = (&'a K, &'a mut V, Option<&'a mut AVLNode<K, V>>);
+
+pub struct IterMut<'a, K, V> {
+    /// like Iter::stack, but without the left subtrees that have already been visited
+    stack: Vec<SplitNode<'a, K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> IterMut<'a, K, V> {
+    fn push_left(&mut self, mut node: Option<&'a mut AVLNode<K, V>>) {
+        while let Some(n) = node {
+            let AVLNode {
+                key,
+                value,
+                left,
+                right,
+                ..
+            } = n;
+            self.stack.push((key, value, right.as_deref_mut()));
+            node = left.as_deref_mut();
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value, right) = self.stack.pop()?;
+        self.push_left(right);
+        self.remaining -= 1;
+        Some((key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a AVLMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut AVLMap<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K: Ord, V> Map61B for AVLMap<K, V> {
+    type Key = K;
+    type Value = V;
+    type Iter<'a>
+        = Iter<'a, K, V>
+    where
+        Self: 'a;
+    type IterMut<'a>
+        = IterMut<'a, K, V>
+    where
+        Self: 'a;
+
+    fn new() -> Self {
+        Self {
+            root: None,
+            size: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.size
+    }
+
+    fn clear(&mut self) {
+        self.size = 0;
+        self.root = None;
+    }
+
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    fn insert(&mut self, key: Self::Key, value: Self::Value) -> Option<Self::Value> {
+        let (root, prev) = insert(self.root.take(), key, value);
+        self.root = Some(root);
+        if prev.is_none() {
+            self.size += 1;
+        }
+        prev
+    }
+
+    fn get<Q>(&self, key: &Q) -> Option<&Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut node = &self.root;
+        while let Some(n) = node {
+            match key.cmp(n.key.borrow()) {
+                Ordering::Less => node = &n.left,
+                Ordering::Equal => return Some(&n.value),
+                Ordering::Greater => node = &n.right,
+            }
+        }
+        None
+    }
+
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut node = &mut self.root;
+        while let Some(n) = node {
+            match key.cmp(n.key.borrow()) {
+                Ordering::Less => node = &mut n.left,
+                Ordering::Equal => return Some(&mut n.value),
+                Ordering::Greater => node = &mut n.right,
+            }
+        }
+        None
+    }
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (root, value) = remove(self.root.take(), key);
+        self.root = root;
+        if value.is_some() {
+            self.size -= 1;
+        }
+        value
+    }
+
+    fn insert_vacant(&mut self, key: Self::Key, value: Self::Value) -> &mut Self::Value {
+        // Rebalancing only rotates nodes on the path down to the new node, so the
+        // new node's ancestors are all on that path. Remember which way the key
+        // goes at each of them, by address, to find the new node after inserting.
+        let mut turns: Vec<(*const AVLNode<K, V>, bool)> = Vec::new();
+        let mut node = &self.root;
+        while let Some(n) = node {
+            let left = key < n.key;
+            turns.push((&**n, left));
+            node = if left { &n.left } else { &n.right };
+        }
+        self.insert(key, value);
+
+        let mut node = self.root.as_mut().unwrap();
+        loop {
+            match turns.iter().find(|(n, _)| std::ptr::eq(*n, &**node)) {
+                Some((_, true)) => node = node.left.as_mut().unwrap(),
+                Some((_, false)) => node = node.right.as_mut().unwrap(),
+                None => return &mut node.value,
+            }
+        }
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        AVLMap::iter(self)
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        AVLMap::iter_mut(self)
+    }
+}
@@ -1,70 +1,102 @@
 use std::{
     collections::{BTreeMap, HashMap},
-    io::{self, Write},
-    time::Instant,
+    panic::{self, AssertUnwindSafe},
+    process,
 };
 
-use lab07::{bstmap::BSTMap, ullmap::ULLMap, Map61B};
+use clap::Parser;
+use map61b_common::{
+    allocator::CountingAllocator,
+    bench::{bench_map, Config, Dist, Op, Row, Sizes},
+    MapUnderTest,
+};
+use rand::{rngs::StdRng, SeedableRng};
 
-fn main() -> io::Result<()> {
-    let stdin = io::stdin();
-    let mut stdout = io::stdout();
+use lab07::{
+    avlmap::AVLMap, bstmap::BSTMap, btreemap61b::BTreeMap61B, llrbmap::LLRBMap, ullmap::ULLMap,
+};
 
-    println!("Welcome to the insertion benchmark!");
-    println!("The algorithm choices are as follows:");
-    println!("  u: ULLMap");
-    println!("  b: BSTMap");
-    println!("  t: std::collections::BTreeMap");
-    println!("  h: std::collections::HashMap");
-    println!("Enter benchmarks in the format \"ordered/random choices num\". For example, to run the random insertion benchmark on all four algorithms with 100 items, enter");
-    println!("random ubth 100");
-    loop {
-        println!();
-        print!("Enter benchmark, or q to quit: ");
-        stdout.flush()?;
-        let mut buffer = String::new();
-        stdin.read_line(&mut buffer)?;
-        if buffer.contains('q') {
-            return Ok(());
-        }
-        let mut tokens = buffer.split_whitespace();
-        let bench = tokens.next();
-        let choices = tokens.next();
-        let num = tokens.next().and_then(|s| s.parse().ok());
-        match (bench, choices, num) {
-            (Some("ordered"), Some(choices), Some(n)) => bench_all(choices, n, true),
-            (Some("random"), Some(choices), Some(n)) => bench_all(choices, n, false),
-            _ => println!("Sorry, I didn't understand that."),
-        }
-    }
+// the labs' maps are benchmarked through Subject, which wraps a Map61B type
+map61b_common::map61b_subject!(lab07::Map61B, tree);
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Parser, Debug)]
+/// Benchmark the Map61B implementations, printing the results as CSV.
+/// After inserting, each map's heap usage is printed too.
+struct Args {
+    /// which maps to benchmark, one letter each:
+    /// u: ULLMap, b: BSTMap, l: LLRBMap, a: AVLMap,
+    /// B: BTreeMap61B, with up to 16 children per node,
+    /// t: std::collections::BTreeMap, h: std::collections::HashMap
+    #[clap(long, default_value = "blaBth")]
+    maps: String,
+    /// which operations to time, separated by commas. Can be insert, get, remove, or mixed.
+    #[clap(long, value_delimiter = ',', default_value = "insert")]
+    ops: Vec<Op>,
+    /// how many keys to use, like 1e5, or a range like 1e3..1e6 to double from the
+    /// first to the second
+    #[clap(long, default_value = "1e3..1e5")]
+    n: Sizes,
+    /// which keys to use. Can be ordered, random, or zipf.
+    #[clap(long, default_value = "random")]
+    dist: Dist,
+    /// how many times to time each operation at each size
+    #[clap(long, default_value_t = 5)]
+    repeat: usize,
+    /// the seed for the random keys, to make runs repeatable
+    #[clap(long)]
+    seed: Option<u64>,
 }
 
-fn bench_all(choices: &str, n: usize, ordered: bool) {
-    for c in choices.chars() {
+fn main() {
+    let args = Args::parse();
+    if args.repeat == 0 {
+        eprintln!("--repeat must be at least 1");
+        process::exit(2);
+    }
+    let config = Config {
+        ops: args.ops,
+        dist: args.dist,
+        sizes: args.n,
+        repeat: args.repeat,
+    };
+    let mut rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    println!("{}", Row::HEADER);
+    for c in args.maps.chars() {
         match c {
-            'u' => bench_single::<ULLMap<usize, usize>>("ULLMap  ", n, ordered),
-            'b' => bench_single::<BSTMap<usize, usize>>("BSTMap  ", n, ordered),
-            't' => bench_single::<BTreeMap<usize, usize>>("BTreeMap", n, ordered),
-            'h' => bench_single::<HashMap<usize, usize>>("HashMap ", n, ordered),
-            _ => {}
+            'u' => bench_single::<Subject<ULLMap<usize, usize>>>("ULLMap", &config, &mut rng),
+            'b' => bench_single::<Subject<BSTMap<usize, usize>>>("BSTMap", &config, &mut rng),
+            'l' => bench_single::<Subject<LLRBMap<usize, usize>>>("LLRBMap", &config, &mut rng),
+            'a' => bench_single::<Subject<AVLMap<usize, usize>>>("AVLMap", &config, &mut rng),
+            'B' => bench_single::<Subject<BTreeMap61B<usize, usize, 16>>>(
+                "BTree61B", &config, &mut rng,
+            ),
+            't' => bench_single::<BTreeMap<usize, usize>>("BTreeMap", &config, &mut rng),
+            'h' => bench_single::<HashMap<usize, usize>>("HashMap", &config, &mut rng),
+            _ => eprintln!("Skipping unknown map {:?}", c),
         }
     }
 }
 
-fn bench_single<T: Map61B<Key = usize, Value = usize>>(name: &str, n: usize, ordered: bool) {
-    let mut map = T::new();
-    let values: Vec<usize> = if ordered {
-        (0..n).collect()
-    } else {
-        (0..n).map(|_| rand::random()).collect()
+/// Runs a map's benchmarks, carrying on with the other maps if it panics,
+/// for example because a method is still unimplemented
+fn bench_single<T: MapUnderTest<usize, usize>>(name: &str, config: &Config, rng: &mut StdRng) {
+    let benchmarks = || {
+        bench_map::<T, _>(
+            name,
+            config,
+            rng,
+            |row| println!("{}", row),
+            |_, _| {},
+            |_| None,
+        )
     };
-    let start = Instant::now();
-    for v in values {
-        map.insert(v, v);
+    if panic::catch_unwind(AssertUnwindSafe(benchmarks)).is_err() {
+        eprintln!("{} panicked, skipping the rest of its benchmarks", name);
     }
-    println!(
-        "{} time taken: {:>12.6} seconds",
-        name,
-        start.elapsed().as_secs_f64()
-    );
 }
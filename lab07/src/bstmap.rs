@@ -1,29 +1,413 @@
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    ops::{Bound, RangeBounds},
+};
+
 use crate::Map61B;
 
+type Link<K, V> = Option<Box<BSTNode<K, V>>>;
+
 struct BSTNode<K, V> {
     key: K,
     value: V,
-    left: Option<Box<BSTNode<K, V>>>,
-    right: Option<Box<BSTNode<K, V>>>,
+    left: Link<K, V>,
+    right: Link<K, V>,
+    /// number of nodes in the subtree rooted at this node
+    size: usize,
 }
 
 pub struct BSTMap<K, V> {
-    root: Option<Box<BSTNode<K, V>>>,
+    root: Link<K, V>,
     size: usize,
 }
 
+fn size<K, V>(link: &Link<K, V>) -> usize {
+    link.as_ref().map_or(0, |n| n.size)
+}
+
+/// Removes the node with the smallest key from the subtree at link
+fn take_min<K, V>(mut link: &mut Link<K, V>) -> Option<Box<BSTNode<K, V>>> {
+    link.as_ref()?;
+    while link.as_ref().unwrap().left.is_some() {
+        let node = link.as_mut().unwrap();
+        node.size -= 1;
+        link = &mut node.left;
+    }
+    let mut min = link.take().unwrap();
+    *link = min.right.take();
+    min.size = 1;
+    Some(min)
+}
+
+/// Builds a perfectly balanced tree from the next n entries, which must be sorted
+fn build<K, V>(entries: &mut impl Iterator<Item = (K, V)>, n: usize) -> Link<K, V> {
+    if n == 0 {
+        return None;
+    }
+    let left = build(entries, n / 2);
+    let (key, value) = entries.next().unwrap();
+    let right = build(entries, n - n / 2 - 1);
+    Some(Box::from(BSTNode {
+        key,
+        value,
+        left,
+        right,
+        size: n,
+    }))
+}
+
+/// Drops nodes one at a time, since dropping a tall tree recursively can overflow the stack
+fn drop_nodes<K, V>(mut stack: Vec<Box<BSTNode<K, V>>>) {
+    while let Some(mut node) = stack.pop() {
+        stack.extend(node.left.take());
+        stack.extend(node.right.take());
+    }
+}
+
+impl<K, V> Drop for BSTMap<K, V> {
+    fn drop(&mut self) {
+        drop_nodes(self.root.take().into_iter().collect());
+    }
+}
+
+impl<K, V> BSTMap<K, V> {
+    /// Returns an iterator over the entries, in increasing order of key
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let mut iter = Iter {
+            stack: Vec::new(),
+            remaining: self.size,
+        };
+        iter.push_left(self.root.as_deref());
+        iter
+    }
+
+    /// Returns an iterator over the entries, in increasing order of key,
+    /// that allows the values to be modified
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        let mut iter = IterMut {
+            stack: Vec::new(),
+            remaining: self.size,
+        };
+        iter.push_left(self.root.as_deref_mut());
+        iter
+    }
+}
+
+impl<K: Ord, V> BSTMap<K, V> {
+    /// Returns the entry with the smallest key
+    pub fn min(&self) -> Option<(&K, &V)> {
+        let mut node = self.root.as_ref()?;
+        while let Some(left) = &node.left {
+            node = left;
+        }
+        Some((&node.key, &node.value))
+    }
+
+    /// Returns the entry with the largest key
+    pub fn max(&self) -> Option<(&K, &V)> {
+        let mut node = self.root.as_ref()?;
+        while let Some(right) = &node.right {
+            node = right;
+        }
+        Some((&node.key, &node.value))
+    }
+
+    /// Returns the entry with the largest key less than or equal to key
+    pub fn floor<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut best = None;
+        let mut node = &self.root;
+        while let Some(n) = node {
+            match key.cmp(n.key.borrow()) {
+                Ordering::Less => node = &n.left,
+                Ordering::Equal => return Some((&n.key, &n.value)),
+                Ordering::Greater => {
+                    best = Some((&n.key, &n.value));
+                    node = &n.right;
+                }
+            }
+        }
+        best
+    }
+
+    /// Returns the entry with the smallest key greater than or equal to key
+    pub fn ceiling<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut best = None;
+        let mut node = &self.root;
+        while let Some(n) = node {
+            match key.cmp(n.key.borrow()) {
+                Ordering::Less => {
+                    best = Some((&n.key, &n.value));
+                    node = &n.left;
+                }
+                Ordering::Equal => return Some((&n.key, &n.value)),
+                Ordering::Greater => node = &n.right,
+            }
+        }
+        best
+    }
+
+    /// Returns the number of keys less than key
+    pub fn rank<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut rank = 0;
+        let mut node = &self.root;
+        while let Some(n) = node {
+            match key.cmp(n.key.borrow()) {
+                Ordering::Less => node = &n.left,
+                Ordering::Equal => return rank + size(&n.left),
+                Ordering::Greater => {
+                    rank += size(&n.left) + 1;
+                    node = &n.right;
+                }
+            }
+        }
+        rank
+    }
+
+    /// Returns the entry whose key has rank i, so select(0) is the smallest
+    pub fn select(&self, mut i: usize) -> Option<(&K, &V)> {
+        let mut node = &self.root;
+        while let Some(n) = node {
+            let left = size(&n.left);
+            match i.cmp(&left) {
+                Ordering::Less => node = &n.left,
+                Ordering::Equal => return Some((&n.key, &n.value)),
+                Ordering::Greater => {
+                    i -= left + 1;
+                    node = &n.right;
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns an iterator over the entries with keys in range, in increasing order of key
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_, K, V, R> {
+        let mut stack = Vec::new();
+        // push the nodes that are at or above the start bound on the way down,
+        // like Iter::push_left does for all nodes
+        let mut node = self.root.as_deref();
+        while let Some(n) = node {
+            let above_start = match range.start_bound() {
+                Bound::Included(start) => n.key >= *start,
+                Bound::Excluded(start) => n.key > *start,
+                Bound::Unbounded => true,
+            };
+            if above_start {
+                stack.push(n);
+                node = n.left.as_deref();
+            } else {
+                node = n.right.as_deref();
+            }
+        }
+        Range {
+            iter: Iter {
+                stack,
+                remaining: self.size,
+            },
+            range,
+        }
+    }
+
+    /// Removes and returns the entry with the smallest key
+    pub fn pop_min(&mut self) -> Option<(K, V)> {
+        let min = take_min(&mut self.root)?;
+        self.size -= 1;
+        let BSTNode { key, value, .. } = *min;
+        Some((key, value))
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    /// nodes whose left subtrees have been visited, but which haven't been visited themselves
+    stack: Vec<&'a BSTNode<K, V>>,
+    /// the number of entries left, or an upper bound on it if this is part of a Range
+    remaining: usize,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    fn push_left(&mut self, mut node: Option<&'a BSTNode<K, V>>) {
+        while let Some(n) = node {
+            self.stack.push(n);
+            node = n.left.as_deref();
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left(node.right.as_deref());
+        self.remaining -= 1;
+        Some((&node.key, &node.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// A node split into its key, value, and right subtree, so that each can be borrowed separately
+type SplitNode<'a, K, V> = (&'a K, &'a mut V, Option<&'a mut BSTNode<K, V>>);
+
+pub struct IterMut<'a, K, V> {
+    /// like Iter::stack, but without the left subtrees that have already been visited,
+    /// so that each value is only borrowed mutably once
+    stack: Vec<SplitNode<'a, K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> IterMut<'a, K, V> {
+    fn push_left(&mut self, mut node: Option<&'a mut BSTNode<K, V>>) {
+        while let Some(n) = node {
+            let BSTNode {
+                key,
+                value,
+                left,
+                right,
+                ..
+            } = n;
+            self.stack.push((key, value, right.as_deref_mut()));
+            node = left.as_deref_mut();
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value, right) = self.stack.pop()?;
+        self.push_left(right);
+        self.remaining -= 1;
+        Some((key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+pub struct IntoIter<K, V> {
+    /// like Iter::stack, but each node's left subtree has been taken out
+    stack: Vec<Box<BSTNode<K, V>>>,
+    remaining: usize,
+}
+
+impl<K, V> IntoIter<K, V> {
+    fn push_left(&mut self, mut link: Link<K, V>) {
+        while let Some(mut node) = link {
+            link = node.left.take();
+            self.stack.push(node);
+        }
+    }
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut node = self.stack.pop()?;
+        self.push_left(node.right.take());
+        self.remaining -= 1;
+        let BSTNode { key, value, .. } = *node;
+        Some((key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> Drop for IntoIter<K, V> {
+    fn drop(&mut self) {
+        drop_nodes(std::mem::take(&mut self.stack));
+    }
+}
+
+pub struct Range<'a, K, V, R> {
+    iter: Iter<'a, K, V>,
+    range: R,
+}
+
+impl<'a, K: Ord, V, R: RangeBounds<K>> Iterator for Range<'a, K, V, R> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.iter.next()?;
+        let below_end = match self.range.end_bound() {
+            Bound::Included(end) => key <= end,
+            Bound::Excluded(end) => key < end,
+            Bound::Unbounded => true,
+        };
+        if below_end {
+            Some((key, value))
+        } else {
+            // everything after key is past the end too
+            self.iter.stack.clear();
+            None
+        }
+    }
+}
+
 impl<K, V> IntoIterator for BSTMap<K, V> {
     type Item = (K, V);
-    type IntoIter = std::vec::IntoIter<(K, V)>;
+    type IntoIter = IntoIter<K, V>;
+
+    /// Returns an iterator over the entries, in increasing order of key
+    fn into_iter(mut self) -> Self::IntoIter {
+        let mut iter = IntoIter {
+            stack: Vec::new(),
+            remaining: self.size,
+        };
+        iter.push_left(self.root.take());
+        iter
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a BSTMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut BSTMap<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
 
     fn into_iter(self) -> Self::IntoIter {
-        unimplemented!()
+        self.iter_mut()
     }
 }
 
 impl<K: Ord, V> Map61B for BSTMap<K, V> {
     type Key = K;
     type Value = V;
+    type Iter<'a>
+        = Iter<'a, K, V>
+    where
+        Self: 'a;
+    type IterMut<'a>
+        = IterMut<'a, K, V>
+    where
+        Self: 'a;
 
     fn new() -> Self {
         BSTMap {
@@ -38,64 +422,147 @@ impl<K: Ord, V> Map61B for BSTMap<K, V> {
 
     fn clear(&mut self) {
         self.size = 0;
-        self.root = None;
+        drop_nodes(self.root.take().into_iter().collect());
     }
 
-    fn contains_key(&self, key: &Self::Key) -> bool {
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         self.get(key).is_some()
     }
 
     fn insert(&mut self, key: Self::Key, value: Self::Value) -> Option<Self::Value> {
         let mut node = &mut self.root;
-
         while let Some(n) = node {
             match key.cmp(&n.key) {
-                std::cmp::Ordering::Less => node = &mut n.left,
-                std::cmp::Ordering::Equal => {
-                    let prev = std::mem::replace(&mut n.value, value);
-                    return Some(prev);
-                }
-                std::cmp::Ordering::Greater => node = &mut n.right,
+                Ordering::Less => node = &mut n.left,
+                Ordering::Equal => return Some(std::mem::replace(&mut n.value, value)),
+                Ordering::Greater => node = &mut n.right,
             }
         }
-        *node = Some(Box::from(BSTNode {
-            key,
-            value,
-            left: None,
-            right: None,
-        }));
-
-        self.size += 1;
+        self.insert_vacant(key, value);
         None
     }
 
-    fn get(&self, key: &Self::Key) -> Option<&Self::Value> {
+    fn get<Q>(&self, key: &Q) -> Option<&Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         let mut node = &self.root;
 
         while let Some(n) = node {
-            match key.cmp(&n.key) {
-                std::cmp::Ordering::Less => node = &n.left,
-                std::cmp::Ordering::Equal => return Some(&n.value),
-                std::cmp::Ordering::Greater => node = &n.right,
+            match key.cmp(n.key.borrow()) {
+                Ordering::Less => node = &n.left,
+                Ordering::Equal => return Some(&n.value),
+                Ordering::Greater => node = &n.right,
             }
         }
         None
     }
 
-    fn get_mut(&mut self, key: &Self::Key) -> Option<&mut Self::Value> {
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         let mut node = &mut self.root;
 
         while let Some(n) = node {
-            match key.cmp(&n.key) {
-                std::cmp::Ordering::Less => node = &mut n.left,
-                std::cmp::Ordering::Equal => return Some(&mut n.value),
-                std::cmp::Ordering::Greater => node = &mut n.right,
+            match key.cmp(n.key.borrow()) {
+                Ordering::Less => node = &mut n.left,
+                Ordering::Equal => return Some(&mut n.value),
+                Ordering::Greater => node = &mut n.right,
             }
         }
         None
     }
 
-    fn remove(&mut self, key: &Self::Key) -> Option<Self::Value> {
-        unimplemented!()
+    fn remove<Q>(&mut self, key: &Q) -> Option<Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        if !self.contains_key(key) {
+            return None;
+        }
+
+        // the key is present, so every node on the way down gets one smaller
+        let mut link = &mut self.root;
+        loop {
+            let ord = key.cmp(link.as_ref().unwrap().key.borrow());
+            if ord == Ordering::Equal {
+                break;
+            }
+            let n = link.as_mut().unwrap();
+            n.size -= 1;
+            link = match ord {
+                Ordering::Less => &mut n.left,
+                _ => &mut n.right,
+            };
+        }
+
+        let mut node = link.take().unwrap();
+        *link = match (node.left.take(), node.right.take()) {
+            (left, None) => left,
+            (None, right) => right,
+            (left, right) => {
+                // Hibbard deletion: replace node with its successor,
+                // the smallest node in its right subtree
+                let mut right = right;
+                let mut successor = take_min(&mut right).unwrap();
+                successor.left = left;
+                successor.right = right;
+                successor.size = node.size - 1;
+                Some(successor)
+            }
+        };
+        self.size -= 1;
+        Some(node.value)
+    }
+
+    fn insert_vacant(&mut self, key: Self::Key, value: Self::Value) -> &mut Self::Value {
+        // the key is new, so every node on the way down gets one bigger
+        let mut node = &mut self.root;
+        while let Some(n) = node {
+            n.size += 1;
+            match key.cmp(&n.key) {
+                Ordering::Less => node = &mut n.left,
+                _ => node = &mut n.right,
+            }
+        }
+        self.size += 1;
+        let node = node.insert(Box::from(BSTNode {
+            key,
+            value,
+            left: None,
+            right: None,
+            size: 1,
+        }));
+        &mut node.value
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        BSTMap::iter(self)
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        BSTMap::iter_mut(self)
+    }
+
+    fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        // putting the sorted entries back one at a time would make a tree as
+        // tall as a linked list, so build a balanced one from them instead
+        let kept: Vec<_> = self
+            .drain()
+            .filter_map(|(k, mut v)| f(&k, &mut v).then_some((k, v)))
+            .collect();
+        self.size = kept.len();
+        self.root = build(&mut kept.into_iter(), self.size);
     }
 }
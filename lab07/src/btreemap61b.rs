@@ -0,0 +1,550 @@
+use std::{borrow::Borrow, cmp::Ordering, iter::Zip, slice};
+
+use crate::Map61B;
+
+struct BTreeNode<K, V> {
+    /// sorted, with values[i] belonging to keys[i]
+    keys: Vec<K>,
+    values: Vec<V>,
+    /// empty for leaves, otherwise one more than keys. Every key in children[i]
+    /// is between keys[i - 1] and keys[i].
+    children: Vec<BTreeNode<K, V>>,
+}
+
+impl<K, V> BTreeNode<K, V> {
+    fn new() -> Self {
+        Self {
+            keys: Vec::new(),
+            values: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+}
+
+/// Where an entry is in the tree: the index of the child to follow at each level,
+/// from the bottom up, and then the index of the entry in the last node
+struct Location {
+    children: Vec<usize>,
+    index: usize,
+}
+
+impl Location {
+    /// Updates a location below node.children[i], where i is the last child index,
+    /// after that child is split around its key mid by split_child
+    fn split(&mut self, mid: usize) {
+        let i = self.children.pop().unwrap();
+        match self.children.last_mut() {
+            None => match self.index.cmp(&mid) {
+                Ordering::Less => self.children.push(i),
+                // the entry moved up into node
+                Ordering::Equal => self.index = i,
+                Ordering::Greater => {
+                    self.index -= mid + 1;
+                    self.children.push(i + 1);
+                }
+            },
+            Some(c) if *c <= mid => self.children.push(i),
+            Some(c) => {
+                *c -= mid + 1;
+                self.children.push(i + 1);
+            }
+        }
+    }
+}
+
+/*
+A B-tree where each node has at most ORDER children, so ORDER = 4 is a 2-3-4 tree.
+Keys are kept sorted in small arrays, so a lookup does a few binary searches
+over contiguous memory instead of following a pointer per comparison.
+Every node but the root has at least ceil(ORDER / 2) - 1 keys, and all leaves
+are at the same depth. Nodes that grow too big are split in two, and nodes that
+shrink too small borrow a key from a sibling or are merged with one.
+*/
+pub struct BTreeMap61B<K, V, const ORDER: usize> {
+    root: BTreeNode<K, V>,
+    size: usize,
+}
+
+impl<K, V, const ORDER: usize> BTreeMap61B<K, V, ORDER> {
+    const MAX_KEYS: usize = ORDER - 1;
+    const MIN_KEYS: usize = ORDER.div_ceil(2) - 1;
+
+    /// Returns the number of nodes on the path from the root to any leaf
+    pub fn height(&self) -> usize {
+        if self.size == 0 {
+            return 0;
+        }
+        let mut height = 1;
+        let mut node = &self.root;
+        while let Some(child) = node.children.first() {
+            node = child;
+            height += 1;
+        }
+        height
+    }
+
+    /// Returns an iterator over the entries, in increasing order of key
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left(&self.root);
+        iter
+    }
+
+    /// Returns an iterator over the entries, in increasing order of key,
+    /// that allows the values to be modified
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        let mut iter = IterMut { stack: Vec::new() };
+        iter.push_left(&mut self.root);
+        iter
+    }
+}
+
+impl<K: Ord, V, const ORDER: usize> BTreeMap61B<K, V, ORDER> {
+    /// Inserts into the subtree at node, which may be left with MAX_KEYS + 1 keys
+    fn insert_into(node: &mut BTreeNode<K, V>, key: K, value: V) -> Option<V> {
+        let i = match node.keys.binary_search(&key) {
+            Ok(i) => return Some(std::mem::replace(&mut node.values[i], value)),
+            Err(i) => i,
+        };
+        if node.is_leaf() {
+            node.keys.insert(i, key);
+            node.values.insert(i, value);
+            return None;
+        }
+        let prev = Self::insert_into(&mut node.children[i], key, value);
+        if node.children[i].keys.len() > Self::MAX_KEYS {
+            Self::split_child(node, i);
+        }
+        prev
+    }
+
+    /// Inserts a key that isn't in the subtree at node, like insert_into, and returns
+    /// where it is after any splits below node
+    fn insert_new(node: &mut BTreeNode<K, V>, key: K, value: V) -> Location {
+        let i = node.keys.binary_search(&key).unwrap_err();
+        if node.is_leaf() {
+            node.keys.insert(i, key);
+            node.values.insert(i, value);
+            return Location {
+                children: Vec::new(),
+                index: i,
+            };
+        }
+        let mut location = Self::insert_new(&mut node.children[i], key, value);
+        location.children.push(i);
+        if node.children[i].keys.len() > Self::MAX_KEYS {
+            location.split(node.children[i].keys.len() / 2);
+            Self::split_child(node, i);
+        }
+        location
+    }
+
+    /// Splits node.children[i], which has one key too many, around its middle key,
+    /// which moves up into node
+    fn split_child(node: &mut BTreeNode<K, V>, i: usize) {
+        let child = &mut node.children[i];
+        let mid = child.keys.len() / 2;
+        let right = BTreeNode {
+            keys: child.keys.split_off(mid + 1),
+            values: child.values.split_off(mid + 1),
+            children: if child.is_leaf() {
+                Vec::new()
+            } else {
+                child.children.split_off(mid + 1)
+            },
+        };
+        let key = child.keys.pop().unwrap();
+        let value = child.values.pop().unwrap();
+        node.keys.insert(i, key);
+        node.values.insert(i, value);
+        node.children.insert(i + 1, right);
+    }
+
+    /// Removes from the subtree at node, which may be left with MIN_KEYS - 1 keys
+    fn remove_from<Q>(node: &mut BTreeNode<K, V>, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (i, value) = match node.keys.binary_search_by(|k| k.borrow().cmp(key)) {
+            Ok(i) if node.is_leaf() => {
+                node.keys.remove(i);
+                return Some(node.values.remove(i));
+            }
+            Ok(i) => {
+                // replace the entry with its predecessor, the largest entry to its left
+                let (k, v) = Self::remove_max(&mut node.children[i]);
+                node.keys[i] = k;
+                (i, std::mem::replace(&mut node.values[i], v))
+            }
+            Err(_) if node.is_leaf() => return None,
+            Err(i) => (i, Self::remove_from(&mut node.children[i], key)?),
+        };
+        if node.children[i].keys.len() < Self::MIN_KEYS {
+            Self::fix_child(node, i);
+        }
+        Some(value)
+    }
+
+    /// Removes the largest entry from the subtree at node, which may be left with
+    /// MIN_KEYS - 1 keys
+    fn remove_max(node: &mut BTreeNode<K, V>) -> (K, V) {
+        if node.is_leaf() {
+            return (node.keys.pop().unwrap(), node.values.pop().unwrap());
+        }
+        let last = node.children.len() - 1;
+        let entry = Self::remove_max(&mut node.children[last]);
+        if node.children[last].keys.len() < Self::MIN_KEYS {
+            Self::fix_child(node, last);
+        }
+        entry
+    }
+
+    /// Gives node.children[i], which has one key too few, a key from a sibling,
+    /// or merges it with a sibling if neither has a key to spare
+    fn fix_child(node: &mut BTreeNode<K, V>, i: usize) {
+        if i > 0 && node.children[i - 1].keys.len() > Self::MIN_KEYS {
+            // rotate right: the separator moves down, and the left sibling's last key moves up
+            let (left, right) = node.children.split_at_mut(i);
+            let (left, child) = (&mut left[i - 1], &mut right[0]);
+            let key = std::mem::replace(&mut node.keys[i - 1], left.keys.pop().unwrap());
+            let value = std::mem::replace(&mut node.values[i - 1], left.values.pop().unwrap());
+            child.keys.insert(0, key);
+            child.values.insert(0, value);
+            if let Some(grandchild) = left.children.pop() {
+                child.children.insert(0, grandchild);
+            }
+        } else if i + 1 < node.children.len() && node.children[i + 1].keys.len() > Self::MIN_KEYS {
+            // rotate left: the separator moves down, and the right sibling's first key moves up
+            let (left, right) = node.children.split_at_mut(i + 1);
+            let (child, right) = (&mut left[i], &mut right[0]);
+            let key = std::mem::replace(&mut node.keys[i], right.keys.remove(0));
+            let value = std::mem::replace(&mut node.values[i], right.values.remove(0));
+            child.keys.push(key);
+            child.values.push(value);
+            if !right.is_leaf() {
+                child.children.push(right.children.remove(0));
+            }
+        } else {
+            // merge the child with a sibling and the separator between them
+            let i = if i > 0 { i - 1 } else { i };
+            let right = node.children.remove(i + 1);
+            let left = &mut node.children[i];
+            left.keys.push(node.keys.remove(i));
+            left.values.push(node.values.remove(i));
+            left.keys.extend(right.keys);
+            left.values.extend(right.values);
+            left.children.extend(right.children);
+        }
+    }
+
+    /// Checks that the tree is a valid B-tree, for testing
+    pub fn check_invariants(&self) -> Result<(), String> {
+        /// returns the depth of the leaves below node
+        fn check<K: Ord, V, const ORDER: usize>(
+            node: &BTreeNode<K, V>,
+            lo: Option<&K>,
+            hi: Option<&K>,
+            is_root: bool,
+        ) -> Result<usize, String> {
+            let len = node.keys.len();
+            if len != node.values.len() {
+                return Err("a node has different numbers of keys and values".to_string());
+            }
+            if len > BTreeMap61B::<K, V, ORDER>::MAX_KEYS {
+                return Err("a node has too many keys".to_string());
+            }
+            if !is_root && len < BTreeMap61B::<K, V, ORDER>::MIN_KEYS {
+                return Err("a node has too few keys".to_string());
+            }
+            if !node.keys.windows(2).all(|w| w[0] < w[1])
+                || lo.is_some_and(|lo| node.keys.first().is_some_and(|k| k <= lo))
+                || hi.is_some_and(|hi| node.keys.last().is_some_and(|k| k >= hi))
+            {
+                return Err("keys are out of order".to_string());
+            }
+            if node.is_leaf() {
+                return Ok(1);
+            }
+            if node.children.len() != len + 1 {
+                return Err("a node has the wrong number of children".to_string());
+            }
+            let mut depth = None;
+            for (j, child) in node.children.iter().enumerate() {
+                let lo = if j == 0 { lo } else { Some(&node.keys[j - 1]) };
+                let hi = node.keys.get(j).or(hi);
+                let d = check::<K, V, ORDER>(child, lo, hi, false)?;
+                if depth.is_some_and(|depth| depth != d) {
+                    return Err("leaves are at different depths".to_string());
+                }
+                depth = Some(d);
+            }
+            Ok(depth.unwrap() + 1)
+        }
+
+        check::<K, V, ORDER>(&self.root, None, None, true)?;
+        if self.size == 0 && !self.root.keys.is_empty() {
+            return Err("an empty map has keys".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    /// the nodes on the path to the next entry, and the index of the next key in each
+    stack: Vec<(&'a BTreeNode<K, V>, usize)>,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    fn push_left(&mut self, mut node: &'a BTreeNode<K, V>) {
+        loop {
+            self.stack.push((node, 0));
+            match node.children.first() {
+                Some(child) => node = child,
+                None => break,
+            }
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node, i) = self.stack.last_mut()?;
+            let node = *node;
+            if *i == node.keys.len() {
+                self.stack.pop();
+                continue;
+            }
+            let item = (&node.keys[*i], &node.values[*i]);
+            *i += 1;
+            if let Some(child) = node.children.get(*i) {
+                self.push_left(child);
+            }
+            return Some(item);
+        }
+    }
+}
+
+/// A node split into its entries and its children, so that each can be borrowed separately
+type SplitNode<'a, K, V> = (
+    Zip<slice::Iter<'a, K>, slice::IterMut<'a, V>>,
+    slice::IterMut<'a, BTreeNode<K, V>>,
+);
+
+pub struct IterMut<'a, K, V> {
+    /// like Iter::stack, but with the entries and children left to visit in each node
+    stack: Vec<SplitNode<'a, K, V>>,
+}
+
+impl<'a, K, V> IterMut<'a, K, V> {
+    fn push_left(&mut self, mut node: &'a mut BTreeNode<K, V>) {
+        loop {
+            let BTreeNode {
+                keys,
+                values,
+                children,
+            } = node;
+            let mut children = children.iter_mut();
+            let first = children.next();
+            self.stack
+                .push((keys.iter().zip(values.iter_mut()), children));
+            match first {
+                Some(child) => node = child,
+                None => break,
+            }
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (entries, children) = self.stack.last_mut()?;
+            let Some(item) = entries.next() else {
+                self.stack.pop();
+                continue;
+            };
+            if let Some(child) = children.next() {
+                self.push_left(child);
+            }
+            return Some(item);
+        }
+    }
+}
+
+impl<'a, K, V, const ORDER: usize> IntoIterator for &'a BTreeMap61B<K, V, ORDER> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V, const ORDER: usize> IntoIterator for &'a mut BTreeMap61B<K, V, ORDER> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K, V, const ORDER: usize> IntoIterator for BTreeMap61B<K, V, ORDER> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    /// Returns an iterator over the entries, in increasing order of key
+    fn into_iter(self) -> Self::IntoIter {
+        fn drain<K, V>(node: BTreeNode<K, V>, storage: &mut Vec<(K, V)>) {
+            let mut children = node.children.into_iter();
+            for entry in node.keys.into_iter().zip(node.values) {
+                if let Some(child) = children.next() {
+                    drain(child, storage);
+                }
+                storage.push(entry);
+            }
+            if let Some(child) = children.next() {
+                drain(child, storage);
+            }
+        }
+
+        let mut storage = Vec::with_capacity(self.size);
+        drain(self.root, &mut storage);
+        storage.into_iter()
+    }
+}
+
+impl<K: Ord, V, const ORDER: usize> Map61B for BTreeMap61B<K, V, ORDER> {
+    type Key = K;
+    type Value = V;
+    type Iter<'a>
+        = Iter<'a, K, V>
+    where
+        Self: 'a;
+    type IterMut<'a>
+        = IterMut<'a, K, V>
+    where
+        Self: 'a;
+
+    fn new() -> Self {
+        const {
+            assert!(
+                ORDER >= 3,
+                "a B-tree node needs room for at least 3 children"
+            )
+        };
+        Self {
+            root: BTreeNode::new(),
+            size: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.size
+    }
+
+    fn clear(&mut self) {
+        self.size = 0;
+        self.root = BTreeNode::new();
+    }
+
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    fn insert(&mut self, key: Self::Key, value: Self::Value) -> Option<Self::Value> {
+        let prev = Self::insert_into(&mut self.root, key, value);
+        if self.root.keys.len() > Self::MAX_KEYS {
+            // the tree grows at the root, so all leaves stay at the same depth
+            let old_root = std::mem::replace(&mut self.root, BTreeNode::new());
+            self.root.children.push(old_root);
+            Self::split_child(&mut self.root, 0);
+        }
+        if prev.is_none() {
+            self.size += 1;
+        }
+        prev
+    }
+
+    fn get<Q>(&self, key: &Q) -> Option<&Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut node = &self.root;
+        loop {
+            match node.keys.binary_search_by(|k| k.borrow().cmp(key)) {
+                Ok(i) => return Some(&node.values[i]),
+                Err(i) => node = node.children.get(i)?,
+            }
+        }
+    }
+
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut node = &mut self.root;
+        loop {
+            match node.keys.binary_search_by(|k| k.borrow().cmp(key)) {
+                Ok(i) => return Some(&mut node.values[i]),
+                Err(i) => node = node.children.get_mut(i)?,
+            }
+        }
+    }
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let value = Self::remove_from(&mut self.root, key)?;
+        if self.root.keys.is_empty() && !self.root.is_leaf() {
+            // the tree shrinks at the root
+            self.root = self.root.children.pop().unwrap();
+        }
+        self.size -= 1;
+        Some(value)
+    }
+
+    fn insert_vacant(&mut self, key: Self::Key, value: Self::Value) -> &mut Self::Value {
+        let mut location = Self::insert_new(&mut self.root, key, value);
+        if self.root.keys.len() > Self::MAX_KEYS {
+            let old_root = std::mem::replace(&mut self.root, BTreeNode::new());
+            self.root.children.push(old_root);
+            location.children.push(0);
+            location.split(self.root.children[0].keys.len() / 2);
+            Self::split_child(&mut self.root, 0);
+        }
+        self.size += 1;
+
+        let mut node = &mut self.root;
+        while let Some(i) = location.children.pop() {
+            node = &mut node.children[i];
+        }
+        &mut node.values[location.index]
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        BTreeMap61B::iter(self)
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        BTreeMap61B::iter_mut(self)
+    }
+}
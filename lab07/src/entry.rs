@@ -0,0 +1,5 @@
+/*
+A key's place in a map, returned by Map61B::entry. The types are shared with the
+other labs, in map61b_common/src/entry.rs.
+*/
+map61b_common::map61b_entry!(crate::Map61B);
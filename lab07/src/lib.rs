@@ -1,12 +1,31 @@
+use std::borrow::Borrow;
+
+use entry::{Entry, OccupiedEntry, VacantEntry};
+
+pub mod avlmap;
 pub mod bstmap;
+pub mod btreemap61b;
+pub mod entry;
+pub mod llrbmap;
 mod std_types;
 #[cfg(test)]
 mod tests;
 pub mod ullmap;
 
+/*
+Lookups take any borrowed form of the key, like a &str for a map with String keys,
+as long as it compares the same way as the key. The maps here compare keys with
+Ord rather than hashing them, so that's all lookups need, like BTreeMap's.
+*/
 pub trait Map61B: IntoIterator<Item = (Self::Key, Self::Value)> {
     type Key;
     type Value;
+    type Iter<'a>: Iterator<Item = (&'a Self::Key, &'a Self::Value)>
+    where
+        Self: 'a;
+    type IterMut<'a>: Iterator<Item = (&'a Self::Key, &'a mut Self::Value)>
+    where
+        Self: 'a;
 
     fn new() -> Self;
     fn len(&self) -> usize;
@@ -14,12 +33,82 @@ pub trait Map61B: IntoIterator<Item = (Self::Key, Self::Value)> {
         self.len() == 0
     }
     fn clear(&mut self);
-    fn contains_key(&self, key: &Self::Key) -> bool;
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Self::Key: Borrow<Q>,
+        Q: Ord + ?Sized;
 
     fn insert(&mut self, key: Self::Key, value: Self::Value) -> Option<Self::Value>;
 
-    fn get(&self, key: &Self::Key) -> Option<&Self::Value>;
-    fn get_mut(&mut self, key: &Self::Key) -> Option<&mut Self::Value>;
+    fn get<Q>(&self, key: &Q) -> Option<&Self::Value>
+    where
+        Self::Key: Borrow<Q>,
+        Q: Ord + ?Sized;
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Self::Value>
+    where
+        Self::Key: Borrow<Q>,
+        Q: Ord + ?Sized;
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<Self::Value>
+    where
+        Self::Key: Borrow<Q>,
+        Q: Ord + ?Sized;
+
+    /// Inserts a key that isn't in the map yet, returning a reference to its value.
+    /// This is what VacantEntry::insert uses.
+    fn insert_vacant(&mut self, key: Self::Key, value: Self::Value) -> &mut Self::Value;
+
+    /// Returns the entry for key, to look at or change its value in place.
+    /// This walks down the tree twice, with contains_key and get_mut, and a vacant
+    /// key is walked to a third time when the entry is filled in.
+    fn entry(&mut self, key: Self::Key) -> Entry<'_, Self>
+    where
+        Self::Key: Ord,
+    {
+        // the borrow checker won't let one walk down the tree end with the
+        // node's value in one branch and the map in the other, and unlike a
+        // hashmap's bucket, a node has no position to find first instead
+        if self.contains_key(&key) {
+            let value = self.get_mut(&key).unwrap();
+            Entry::Occupied(OccupiedEntry { key, value })
+        } else {
+            Entry::Vacant(VacantEntry { key, map: self })
+        }
+    }
+
+    /// Returns an iterator over the entries, in the same order as into_iter
+    fn iter(&self) -> Self::Iter<'_>;
+    /// Returns an iterator over the entries that allows the values to be modified
+    fn iter_mut(&mut self) -> Self::IterMut<'_>;
 
-    fn remove(&mut self, key: &Self::Key) -> Option<Self::Value>;
+    fn keys(&self) -> impl Iterator<Item = &Self::Key> {
+        self.iter().map(|(k, _)| k)
+    }
+    fn values(&self) -> impl Iterator<Item = &Self::Value> {
+        self.iter().map(|(_, v)| v)
+    }
+    fn values_mut(&mut self) -> impl Iterator<Item = &mut Self::Value> {
+        self.iter_mut().map(|(_, v)| v)
+    }
+
+    /// Removes every entry, and returns them in the order of into_iter
+    fn drain(&mut self) -> Self::IntoIter
+    where
+        Self: Sized,
+    {
+        std::mem::replace(self, Self::new()).into_iter()
+    }
+
+    /// Removes every entry for which f returns false
+    fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Self::Key, &mut Self::Value) -> bool,
+        Self: Sized,
+    {
+        for (key, mut value) in self.drain() {
+            if f(&key, &mut value) {
+                self.insert_vacant(key, value);
+            }
+        }
+    }
 }
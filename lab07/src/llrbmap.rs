@@ -0,0 +1,491 @@
+use std::{borrow::Borrow, cmp::Ordering};
+
+use crate::Map61B;
+
+type Link<K, V> = Option<Box<LLRBNode<K, V>>>;
+
+struct LLRBNode<K, V> {
+    key: K,
+    value: V,
+    left: Link<K, V>,
+    right: Link<K, V>,
+    /// whether the link from this node's parent to it is red
+    red: bool,
+}
+
+/*
+A left-leaning red-black tree, as described in Sedgewick's Algorithms.
+It is a binary search tree that mirrors a 2-3 tree: a node joined to its left
+child by a red link is one 3-node of the 2-3 tree. Red links only lean left,
+no node has two red links, and every path from the root to a missing child
+crosses the same number of black links, so the height is at most 2 log2(n).
+*/
+pub struct LLRBMap<K, V> {
+    root: Link<K, V>,
+    size: usize,
+}
+
+fn is_red<K, V>(link: &Link<K, V>) -> bool {
+    link.as_ref().is_some_and(|n| n.red)
+}
+
+/// Turns a right-leaning red link into a left-leaning one
+fn rotate_left<K, V>(mut h: Box<LLRBNode<K, V>>) -> Box<LLRBNode<K, V>> {
+    let mut x = h.right.take().unwrap();
+    h.right = x.left.take();
+    x.red = h.red;
+    h.red = true;
+    x.left = Some(h);
+    x
+}
+
+/// Turns a left-leaning red link into a right-leaning one
+fn rotate_right<K, V>(mut h: Box<LLRBNode<K, V>>) -> Box<LLRBNode<K, V>> {
+    let mut x = h.left.take().unwrap();
+    h.left = x.right.take();
+    x.red = h.red;
+    h.red = true;
+    x.right = Some(h);
+    x
+}
+
+/// Splits a temporary 4-node, or joins h and its children into one
+fn flip_colors<K, V>(h: &mut LLRBNode<K, V>) {
+    h.red = !h.red;
+    for child in [&mut h.left, &mut h.right].into_iter().flatten() {
+        child.red = !child.red;
+    }
+}
+
+/// Makes h.left or one of its children red, assuming h is red and h.left and
+/// h.left.left are black
+fn move_red_left<K, V>(mut h: Box<LLRBNode<K, V>>) -> Box<LLRBNode<K, V>> {
+    flip_colors(&mut h);
+    if is_red(&h.right.as_ref().unwrap().left) {
+        h.right = h.right.take().map(rotate_right);
+        h = rotate_left(h);
+        flip_colors(&mut h);
+    }
+    h
+}
+
+/// Makes h.right or one of its children red, assuming h is red and h.right and
+/// h.right.left are black
+fn move_red_right<K, V>(mut h: Box<LLRBNode<K, V>>) -> Box<LLRBNode<K, V>> {
+    flip_colors(&mut h);
+    if is_red(&h.left.as_ref().unwrap().left) {
+        h = rotate_right(h);
+        flip_colors(&mut h);
+    }
+    h
+}
+
+/// Restores the invariants on the way back up from an insertion or removal below h
+fn balance<K, V>(mut h: Box<LLRBNode<K, V>>) -> Box<LLRBNode<K, V>> {
+    if is_red(&h.right) && !is_red(&h.left) {
+        h = rotate_left(h);
+    }
+    if is_red(&h.left) && is_red(&h.left.as_ref().unwrap().left) {
+        h = rotate_right(h);
+    }
+    if is_red(&h.left) && is_red(&h.right) {
+        flip_colors(&mut h);
+    }
+    h
+}
+
+fn insert<K: Ord, V>(h: Link<K, V>, key: K, value: V) -> (Box<LLRBNode<K, V>>, Option<V>) {
+    let Some(mut h) = h else {
+        let node = LLRBNode {
+            key,
+            value,
+            left: None,
+            right: None,
+            red: true,
+        };
+        return (Box::new(node), None);
+    };
+    let prev = match key.cmp(&h.key) {
+        Ordering::Less => {
+            let (left, prev) = insert(h.left.take(), key, value);
+            h.left = Some(left);
+            prev
+        }
+        Ordering::Equal => Some(std::mem::replace(&mut h.value, value)),
+        Ordering::Greater => {
+            let (right, prev) = insert(h.right.take(), key, value);
+            h.right = Some(right);
+            prev
+        }
+    };
+    (balance(h), prev)
+}
+
+/// Removes the smallest node below h, returning what is left of h and the removed node
+fn remove_min<K, V>(mut h: Box<LLRBNode<K, V>>) -> (Link<K, V>, Box<LLRBNode<K, V>>) {
+    if h.left.is_none() {
+        // h has no right child either, since red links lean left
+        return (None, h);
+    }
+    if !is_red(&h.left) && !is_red(&h.left.as_ref().unwrap().left) {
+        h = move_red_left(h);
+    }
+    let (left, min) = remove_min(h.left.take().unwrap());
+    h.left = left;
+    (Some(balance(h)), min)
+}
+
+/// Removes key, which must be below h, returning what is left of h and key's value.
+/// On the way down, h is always red or has a red left child, so the removed node
+/// is never a 2-node and removing it keeps the tree balanced.
+fn remove<K, V, Q>(mut h: Box<LLRBNode<K, V>>, key: &Q) -> (Link<K, V>, V)
+where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+{
+    if key < h.key.borrow() {
+        if !is_red(&h.left) && !is_red(&h.left.as_ref().unwrap().left) {
+            h = move_red_left(h);
+        }
+        let (left, value) = remove(h.left.take().unwrap(), key);
+        h.left = left;
+        return (Some(balance(h)), value);
+    }
+
+    if is_red(&h.left) {
+        h = rotate_right(h);
+    }
+    if key == h.key.borrow() && h.right.is_none() {
+        // h has no left child either
+        return (None, h.value);
+    }
+    if !is_red(&h.right) && !is_red(&h.right.as_ref().unwrap().left) {
+        h = move_red_right(h);
+    }
+    let value = if key == h.key.borrow() {
+        // replace h's entry with its successor's
+        let (right, min) = remove_min(h.right.take().unwrap());
+        h.right = right;
+        h.key = min.key;
+        std::mem::replace(&mut h.value, min.value)
+    } else {
+        let (right, value) = remove(h.right.take().unwrap(), key);
+        h.right = right;
+        value
+    };
+    (Some(balance(h)), value)
+}
+
+/// Checks the invariants of the subtree at link, whose keys must be in (lo, hi),
+/// returning the number of black links on every path down from it
+fn check<'a, K: Ord, V>(
+    link: &'a Link<K, V>,
+    lo: Option<&'a K>,
+    hi: Option<&'a K>,
+) -> Result<usize, String> {
+    let Some(n) = link else {
+        return Ok(0);
+    };
+    if lo.is_some_and(|lo| n.key <= *lo) || hi.is_some_and(|hi| n.key >= *hi) {
+        return Err("keys are out of order".to_string());
+    }
+    if is_red(&n.right) {
+        return Err("a red link leans right".to_string());
+    }
+    if n.red && is_red(&n.left) {
+        return Err("two red links in a row".to_string());
+    }
+    let left = check(&n.left, lo, Some(&n.key))?;
+    let right = check(&n.right, Some(&n.key), hi)?;
+    if left != right {
+        return Err("paths have different numbers of black links".to_string());
+    }
+    Ok(left + !n.red as usize)
+}
+
+impl<K, V> LLRBMap<K, V> {
+    /// Returns the number of nodes on the longest path from the root down
+    pub fn height(&self) -> usize {
+        fn height<K, V>(link: &Link<K, V>) -> usize {
+            link.as_ref()
+                .map_or(0, |n| 1 + height(&n.left).max(height(&n.right)))
+        }
+        height(&self.root)
+    }
+
+    /// Returns an iterator over the entries, in increasing order of key
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let mut iter = Iter {
+            stack: Vec::new(),
+            remaining: self.size,
+        };
+        iter.push_left(self.root.as_deref());
+        iter
+    }
+
+    /// Returns an iterator over the entries, in increasing order of key,
+    /// that allows the values to be modified
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        let mut iter = IterMut {
+            stack: Vec::new(),
+            remaining: self.size,
+        };
+        iter.push_left(self.root.as_deref_mut());
+        iter
+    }
+}
+
+impl<K: Ord, V> LLRBMap<K, V> {
+    /// Checks that the tree is a valid left-leaning red-black tree, for testing
+    pub fn check_invariants(&self) -> Result<(), String> {
+        if is_red(&self.root) {
+            return Err("the root is red".to_string());
+        }
+        check(&self.root, None, None)?;
+        Ok(())
+    }
+}
+
+impl<K, V> IntoIterator for LLRBMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    /// Returns an iterator over the entries, in increasing order of key
+    fn into_iter(self) -> Self::IntoIter {
+        let mut storage = Vec::with_capacity(self.size);
+        let mut stack = Vec::new();
+        let mut link = self.root;
+        loop {
+            while let Some(mut node) = link {
+                link = node.left.take();
+                stack.push(node);
+            }
+            let Some(mut node) = stack.pop() else {
+                break;
+            };
+            link = node.right.take();
+            let LLRBNode { key, value, .. } = *node;
+            storage.push((key, value));
+        }
+        storage.into_iter()
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    /// nodes whose left subtrees have been visited, but which haven't been visited themselves
+    stack: Vec<&'a LLRBNode<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    fn push_left(&mut self, mut node: Option<&'a LLRBNode<K, V>>) {
+        while let Some(n) = node {
+            self.stack.push(n);
+            node = n.left.as_deref();
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left(node.right.as_deref());
+        self.remaining -= 1;
+        Some((&node.key, &node.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// A node split into its key, value, and right subtree, so that each can be borrowed separately
+type SplitNode<'a, K, V> = (&'a K, &'a mut V, Option<&'a mut LLRBNode<K, V>>);
+
+pub struct IterMut<'a, K, V> {
+    /// like Iter::stack, but without the left subtrees that have already been visited
+    stack: Vec<SplitNode<'a, K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> IterMut<'a, K, V> {
+    fn push_left(&mut self, mut node: Option<&'a mut LLRBNode<K, V>>) {
+        while let Some(n) = node {
+            let LLRBNode {
+                key,
+                value,
+                left,
+                right,
+                ..
+            } = n;
+            self.stack.push((key, value, right.as_deref_mut()));
+            node = left.as_deref_mut();
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value, right) = self.stack.pop()?;
+        self.push_left(right);
+        self.remaining -= 1;
+        Some((key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a LLRBMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut LLRBMap<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K: Ord, V> Map61B for LLRBMap<K, V> {
+    type Key = K;
+    type Value = V;
+    type Iter<'a>
+        = Iter<'a, K, V>
+    where
+        Self: 'a;
+    type IterMut<'a>
+        = IterMut<'a, K, V>
+    where
+        Self: 'a;
+
+    fn new() -> Self {
+        Self {
+            root: None,
+            size: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.size
+    }
+
+    fn clear(&mut self) {
+        self.size = 0;
+        self.root = None;
+    }
+
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    fn insert(&mut self, key: Self::Key, value: Self::Value) -> Option<Self::Value> {
+        let (mut root, prev) = insert(self.root.take(), key, value);
+        root.red = false;
+        self.root = Some(root);
+        if prev.is_none() {
+            self.size += 1;
+        }
+        prev
+    }
+
+    fn get<Q>(&self, key: &Q) -> Option<&Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut node = &self.root;
+        while let Some(n) = node {
+            match key.cmp(n.key.borrow()) {
+                Ordering::Less => node = &n.left,
+                Ordering::Equal => return Some(&n.value),
+                Ordering::Greater => node = &n.right,
+            }
+        }
+        None
+    }
+
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut node = &mut self.root;
+        while let Some(n) = node {
+            match key.cmp(n.key.borrow()) {
+                Ordering::Less => node = &mut n.left,
+                Ordering::Equal => return Some(&mut n.value),
+                Ordering::Greater => node = &mut n.right,
+            }
+        }
+        None
+    }
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        if !self.contains_key(key) {
+            return None;
+        }
+        let mut root = self.root.take().unwrap();
+        if !is_red(&root.left) && !is_red(&root.right) {
+            root.red = true;
+        }
+        let (mut root, value) = remove(root, key);
+        if let Some(root) = &mut root {
+            root.red = false;
+        }
+        self.root = root;
+        self.size -= 1;
+        Some(value)
+    }
+
+    fn insert_vacant(&mut self, key: Self::Key, value: Self::Value) -> &mut Self::Value {
+        // Rebalancing only rotates nodes on the path down to the new node, so the
+        // new node's ancestors are all on that path. Remember which way the key
+        // goes at each of them, by address, to find the new node after inserting.
+        let mut turns: Vec<(*const LLRBNode<K, V>, bool)> = Vec::new();
+        let mut node = &self.root;
+        while let Some(n) = node {
+            let left = key < n.key;
+            turns.push((&**n, left));
+            node = if left { &n.left } else { &n.right };
+        }
+        self.insert(key, value);
+
+        let mut node = self.root.as_mut().unwrap();
+        loop {
+            match turns.iter().find(|(n, _)| std::ptr::eq(*n, &**node)) {
+                Some((_, true)) => node = node.left.as_mut().unwrap(),
+                Some((_, false)) => node = node.right.as_mut().unwrap(),
+                None => return &mut node.value,
+            }
+        }
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        LLRBMap::iter(self)
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        LLRBMap::iter_mut(self)
+    }
+}
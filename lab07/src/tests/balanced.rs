@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+
+use rand::Rng;
+
+use crate::{avlmap::AVLMap, btreemap61b::BTreeMap61B, llrbmap::LLRBMap, Map61B};
+
+/// The operations that the balanced tree tests need besides Map61B
+trait Balanced: Map61B<Key = u32, Value = u32> {
+    fn height(&self) -> usize;
+    fn check_invariants(&self) -> Result<(), String>;
+    /// the tallest that a valid tree with n entries can be
+    fn max_height(n: usize) -> usize;
+}
+
+impl Balanced for LLRBMap<u32, u32> {
+    fn height(&self) -> usize {
+        self.height()
+    }
+
+    fn check_invariants(&self) -> Result<(), String> {
+        self.check_invariants()
+    }
+
+    fn max_height(n: usize) -> usize {
+        2 * (n + 1).ilog2() as usize + 1
+    }
+}
+
+impl Balanced for AVLMap<u32, u32> {
+    fn height(&self) -> usize {
+        self.height()
+    }
+
+    fn check_invariants(&self) -> Result<(), String> {
+        self.check_invariants()
+    }
+
+    fn max_height(n: usize) -> usize {
+        (1.45 * ((n + 2) as f64).log2()) as usize
+    }
+}
+
+impl<const ORDER: usize> Balanced for BTreeMap61B<u32, u32, ORDER> {
+    fn height(&self) -> usize {
+        self.height()
+    }
+
+    fn check_invariants(&self) -> Result<(), String> {
+        self.check_invariants()
+    }
+
+    fn max_height(n: usize) -> usize {
+        // the root has at least 2 children and every other internal node has
+        // at least ceil(ORDER / 2), so there are at least 2 * d^(h - 1) leaves
+        let d = ORDER.div_ceil(2) as f64;
+        1 + ((n + 1) as f64 / 2.0).log(d).floor() as usize
+    }
+}
+
+fn ordered_inserts<T: Balanced>() {
+    let mut map = T::new();
+    for i in 0..10_000 {
+        map.insert(i, i);
+        if i % 97 == 0 {
+            assert_eq!(map.check_invariants(), Ok(()));
+        }
+    }
+    assert_eq!(map.check_invariants(), Ok(()));
+    assert!(map.height() <= T::max_height(map.len()), "{}", map.height());
+    for i in (0..10_000).rev().step_by(2) {
+        assert_eq!(map.remove(&i), Some(i));
+    }
+    assert_eq!(map.check_invariants(), Ok(()));
+    assert!(map.height() <= T::max_height(map.len()));
+    let entries: Vec<(u32, u32)> = map.into_iter().collect();
+    let expected: Vec<(u32, u32)> = (0..10_000).step_by(2).map(|i| (i, i)).collect();
+    assert_eq!(entries, expected);
+}
+
+fn randomized_against_btreemap<T: Balanced>() {
+    let mut rng = rand::thread_rng();
+    let mut map = T::new();
+    let mut expected = BTreeMap::new();
+    for _ in 0..20_000 {
+        let key = rng.gen_range(0..1000);
+        let value = rng.gen();
+        match rng.gen_range(0..10) {
+            0..=3 => assert_eq!(map.remove(&key), expected.remove(&key)),
+            4..=6 => assert_eq!(map.insert(key, value), expected.insert(key, value)),
+            _ => {
+                // inserting through a vacant entry has to find the new node after rebalancing
+                let got = *map.entry(key).and_modify(|v| *v ^= value).or_insert(value);
+                let want = *expected
+                    .entry(key)
+                    .and_modify(|v| *v ^= value)
+                    .or_insert(value);
+                assert_eq!(got, want);
+            }
+        }
+        assert_eq!(map.len(), expected.len());
+        assert_eq!(map.get(&key), expected.get(&key));
+        assert_eq!(map.check_invariants(), Ok(()));
+        assert!(map.height() <= T::max_height(map.len()));
+    }
+    assert!(map.into_iter().eq(expected));
+}
+
+#[test]
+fn llrb_ordered_inserts() {
+    ordered_inserts::<LLRBMap<u32, u32>>();
+}
+
+#[test]
+fn llrb_randomized() {
+    randomized_against_btreemap::<LLRBMap<u32, u32>>();
+}
+
+#[test]
+fn avl_ordered_inserts() {
+    ordered_inserts::<AVLMap<u32, u32>>();
+}
+
+#[test]
+fn avl_randomized() {
+    randomized_against_btreemap::<AVLMap<u32, u32>>();
+}
+
+#[test]
+fn btree_ordered_inserts() {
+    ordered_inserts::<BTreeMap61B<u32, u32, 3>>();
+    ordered_inserts::<BTreeMap61B<u32, u32, 4>>();
+    ordered_inserts::<BTreeMap61B<u32, u32, 5>>();
+    ordered_inserts::<BTreeMap61B<u32, u32, 32>>();
+}
+
+#[test]
+fn btree_randomized() {
+    randomized_against_btreemap::<BTreeMap61B<u32, u32, 3>>();
+    randomized_against_btreemap::<BTreeMap61B<u32, u32, 4>>();
+    randomized_against_btreemap::<BTreeMap61B<u32, u32, 5>>();
+    randomized_against_btreemap::<BTreeMap61B<u32, u32, 32>>();
+}
+
+#[test]
+fn btree_iter() {
+    let mut map = BTreeMap61B::<u32, u32, 3>::new();
+    for i in (0..1000).rev() {
+        map.insert(i * 2, i);
+    }
+    assert!(map
+        .iter()
+        .map(|(k, v)| (*k, *v))
+        .eq((0..1000).map(|i| (i * 2, i))));
+    assert_eq!(BTreeMap61B::<u32, u32, 3>::new().iter().next(), None);
+}
+
+#[test]
+fn check_invariants_empty() {
+    assert_eq!(LLRBMap::<u32, u32>::new().check_invariants(), Ok(()));
+    assert_eq!(AVLMap::<u32, u32>::new().check_invariants(), Ok(()));
+    assert_eq!(BTreeMap61B::<u32, u32, 4>::new().check_invariants(), Ok(()));
+    assert_eq!(LLRBMap::<u32, u32>::new().height(), 0);
+    assert_eq!(BTreeMap61B::<u32, u32, 4>::new().height(), 0);
+}
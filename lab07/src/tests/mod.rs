@@ -1,7 +1,28 @@
-use crate::bstmap::BSTMap;
+use std::collections::BTreeMap;
 
-mod extra;
-mod map;
+use crate::{
+    avlmap::AVLMap, bstmap::BSTMap, btreemap61b::BTreeMap61B, llrbmap::LLRBMap, ullmap::ULLMap,
+};
 
-// This can be changed to any Map61B type that you want to test
-type TestMap<K, V> = BSTMap<K, V>;
+type BTree234<K, V> = BTreeMap61B<K, V, 4>;
+
+mod balanced;
+mod ordered;
+
+/// Runs the tests from map61b_tests against a Map61B type
+macro_rules! map_tests {
+    ($name: ident, $map: ident) => {
+        mod $name {
+            use super::$map;
+
+            map61b_tests::map61b_tests!($map, crate::Map61B, tree);
+        }
+    };
+}
+
+map_tests!(ullmap, ULLMap);
+map_tests!(bstmap, BSTMap);
+map_tests!(llrbmap, LLRBMap);
+map_tests!(avlmap, AVLMap);
+map_tests!(btreemap61b, BTree234);
+map_tests!(btreemap, BTreeMap);
@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+
+use rand::Rng;
+
+use crate::{bstmap::BSTMap, Map61B};
+
+fn letters() -> BSTMap<char, usize> {
+    let mut b = BSTMap::new();
+    for (i, c) in "smerxacph".chars().enumerate() {
+        b.insert(c, i);
+    }
+    b
+}
+
+#[test]
+fn iter_in_order() {
+    let mut b = letters();
+    let keys: String = b.keys().collect();
+    assert_eq!(keys, "acehmprsx");
+    let values: Vec<usize> = b.values().copied().collect();
+    assert_eq!(values, vec![5, 6, 2, 8, 1, 7, 3, 0, 4]);
+    assert_eq!(b.iter().size_hint(), (9, Some(9)));
+
+    for (_, v) in b.iter_mut() {
+        *v *= 10;
+    }
+    for (_, v) in &mut b {
+        *v += 1;
+    }
+    assert_eq!(b.get(&'s'), Some(&1));
+    assert_eq!(b.get(&'h'), Some(&81));
+
+    let entries: Vec<(char, usize)> = b.into_iter().collect();
+    assert_eq!(entries[0], ('a', 51));
+    assert_eq!(entries[8], ('x', 41));
+    assert!(entries.windows(2).all(|w| w[0].0 < w[1].0));
+}
+
+#[test]
+fn min_max_floor_ceiling() {
+    let b = letters();
+    assert_eq!(b.min(), Some((&'a', &5)));
+    assert_eq!(b.max(), Some((&'x', &4)));
+    assert_eq!(b.floor(&'g').map(|(k, _)| *k), Some('e'));
+    assert_eq!(b.floor(&'e').map(|(k, _)| *k), Some('e'));
+    assert_eq!(b.floor(&'0'), None);
+    assert_eq!(b.ceiling(&'q').map(|(k, _)| *k), Some('r'));
+    assert_eq!(b.ceiling(&'x').map(|(k, _)| *k), Some('x'));
+    assert_eq!(b.ceiling(&'z'), None);
+
+    let empty = BSTMap::<char, usize>::new();
+    assert_eq!(empty.min(), None);
+    assert_eq!(empty.max(), None);
+}
+
+#[test]
+fn rank_select() {
+    let b = letters();
+    let keys: Vec<char> = b.keys().copied().collect();
+    for (i, k) in keys.iter().enumerate() {
+        assert_eq!(b.rank(k), i);
+        assert_eq!(b.select(i).map(|(k, _)| *k), Some(*k));
+    }
+    assert_eq!(b.rank(&'0'), 0);
+    assert_eq!(b.rank(&'f'), 3);
+    assert_eq!(b.rank(&'z'), 9);
+    assert_eq!(b.select(9), None);
+}
+
+#[test]
+fn range() {
+    let b = letters();
+    let keys = |r: Vec<(&char, &usize)>| -> String { r.into_iter().map(|(k, _)| *k).collect() };
+    assert_eq!(keys(b.range('e'..'r').collect()), "ehmp");
+    assert_eq!(keys(b.range('d'..='r').collect()), "ehmpr");
+    assert_eq!(keys(b.range(..'e').collect()), "ac");
+    assert_eq!(keys(b.range('s'..).collect()), "sx");
+    assert_eq!(keys(b.range(..).collect()), "acehmprsx");
+    assert_eq!(keys(b.range('f'..'g').collect()), "");
+    assert_eq!(keys(b.range('y'..).collect()), "");
+}
+
+#[test]
+fn pop_min_and_remove_keep_order() {
+    let mut b = letters();
+    assert_eq!(b.pop_min(), Some(('a', 5)));
+    assert_eq!(b.pop_min(), Some(('c', 6)));
+    assert_eq!(b.len(), 7);
+    // e has two children, so it is replaced by its successor
+    assert_eq!(b.remove(&'e'), Some(2));
+    assert_eq!(b.remove(&'e'), None);
+    assert_eq!(b.len(), 6);
+    assert_eq!(b.keys().collect::<String>(), "hmprsx");
+    assert_eq!(b.rank(&'p'), 2);
+    assert_eq!(b.select(5), Some((&'x', &4)));
+
+    while b.pop_min().is_some() {}
+    assert!(b.is_empty());
+    assert_eq!(b.pop_min(), None);
+}
+
+#[test]
+fn tall_tree() {
+    // inserting in order makes a tree as tall as it is big, which must not
+    // overflow the stack when it is iterated over or dropped
+    let mut b = BSTMap::new();
+    for i in 0..20_000 {
+        b.insert(i, i);
+    }
+    assert_eq!(b.iter().count(), 20_000);
+    assert_eq!(b.into_iter().take(10).count(), 10);
+}
+
+#[test]
+fn randomized_against_btreemap() {
+    let mut rng = rand::thread_rng();
+    let mut b = BSTMap::new();
+    let mut expected = BTreeMap::new();
+    for _ in 0..5000 {
+        let key: u16 = rng.gen_range(0..500);
+        match rng.gen_range(0..4) {
+            0 => assert_eq!(b.remove(&key), expected.remove(&key)),
+            1 => assert_eq!(b.pop_min(), expected.pop_first(),),
+            _ => assert_eq!(b.insert(key, key), expected.insert(key, key)),
+        }
+        assert_eq!(b.len(), expected.len());
+        assert_eq!(b.floor(&key), expected.range(..=key).next_back());
+        assert_eq!(b.ceiling(&key), expected.range(key..).next());
+        assert_eq!(b.rank(&key), expected.range(..key).count());
+        let hi = key.saturating_add(rng.gen_range(0..50));
+        assert!(b.range(key..hi).eq(expected.range(key..hi)));
+    }
+    assert!(b.iter().eq(expected.iter()));
+    for (i, entry) in expected.iter().enumerate() {
+        assert_eq!(b.select(i), Some(entry));
+    }
+}
+
+// like get, the ordered lookups take any borrowed form of the key
+#[test]
+fn borrowed_keys() {
+    let mut b = BSTMap::new();
+    for word in ["dog", "cat", "fish"] {
+        b.insert(word.to_string(), word.len());
+    }
+    assert_eq!(b.floor("dot").map(|(k, _)| k.as_str()), Some("dog"));
+    assert_eq!(b.ceiling("dot").map(|(k, _)| k.as_str()), Some("fish"));
+    assert_eq!(b.rank("dog"), 1);
+    assert_eq!(b.rank("zebra"), 3);
+}
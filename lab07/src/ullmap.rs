@@ -1,3 +1,5 @@
+use std::borrow::Borrow;
+
 use crate::Map61B;
 
 struct ULLNode<K, V> {
@@ -27,9 +29,91 @@ impl<K, V> IntoIterator for ULLMap<K, V> {
     }
 }
 
+impl<K, V> ULLMap<K, V> {
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            node: self.root.as_deref(),
+            remaining: self.size,
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            node: self.root.as_deref_mut(),
+            remaining: self.size,
+        }
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    node: Option<&'a ULLNode<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n = self.node?;
+        self.node = n.next.as_deref();
+        self.remaining -= 1;
+        Some((&n.key, &n.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+pub struct IterMut<'a, K, V> {
+    node: Option<&'a mut ULLNode<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n = self.node.take()?;
+        self.node = n.next.as_deref_mut();
+        self.remaining -= 1;
+        Some((&n.key, &mut n.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a ULLMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut ULLMap<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
 impl<K: Eq, V> Map61B for ULLMap<K, V> {
     type Key = K;
     type Value = V;
+    type Iter<'a>
+        = Iter<'a, K, V>
+    where
+        Self: 'a;
+    type IterMut<'a>
+        = IterMut<'a, K, V>
+    where
+        Self: 'a;
 
     fn new() -> Self {
         Self {
@@ -47,7 +131,11 @@ impl<K: Eq, V> Map61B for ULLMap<K, V> {
         self.root = None;
     }
 
-    fn contains_key(&self, key: &Self::Key) -> bool {
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         self.get(key).is_some()
     }
 
@@ -69,10 +157,14 @@ impl<K: Eq, V> Map61B for ULLMap<K, V> {
         None
     }
 
-    fn get(&self, key: &Self::Key) -> Option<&Self::Value> {
+    fn get<Q>(&self, key: &Q) -> Option<&Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         let mut node = &self.root;
         while let Some(n) = node {
-            if n.key == *key {
+            if n.key.borrow() == key {
                 return Some(&n.value);
             }
             node = &n.next;
@@ -80,10 +172,14 @@ impl<K: Eq, V> Map61B for ULLMap<K, V> {
         None
     }
 
-    fn get_mut(&mut self, key: &Self::Key) -> Option<&mut Self::Value> {
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         let mut node = &mut self.root;
         while let Some(n) = node {
-            if n.key == *key {
+            if n.key.borrow() == key {
                 return Some(&mut n.value);
             }
             node = &mut n.next;
@@ -91,11 +187,15 @@ impl<K: Eq, V> Map61B for ULLMap<K, V> {
         None
     }
 
-    fn remove(&mut self, key: &Self::Key) -> Option<Self::Value> {
+    fn remove<Q>(&mut self, key: &Q) -> Option<Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         let mut node = &mut self.root;
         loop {
             if let Some(n) = node {
-                if n.key == *key {
+                if n.key.borrow() == key {
                     let removed = node.take();
                     let ULLNode {
                         key: _,
@@ -114,4 +214,20 @@ impl<K: Eq, V> Map61B for ULLMap<K, V> {
             }
         }
     }
+
+    fn insert_vacant(&mut self, key: Self::Key, value: Self::Value) -> &mut Self::Value {
+        // the key isn't anywhere in the list, so it can go at the front
+        let next = self.root.take();
+        let node = self.root.insert(Box::from(ULLNode { key, value, next }));
+        self.size += 1;
+        &mut node.value
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        ULLMap::iter(self)
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        ULLMap::iter_mut(self)
+    }
 }
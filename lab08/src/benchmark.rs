@@ -1,74 +1,227 @@
 use std::{
     collections::{BTreeMap, HashMap},
-    io::{self, Write},
-    time::Instant,
+    hash::BuildHasher,
+    panic::{self, AssertUnwindSafe},
+    process,
+    sync::Mutex,
 };
 
-use lab08::{chashmap::CHashMap, myhashmap::MyHashMap, ohashmap::OHashMap, ullmap::ULLMap, Map61B};
+use clap::Parser;
+use map61b_common::{
+    allocator::CountingAllocator,
+    bench::{
+        bench_concurrent, bench_map, ConcurrentConfig, ConcurrentMap, ConcurrentRow, Config, Dist,
+        Op, Row, Sizes,
+    },
+    MapUnderTest,
+};
+use rand::{rngs::StdRng, SeedableRng};
+
+use lab08::{
+    chashmap::CHashMap,
+    hashers::{BadBuildHasher, FnvBuildHasher, FxBuildHasher, SipBuildHasher},
+    myhashmap::MyHashMap,
+    ohashmap::OHashMap,
+    policy::LoadFactor,
+    probing::{DHashMap, ProbeLengths, QHashMap},
+    rhhashmap::RHashMap,
+    sharded::ShardedMap,
+    shashmap::SHashMap,
+    ullmap::ULLMap,
+    Map61B,
+};
+
+// the labs' maps are benchmarked through Subject, which wraps a Map61B type
+map61b_common::map61b_subject!(lab08::Map61B, hash);
 
-fn main() -> io::Result<()> {
-    let stdin = io::stdin();
-    let mut stdout = io::stdout();
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
 
-    println!("Welcome to the insertion benchmark!");
-    println!("The algorithm choices are as follows:");
-    println!("  u: ULLMap");
-    println!("  m: MyHashMap");
-    println!("  c: CHashMap");
-    println!("  o: OHashMap");
-    println!("  t: std::collections::BTreeMap");
-    println!("  h: std::collections::HashMap");
-    println!("Enter benchmarks in the format \"ordered/random choices num\". For example, to run the random insertion benchmark on all six algorithms with 100 items, enter");
-    println!("random umcoth 100");
-    loop {
-        println!();
-        print!("Enter benchmark, or q to quit: ");
-        stdout.flush()?;
-        let mut buffer = String::new();
-        stdin.read_line(&mut buffer)?;
-        if buffer.contains('q') {
-            return Ok(());
+#[derive(Parser, Debug)]
+/// Benchmark the Map61B implementations, printing the results as CSV.
+/// After inserting, each map's heap usage is printed too, along with the
+/// hashmaps' load factors.
+/// The open-addressing hashmaps also print their probe lengths to stderr.
+/// With --threads, the maps that threads can share are benchmarked instead.
+struct Args {
+    /// which maps to benchmark, one letter each:
+    /// u: ULLMap, m: MyHashMap, c: CHashMap, o: OHashMap, r: RHashMap (Robin Hood),
+    /// q: QHashMap (quadratic probing), d: DHashMap (double hashing),
+    /// s: SHashMap (Swiss table), t: std::collections::BTreeMap,
+    /// h: std::collections::HashMap
+    #[clap(long, default_value = "mcorqdsth")]
+    maps: String,
+    /// which operations to time, separated by commas. Can be insert, get, remove, or mixed.
+    #[clap(long, value_delimiter = ',', default_value = "insert")]
+    ops: Vec<Op>,
+    /// how many keys to use, like 1e5, or a range like 1e3..1e6 to double from the
+    /// first to the second
+    #[clap(long, default_value = "1e3..1e5")]
+    n: Sizes,
+    /// which keys to use. Can be ordered, random, or zipf.
+    #[clap(long, default_value = "random")]
+    dist: Dist,
+    /// how many times to time each operation at each size
+    #[clap(long, default_value_t = 5)]
+    repeat: usize,
+    /// which hasher the hashmaps use. Can be sip, fx, fnv, or bad.
+    #[clap(long, default_value = "sip")]
+    hasher: String,
+    /// the seed for the random keys, to make runs repeatable
+    #[clap(long)]
+    seed: Option<u64>,
+    /// benchmark the maps that threads can share instead, with each of these numbers
+    /// of threads, separated by commas. Only these maps are used:
+    /// c: ShardedMap, made of CHashMaps, h: std::collections::HashMap in a Mutex.
+    /// --ops is ignored: the threads get and insert instead.
+    #[clap(long, value_delimiter = ',')]
+    threads: Vec<usize>,
+    /// with --threads, the percentage of operations that are gets rather than inserts
+    #[clap(long, default_value_t = 90)]
+    reads: u32,
+}
+
+fn main() {
+    let args = Args::parse();
+    if args.repeat == 0 {
+        eprintln!("--repeat must be at least 1");
+        process::exit(2);
+    }
+    let config = Config {
+        ops: args.ops,
+        dist: args.dist,
+        sizes: args.n,
+        repeat: args.repeat,
+    };
+    let mut rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    if !args.threads.is_empty() {
+        if args.threads.contains(&0) || args.reads > 100 {
+            eprintln!("--threads must be at least 1, and --reads at most 100");
+            process::exit(2);
         }
-        let mut tokens = buffer.split_whitespace();
-        let bench = tokens.next();
-        let choices = tokens.next();
-        let num = tokens.next().and_then(|s| s.parse().ok());
-        match (bench, choices, num) {
-            (Some("ordered"), Some(choices), Some(n)) => bench_all(choices, n, true),
-            (Some("random"), Some(choices), Some(n)) => bench_all(choices, n, false),
-            _ => println!("Sorry, I didn't understand that."),
+        let config = ConcurrentConfig {
+            threads: args.threads,
+            dist: config.dist,
+            sizes: config.sizes,
+            repeat: config.repeat,
+            read_percent: args.reads,
+        };
+        println!("{}", ConcurrentRow::HEADER);
+        match args.hasher.as_str() {
+            "sip" => bench_all_concurrent::<SipBuildHasher>(&args.maps, &config, &mut rng),
+            "fx" => bench_all_concurrent::<FxBuildHasher>(&args.maps, &config, &mut rng),
+            "fnv" => bench_all_concurrent::<FnvBuildHasher>(&args.maps, &config, &mut rng),
+            "bad" => bench_all_concurrent::<BadBuildHasher>(&args.maps, &config, &mut rng),
+            _ => {
+                eprintln!("Not a valid hasher: use sip, fx, fnv or bad");
+                process::exit(2);
+            }
+        }
+        return;
+    }
+    println!("{}", Row::HEADER);
+    match args.hasher.as_str() {
+        "sip" => bench_all::<SipBuildHasher>(&args.maps, &config, &mut rng),
+        "fx" => bench_all::<FxBuildHasher>(&args.maps, &config, &mut rng),
+        "fnv" => bench_all::<FnvBuildHasher>(&args.maps, &config, &mut rng),
+        "bad" => bench_all::<BadBuildHasher>(&args.maps, &config, &mut rng),
+        _ => {
+            eprintln!("Not a valid hasher: use sip, fx, fnv or bad");
+            process::exit(2);
         }
     }
 }
 
-fn bench_all(choices: &str, n: usize, ordered: bool) {
+/// Runs the benchmarks on each map in choices, with the hashmaps using the hasher S
+fn bench_all<S: BuildHasher + Default>(choices: &str, config: &Config, rng: &mut StdRng) {
     for c in choices.chars() {
         match c {
-            'u' => bench_single::<ULLMap<usize, usize>>("ULLMap   ", n, ordered),
-            'm' => bench_single::<MyHashMap<usize, usize>>("MyHashMap", n, ordered),
-            'c' => bench_single::<CHashMap<usize, usize>>("CHashMap ", n, ordered),
-            'o' => bench_single::<OHashMap<usize, usize>>("OHashMap ", n, ordered),
-            't' => bench_single::<BTreeMap<usize, usize>>("BTreeMap ", n, ordered),
-            'h' => bench_single::<HashMap<usize, usize>>("HashMap  ", n, ordered),
-            _ => {}
+            'u' => bench_single::<Subject<ULLMap<usize, usize>>>("ULLMap", config, rng),
+            'm' => bench_hashed::<MyHashMap<usize, usize, S>>("MyHashMap", config, rng),
+            'c' => bench_hashed::<CHashMap<usize, usize, S>>("CHashMap", config, rng),
+            'o' => bench_probed::<OHashMap<usize, usize, S>>("OHashMap", config, rng),
+            'r' => bench_probed::<RHashMap<usize, usize, S>>("RHashMap", config, rng),
+            'q' => bench_probed::<QHashMap<usize, usize, S>>("QHashMap", config, rng),
+            'd' => bench_probed::<DHashMap<usize, usize, S>>("DHashMap", config, rng),
+            's' => bench_hashed::<SHashMap<usize, usize, S>>("SHashMap", config, rng),
+            't' => bench_single::<BTreeMap<usize, usize>>("BTreeMap", config, rng),
+            'h' => bench_single::<HashMap<usize, usize, S>>("HashMap", config, rng),
+            _ => eprintln!("Skipping unknown map {:?}", c),
         }
     }
 }
 
-fn bench_single<T: Map61B<Key = usize, Value = usize>>(name: &str, n: usize, ordered: bool) {
-    let mut map = T::new();
-    let values: Vec<usize> = if ordered {
-        (0..n).collect()
-    } else {
-        (0..n).map(|_| rand::random()).collect()
-    };
-    let start = Instant::now();
-    for v in values {
-        map.insert(v, v);
+/// Runs the concurrent benchmarks on each map in choices that threads can share,
+/// with the hashmaps using the hasher S
+fn bench_all_concurrent<S>(choices: &str, config: &ConcurrentConfig, rng: &mut StdRng)
+where
+    S: BuildHasher + Default + Clone + Send + Sync,
+{
+    for c in choices.chars() {
+        match c {
+            'c' => bench_shared::<ShardedMap<usize, usize, S>>("ShardedMap", config, rng),
+            'h' => bench_shared::<Mutex<HashMap<usize, usize, S>>>("Mutex<HashMap>", config, rng),
+            _ => eprintln!("Skipping map {:?}, which threads can't share", c),
+        }
+    }
+}
+
+fn bench_shared<T: ConcurrentMap>(name: &str, config: &ConcurrentConfig, rng: &mut StdRng) {
+    run(name, || {
+        bench_concurrent::<T, _>(name, config, rng, |row| println!("{}", row))
+    });
+}
+
+/// Runs the benchmarks, and prints the map's probe lengths after inserting
+fn bench_probed<T>(name: &str, config: &Config, rng: &mut StdRng)
+where
+    T: Map61B<Key = usize, Value = usize> + LoadFactor + ProbeLengths,
+{
+    run(name, || {
+        bench_map::<Subject<T>, _>(
+            name,
+            config,
+            rng,
+            print_row,
+            |n, map| eprintln!("{} n={}: {}", name, n, map.0.probe_stats()),
+            |map| Some(map.0.load_factor()),
+        )
+    });
+}
+
+fn bench_hashed<T>(name: &str, config: &Config, rng: &mut StdRng)
+where
+    T: Map61B<Key = usize, Value = usize> + LoadFactor,
+{
+    run(name, || {
+        bench_map::<Subject<T>, _>(
+            name,
+            config,
+            rng,
+            print_row,
+            |_, _| {},
+            |map| Some(map.0.load_factor()),
+        )
+    });
+}
+
+fn bench_single<T: MapUnderTest<usize, usize>>(name: &str, config: &Config, rng: &mut StdRng) {
+    run(name, || {
+        bench_map::<T, _>(name, config, rng, print_row, |_, _| {}, |_| None)
+    });
+}
+
+fn print_row(row: Row) {
+    println!("{}", row);
+}
+
+/// Runs a map's benchmarks, carrying on with the other maps if it panics,
+/// for example because a method is still unimplemented
+fn run(name: &str, benchmarks: impl FnOnce()) {
+    if panic::catch_unwind(AssertUnwindSafe(benchmarks)).is_err() {
+        eprintln!("{} panicked, skipping the rest of its benchmarks", name);
     }
-    println!(
-        "{} time taken: {:>12.6} seconds",
-        name,
-        start.elapsed().as_secs_f64()
-    );
 }
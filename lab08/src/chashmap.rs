@@ -1,36 +1,58 @@
 use std::{
-    collections::hash_map::DefaultHasher,
-    hash::{Hash, Hasher},
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+    slice,
 };
 
-use crate::Map61B;
+use crate::{
+    entry::{Entry, OccupiedEntry, VacantEntry},
+    hashers::SipBuildHasher,
+    policy::{LoadFactor, ResizePolicy},
+    Map61B,
+};
 
 const STARTING_BUCKETS: usize = 10;
-const RESIZE_FACTOR: usize = 4; // newlen = r * (# elements)
-const MAX_LOAD_FACTOR: f64 = 0.75;
-const MIN_LOAD_FACTOR: f64 = 0.05; // only applicable when buckets.len() > STARTING_BUCKETS
+// shrinking never goes below min_buckets
+const DEFAULT_POLICY: ResizePolicy = ResizePolicy::new(0.05, 0.25, 0.75);
 
 // A linked-list based hashmap
 // Rather than using a Vec for each bucket, all buckets are
 // backed my linked list nodes in the "chains" vec
 // Each "index" stored in hashmap is actually an index+1
 // 0 represents "no next element"
-pub struct CHashMap<K, V> {
+pub struct CHashMap<K, V, S = SipBuildHasher> {
     heads: Vec<usize>,          // indices of chains
     chains: Vec<(K, V, usize)>, // key, value, next index of chains
+    hash_builder: S,
+    policy: ResizePolicy,
+    /// the fewest buckets removing shrinks the map to, which with_capacity and
+    /// reserve raise so the map doesn't shrink below the capacity it was given
+    min_buckets: usize,
+}
+
+fn hash_index<Q: Hash + ?Sized, S: BuildHasher>(hash_builder: &S, key: &Q, len: usize) -> usize {
+    (hash_builder.hash_one(key) % (len as u64)) as usize
+}
+
+/// Returns how many buckets are needed to hold capacity entries without resizing
+fn buckets_for(policy: &ResizePolicy, capacity: usize) -> usize {
+    STARTING_BUCKETS.max(policy.buckets_for(capacity))
 }
 
-fn hash_index<K: Hash>(key: &K, len: usize) -> usize {
-    let mut h = DefaultHasher::new();
-    key.hash(&mut h);
-    (h.finish() % (len as u64)) as usize
+/// Returns how many buckets to resize to when there are num_elements entries
+fn resized_len(policy: &ResizePolicy, num_elements: usize) -> usize {
+    STARTING_BUCKETS.max(policy.resized_buckets(num_elements))
 }
 
-impl<K: Hash + Eq, V> CHashMap<K, V> {
-    fn find_index(&self, hash_index: usize, key: &K) -> Option<usize> {
+impl<K: Hash + Eq, V, S: BuildHasher> CHashMap<K, V, S> {
+    fn find_index<Q>(&self, hash_index: usize, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
         let mut i = self.heads[hash_index];
         while i != 0 {
-            if self.chains[i - 1].0 == *key {
+            if self.chains[i - 1].0.borrow() == key {
                 // see comment above struct
                 return Some(i - 1);
             }
@@ -39,6 +61,76 @@ impl<K: Hash + Eq, V> CHashMap<K, V> {
         None
     }
 
+    /// Returns where key's entry is in chains. It stays there until the map is changed.
+    pub(crate) fn position<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find_index(hash_index(&self.hash_builder, key, self.heads.len()), key)
+    }
+
+    pub(crate) fn value_at(&self, position: usize) -> &V {
+        &self.chains[position].1
+    }
+
+    pub(crate) fn value_at_mut(&mut self, position: usize) -> &mut V {
+        &mut self.chains[position].1
+    }
+
+    /// Removes the entry at position, returning its key and value
+    pub(crate) fn remove_at(&mut self, position: usize) -> (K, V) {
+        let hash_rm = hash_index(
+            &self.hash_builder,
+            &self.chains[position].0,
+            self.heads.len(),
+        );
+        self.unlink(hash_rm, position)
+    }
+
+    /// Removes key from the map, returning the key that was stored and its value
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash_rm = hash_index(&self.hash_builder, key, self.heads.len());
+        let rm_index = self.find_index(hash_rm, key)?;
+        Some(self.unlink(hash_rm, rm_index))
+    }
+
+    /// Removes the entry at rm_index in chains, which is in bucket hash_rm
+    fn unlink(&mut self, hash_rm: usize, rm_index: usize) -> (K, V) {
+        let hash_end = hash_index(
+            &self.hash_builder,
+            &self.chains.last().unwrap().0,
+            self.heads.len(),
+        );
+
+        let pre_rm = self.find_preceding(hash_rm, rm_index);
+        let pre_end = self.find_preceding(hash_end, self.chains.len() - 1);
+        // if pre-rm and pre-end are different, pre-rm will point to the link after rm, and pre-end will point to rm
+        // if they are the same, point to the link after rm
+        match pre_end {
+            Some(i) => self.chains[i].2 = rm_index + 1,
+            None => self.heads[hash_end] = rm_index + 1,
+        }
+        match pre_rm {
+            Some(i) => self.chains[i].2 = self.chains[rm_index].2,
+            None => self.heads[hash_rm] = self.chains[rm_index].2,
+        }
+
+        let (k, v, _) = self.chains.swap_remove(rm_index);
+        if self.heads.len() > self.min_buckets
+            && self
+                .policy
+                .should_shrink(self.chains.len(), self.heads.len())
+        {
+            self.resize(resized_len(&self.policy, self.chains.len()).max(self.min_buckets));
+        }
+        (k, v)
+    }
+
     // returns None if target_index is the head of a list
     fn find_preceding(&self, hash_index: usize, target_index: usize) -> Option<usize> {
         if self.heads[hash_index] == target_index + 1 {
@@ -51,22 +143,159 @@ impl<K: Hash + Eq, V> CHashMap<K, V> {
         Some(r)
     }
 
-    fn loading(&self) -> f64 {
-        self.chains.len() as f64 / self.heads.len() as f64
-    }
-
     fn resize(&mut self, new_len: usize) {
         self.heads.clear();
         self.heads.resize(new_len, 0);
         for i in 0..self.chains.len() {
-            let h = hash_index(&self.chains[i].0, new_len);
+            let h = hash_index(&self.hash_builder, &self.chains[i].0, new_len);
             self.chains[i].2 = self.heads[h];
             self.heads[h] = i + 1; // see comment above struct
         }
     }
+
+    /// Makes room for at least additional more entries before the map has to resize
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self.chains.len() + additional;
+        self.chains.reserve(additional);
+        self.min_buckets = self.min_buckets.max(buckets_for(&self.policy, needed));
+        if needed > self.capacity() {
+            self.resize(buckets_for(&self.policy, needed));
+        }
+    }
+
+    /// Shrinks the map to as few buckets as it can hold its entries in, giving up
+    /// any capacity reserved before, and frees any unused space in chains
+    pub fn shrink_to_fit(&mut self) {
+        self.min_buckets = STARTING_BUCKETS;
+        let len = buckets_for(&self.policy, self.chains.len());
+        if len < self.heads.len() {
+            self.resize(len);
+            self.heads.shrink_to_fit();
+        }
+        self.chains.shrink_to_fit();
+    }
+
+    /// Changes when the map resizes, growing it straight away if it's too full
+    pub fn set_policy(&mut self, policy: ResizePolicy) {
+        self.policy = policy;
+        if policy.should_grow(self.chains.len(), self.heads.len()) {
+            self.resize(resized_len(&policy, self.chains.len()));
+        }
+    }
+}
+
+impl<K, V> CHashMap<K, V> {
+    /// Returns an empty map with room for capacity entries before it has to resize
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, SipBuildHasher::default())
+    }
+}
+
+impl<K, V, S> CHashMap<K, V, S> {
+    /// Returns an empty map that hashes its keys with hash_builder
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self::with_capacity_and_hasher(0, hash_builder)
+    }
+
+    /// Returns an empty map with room for capacity entries before it has to resize,
+    /// that hashes its keys with hash_builder
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        let len = buckets_for(&DEFAULT_POLICY, capacity);
+        Self {
+            heads: vec![0; len],
+            chains: Vec::with_capacity(capacity),
+            hash_builder,
+            policy: DEFAULT_POLICY,
+            min_buckets: len,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    pub fn policy(&self) -> &ResizePolicy {
+        &self.policy
+    }
+
+    /// Returns how many entries the map can hold before it has to resize
+    pub fn capacity(&self) -> usize {
+        self.policy.capacity(self.heads.len())
+    }
+
+    /// Returns an iterator over the entries, in the order they are stored in chains
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            chains: self.chains.iter(),
+        }
+    }
+
+    /// Returns an iterator over the entries, in the order they are stored in chains,
+    /// that allows the values to be modified
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            chains: self.chains.iter_mut(),
+        }
+    }
+}
+
+impl<K, V, S> LoadFactor for CHashMap<K, V, S> {
+    fn load_factor(&self) -> f64 {
+        self.chains.len() as f64 / self.heads.len() as f64
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    chains: slice::Iter<'a, (K, V, usize)>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.chains.next().map(|(k, v, _)| (k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chains.size_hint()
+    }
+}
+
+pub struct IterMut<'a, K, V> {
+    chains: slice::IterMut<'a, (K, V, usize)>,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.chains.next().map(|(k, v, _)| (&*k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chains.size_hint()
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a CHashMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a mut CHashMap<K, V, S> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
 }
 
-impl<K, V> IntoIterator for CHashMap<K, V> {
+impl<K, V, S> IntoIterator for CHashMap<K, V, S> {
     type Item = (K, V);
     type IntoIter = std::vec::IntoIter<(K, V)>;
 
@@ -79,15 +308,20 @@ impl<K, V> IntoIterator for CHashMap<K, V> {
     }
 }
 
-impl<K: Hash + Eq, V> Map61B for CHashMap<K, V> {
+impl<K: Hash + Eq, V, S: BuildHasher + Default> Map61B for CHashMap<K, V, S> {
     type Key = K;
     type Value = V;
+    type Iter<'a>
+        = Iter<'a, K, V>
+    where
+        Self: 'a;
+    type IterMut<'a>
+        = IterMut<'a, K, V>
+    where
+        Self: 'a;
 
     fn new() -> Self {
-        Self {
-            heads: vec![0; STARTING_BUCKETS],
-            chains: Vec::new(),
-        }
+        Self::with_hasher(S::default())
     }
 
     fn len(&self) -> usize {
@@ -95,62 +329,107 @@ impl<K: Hash + Eq, V> Map61B for CHashMap<K, V> {
     }
 
     fn clear(&mut self) {
-        self.heads.clear();
-        self.heads.resize(STARTING_BUCKETS, 0);
+        self.heads.fill(0);
         self.chains.clear();
     }
 
-    fn contains_key(&self, key: &Self::Key) -> bool {
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
         self.get(key).is_some()
     }
 
     fn insert(&mut self, key: Self::Key, value: Self::Value) -> Option<Self::Value> {
-        let h = hash_index(&key, self.heads.len());
+        let h = hash_index(&self.hash_builder, &key, self.heads.len());
         if let Some(i) = self.find_index(h, &key) {
             return Some(std::mem::replace(&mut self.chains[i].1, value));
         }
         self.chains.push((key, value, self.heads[h]));
         self.heads[h] = self.chains.len(); // not - 1, see comment above struct
-        if self.loading() > MAX_LOAD_FACTOR {
-            self.resize(self.chains.len() * RESIZE_FACTOR);
+        if self.policy.should_grow(self.chains.len(), self.heads.len()) {
+            self.resize(resized_len(&self.policy, self.chains.len()));
         }
         None
     }
 
-    fn get(&self, key: &Self::Key) -> Option<&Self::Value> {
-        let h = hash_index(&key, self.heads.len());
+    fn get<Q>(&self, key: &Q) -> Option<&Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let h = hash_index(&self.hash_builder, key, self.heads.len());
         let i = self.find_index(h, key)?;
         Some(&self.chains[i].1)
     }
 
-    fn get_mut(&mut self, key: &Self::Key) -> Option<&mut Self::Value> {
-        let h = hash_index(&key, self.heads.len());
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let h = hash_index(&self.hash_builder, key, self.heads.len());
         let i = self.find_index(h, key)?;
         Some(&mut self.chains[i].1)
     }
 
-    fn remove(&mut self, key: &Self::Key) -> Option<Self::Value> {
-        let hash_rm = hash_index(&key, self.heads.len());
-        let rm_index = self.find_index(hash_rm, key)?;
-        let hash_end = hash_index(&self.chains.last().unwrap().0, self.heads.len());
+    fn remove<Q>(&mut self, key: &Q) -> Option<Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
 
-        let pre_rm = self.find_preceding(hash_rm, rm_index);
-        let pre_end = self.find_preceding(hash_end, self.chains.len() - 1);
-        // if pre-rm and pre-end are different, pre-rm will point to the link after rm, and pre-end will point to rm
-        // if they are the same, point to the link after rm
-        match pre_end {
-            Some(i) => self.chains[i].2 = rm_index + 1,
-            None => self.heads[hash_end] = rm_index + 1,
-        }
-        match pre_rm {
-            Some(i) => self.chains[i].2 = self.chains[rm_index].2,
-            None => self.heads[hash_rm] = self.chains[rm_index].2,
+    fn insert_vacant(&mut self, key: Self::Key, value: Self::Value) -> &mut Self::Value {
+        let h = hash_index(&self.hash_builder, &key, self.heads.len());
+        self.chains.push((key, value, self.heads[h]));
+        self.heads[h] = self.chains.len(); // not - 1, see comment above struct
+        if self.policy.should_grow(self.chains.len(), self.heads.len()) {
+            // resizing only relinks the chains, so the new entry stays last
+            self.resize(resized_len(&self.policy, self.chains.len()));
         }
+        &mut self.chains.last_mut().unwrap().1
+    }
 
-        let res = self.chains.swap_remove(rm_index).1;
-        if self.loading() < MIN_LOAD_FACTOR && self.heads.len() != STARTING_BUCKETS {
-            self.resize(STARTING_BUCKETS.max(self.chains.len() * RESIZE_FACTOR));
+    fn entry(&mut self, key: K) -> Entry<'_, Self> {
+        // finding the key's position rather than a reference to its value lets
+        // the vacant branch have the map back, so the key is only looked up once
+        let h = hash_index(&self.hash_builder, &key, self.heads.len());
+        match self.find_index(h, &key) {
+            Some(i) => Entry::Occupied(OccupiedEntry {
+                key,
+                value: &mut self.chains[i].1,
+            }),
+            None => Entry::Vacant(VacantEntry { key, map: self }),
         }
-        Some(res)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        CHashMap::iter(self)
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        CHashMap::iter_mut(self)
+    }
+
+    fn drain(&mut self) -> Self::IntoIter {
+        // keeps the buckets, like clear
+        self.heads.fill(0);
+        self.chains
+            .drain(..)
+            .map(|(k, v, _)| (k, v))
+            .collect::<Vec<(K, V)>>()
+            .into_iter()
+    }
+
+    fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        self.chains.retain_mut(|(k, v, _)| f(k, v));
+        // removing entries moves the ones after them, so relink every chain
+        self.resize(self.heads.len());
     }
 }
@@ -0,0 +1,707 @@
+use std::{
+    borrow::Borrow,
+    cell::OnceCell,
+    env,
+    fs::{self, File, OpenOptions},
+    hash::{Hash, Hasher},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    chashmap::{self, CHashMap},
+    codec::Codec,
+    hashers::{FnvHasher, SipBuildHasher},
+    Map61B,
+};
+
+const HEADER_LEN: usize = 16;
+// the value length of a record that removes its key
+const TOMBSTONE: u32 = u32::MAX;
+// the log is compacted once at least this many bytes of it are records that
+// have been overwritten or removed, and they're over half of it
+const COMPACT_MIN_DEAD_BYTES: u64 = 1 << 16;
+
+static TEMPORARY_MAPS: AtomicUsize = AtomicUsize::new(0);
+
+/*
+A map stored in a file, like Bitcask. The file is a log of records, and every
+insert or remove appends one: the key and its new value, or a tombstone saying
+the key was removed. Nothing in the log is ever changed, so a crash can at worst
+cut off the last record, and opening the map again replays the log to find the
+latest record for each key.
+
+Each record is
+    checksum: u64, key_len: u32, value_len: u32, key, value
+with the numbers little-endian, and value_len = TOMBSTONE for a tombstone. The
+checksum is FNV-1a of everything after it, so a record that was only partly
+written can be told apart from a complete one.
+
+The index is a CHashMap from each key to where its latest record is. Values
+are only read from the file when they're first looked at, and then kept in
+memory, since Map61B hands out references to them. Changes made through
+get_mut, iter_mut or entry can't be seen as they happen, so any value that has
+been borrowed mutably is written again by flush, which also runs when the map
+is dropped. A value inserted through entry is only written by flush, since it's
+handed out mutably straight away.
+
+Overwritten and removed records take up space until compact rewrites the log
+with just the latest records. That happens automatically once most of the log
+is dead records.
+
+The Map61B methods panic if the file can't be read or written, since they
+have no way to return an error.
+*/
+pub struct DiskMap<K: Codec, V: Codec> {
+    index: CHashMap<K, Slot<V>>,
+    log: Log,
+    // temporary maps are deleted when they're dropped
+    temporary: bool,
+}
+
+struct Log {
+    file: File,
+    path: PathBuf,
+    // the length of the file, where the next record goes
+    end: u64,
+    // how many bytes of the file are records that have been overwritten or removed
+    dead_bytes: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Record {
+    offset: u64,
+    len: u64,
+}
+
+// the record of a value that hasn't been written yet, which flush will write
+const UNWRITTEN: Record = Record { offset: 0, len: 0 };
+
+struct Slot<V> {
+    record: Record,
+    value: OnceCell<V>,
+    // whether the value might have changed since the record was written
+    dirty: bool,
+}
+
+struct Header {
+    checksum: u64,
+    key_len: u32,
+    value_len: u32,
+}
+
+impl Header {
+    fn parse(bytes: &[u8]) -> Self {
+        Header {
+            checksum: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+            key_len: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            value_len: u32::from_le_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+
+    /// Returns the length of the whole record
+    fn record_len(&self) -> u64 {
+        let value_len = if self.value_len == TOMBSTONE {
+            0
+        } else {
+            self.value_len as u64
+        };
+        HEADER_LEN as u64 + self.key_len as u64 + value_len
+    }
+}
+
+fn checksum(bytes: &[u8]) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+/// Appends a record for key and value to buf, or a tombstone if value is None
+fn encode_record<K: Codec, V: Codec>(key: &K, value: Option<&V>, buf: &mut Vec<u8>) {
+    let start = buf.len();
+    buf.extend_from_slice(&[0; HEADER_LEN]);
+    key.encode(buf);
+    let key_len = buf.len() - start - HEADER_LEN;
+    let value_len = match value {
+        Some(value) => {
+            value.encode(buf);
+            buf.len() - start - HEADER_LEN - key_len
+        }
+        None => TOMBSTONE as usize,
+    };
+    assert!(
+        key_len < TOMBSTONE as usize && (value.is_none() || value_len < TOMBSTONE as usize),
+        "keys and values must encode to less than 4GiB"
+    );
+    buf[start + 8..start + 12].copy_from_slice(&(key_len as u32).to_le_bytes());
+    buf[start + 12..start + 16].copy_from_slice(&(value_len as u32).to_le_bytes());
+    let checksum = checksum(&buf[start + 8..]);
+    buf[start..start + 8].copy_from_slice(&checksum.to_le_bytes());
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Returns whether a whole record with a matching checksum might start anywhere
+/// in bytes, scanning forward from each byte in turn. Only a record whose lengths
+/// fit in the rest of bytes is hashed, and no more than HEADER_LEN times the
+/// length of bytes is hashed in all, so garbage full of lengths that fit can't
+/// make the scan quadratic. Past that it gives up and says there might be one,
+/// which keeps the file as it is rather than cutting records off it.
+fn may_contain_record(bytes: &[u8]) -> bool {
+    let mut budget = (HEADER_LEN * bytes.len()) as u64;
+    let mut start = 0;
+    while bytes.len() - start >= HEADER_LEN {
+        let rest = &bytes[start..];
+        let header = Header::parse(rest);
+        let len = header.record_len();
+        if len <= rest.len() as u64 {
+            // the checksum covers everything after itself
+            let hashed = &rest[8..len as usize];
+            if hashed.len() as u64 > budget || checksum(hashed) == header.checksum {
+                return true;
+            }
+            budget -= hashed.len() as u64;
+        }
+        start += 1;
+    }
+    false
+}
+
+/// Opens path for reading and appending, creating it if create_new is set
+fn open_log(path: &Path, create_new: bool) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.read(true).append(true);
+    if create_new {
+        options.create_new(true);
+    } else {
+        options.create(true);
+    }
+    options.open(path)
+}
+
+impl Log {
+    fn read(&self, record: Record) -> io::Result<Vec<u8>> {
+        let mut bytes = vec![0; record.len as usize];
+        // appending ignores the position, so reads can move it wherever they need
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(record.offset))?;
+        file.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Reads the value from record, which must be a record this map wrote
+    fn read_value<V: Codec>(&self, record: Record) -> V {
+        let bytes = self
+            .read(record)
+            .unwrap_or_else(|e| panic!("failed to read {}: {}", self.path.display(), e));
+        let header = Header::parse(&bytes);
+        V::decode(&bytes[HEADER_LEN + header.key_len as usize..]).unwrap_or_else(|| {
+            panic!(
+                "the value at byte {} of {} isn't a valid {}",
+                record.offset,
+                self.path.display(),
+                std::any::type_name::<V>()
+            )
+        })
+    }
+
+    /// Appends bytes to the file, returning the offset they start at
+    fn append(&mut self, bytes: &[u8]) -> io::Result<u64> {
+        self.file.write_all(bytes)?;
+        let offset = self.end;
+        self.end += bytes.len() as u64;
+        Ok(offset)
+    }
+}
+
+/// Returns slot's value, reading it from the log the first time
+fn load<'a, V: Codec>(log: &Log, slot: &'a Slot<V>) -> &'a V {
+    slot.value.get_or_init(|| log.read_value(slot.record))
+}
+
+/// Returns slot's value for changing, so it has to be written again
+fn load_mut<'a, V: Codec>(log: &Log, slot: &'a mut Slot<V>) -> &'a mut V {
+    load(log, slot);
+    slot.dirty = true;
+    slot.value.get_mut().unwrap()
+}
+
+fn take<V: Codec>(log: &Log, slot: Slot<V>) -> V {
+    let record = slot.record;
+    slot.value
+        .into_inner()
+        .unwrap_or_else(|| log.read_value(record))
+}
+
+impl<K: Codec + Hash + Eq, V: Codec> DiskMap<K, V> {
+    /// Opens the map stored in the file at path, or makes a new one there if
+    /// there's no file. If the last record in the file was only partly written,
+    /// because the program stopped part way through writing it, it's cut off.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = open_log(&path, false)?;
+        Self::from_log(file, path, false)
+    }
+
+    /// Returns an empty map in a new file in the temporary directory, which is
+    /// deleted when the map is dropped
+    pub fn temporary() -> io::Result<Self> {
+        loop {
+            let name = format!(
+                "lab08-diskmap-{}-{}.log",
+                process::id(),
+                TEMPORARY_MAPS.fetch_add(1, Ordering::Relaxed)
+            );
+            let path = env::temp_dir().join(name);
+            // a file left behind by an earlier process with the same id is skipped
+            match open_log(&path, true) {
+                Ok(file) => return Self::from_log(file, path, true),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn from_log(file: File, path: PathBuf, temporary: bool) -> io::Result<Self> {
+        let mut map = DiskMap {
+            index: CHashMap::new(),
+            log: Log {
+                file,
+                path,
+                end: 0,
+                dead_bytes: 0,
+            },
+            temporary,
+        };
+        map.replay()?;
+        Ok(map)
+    }
+
+    /// Builds the index from the records in the log
+    fn replay(&mut self) -> io::Result<()> {
+        let len = self.log.file.metadata()?.len();
+        let mut reader = BufReader::new(&self.log.file);
+        reader.seek(SeekFrom::Start(0))?;
+        let corrupt = |offset| {
+            invalid_data(format!(
+                "the record at byte {} of {} is corrupt",
+                offset,
+                self.log.path.display()
+            ))
+        };
+        let mut bytes = Vec::new();
+        let mut offset = 0;
+        while len - offset >= HEADER_LEN as u64 {
+            bytes.resize(HEADER_LEN, 0);
+            reader.read_exact(&mut bytes)?;
+            let header = Header::parse(&bytes);
+            let record = Record {
+                offset,
+                len: header.record_len(),
+            };
+            // only the last record can have been cut off by a crash. Its
+            // lengths can't be checked without the rest of it, so it has to
+            // be the end of the file
+            if record.len > len - offset {
+                bytes.resize((len - offset) as usize, 0);
+                reader.read_exact(&mut bytes[HEADER_LEN..])?;
+                // nothing can come after a record that a crash cut off, so if
+                // a record does, this one was corrupted rather than cut off
+                if may_contain_record(&bytes[1..]) {
+                    return Err(corrupt(offset));
+                }
+                break;
+            }
+            bytes.resize(record.len as usize, 0);
+            reader.read_exact(&mut bytes[HEADER_LEN..])?;
+            if checksum(&bytes[8..]) != header.checksum {
+                if record.len == len - offset {
+                    break;
+                }
+                return Err(corrupt(offset));
+            }
+            let key_bytes = &bytes[HEADER_LEN..HEADER_LEN + header.key_len as usize];
+            let key = K::decode(key_bytes).ok_or_else(|| {
+                invalid_data(format!(
+                    "the key at byte {} of {} isn't a valid {}",
+                    offset,
+                    self.log.path.display(),
+                    std::any::type_name::<K>()
+                ))
+            })?;
+            let old = if header.value_len == TOMBSTONE {
+                self.log.dead_bytes += record.len;
+                self.index.remove(&key)
+            } else {
+                let slot = Slot {
+                    record,
+                    value: OnceCell::new(),
+                    dirty: false,
+                };
+                self.index.insert(key, slot)
+            };
+            if let Some(old) = old {
+                self.log.dead_bytes += old.record.len;
+            }
+            offset += record.len;
+        }
+        drop(reader);
+        if offset < len {
+            self.log.file.set_len(offset)?;
+        }
+        self.log.end = offset;
+        Ok(())
+    }
+
+    /// Appends a record for key and value, or a tombstone if value is None
+    fn write_record(&mut self, key: &K, value: Option<&V>) -> Record {
+        let mut bytes = Vec::new();
+        encode_record(key, value, &mut bytes);
+        let offset = self
+            .log
+            .append(&bytes)
+            .unwrap_or_else(|e| panic!("failed to write to {}: {}", self.log.path.display(), e));
+        Record {
+            offset,
+            len: bytes.len() as u64,
+        }
+    }
+
+    fn compact_if_wasteful(&mut self) {
+        if self.log.dead_bytes >= COMPACT_MIN_DEAD_BYTES && self.log.dead_bytes * 2 > self.log.end {
+            self.compact()
+                .unwrap_or_else(|e| panic!("failed to compact {}: {}", self.log.path.display(), e));
+        }
+    }
+}
+
+impl<K: Codec, V: Codec> DiskMap<K, V> {
+    pub fn path(&self) -> &Path {
+        &self.log.path
+    }
+
+    /// Returns the length of the log, in bytes
+    pub fn log_len(&self) -> u64 {
+        self.log.end
+    }
+
+    /// Returns how many bytes of the log are records that compact would remove
+    pub fn dead_bytes(&self) -> u64 {
+        self.log.dead_bytes
+    }
+
+    /// Writes every value that has been borrowed mutably since it was last
+    /// written, and waits until the whole log is on disk
+    pub fn flush(&mut self) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        let mut lens = Vec::new();
+        for (key, slot) in self.index.iter().filter(|(_, slot)| slot.dirty) {
+            let start = bytes.len();
+            encode_record(key, slot.value.get(), &mut bytes);
+            lens.push((bytes.len() - start) as u64);
+        }
+        if !bytes.is_empty() {
+            let mut offset = self.log.append(&bytes)?;
+            let slots = self.index.iter_mut().filter(|(_, slot)| slot.dirty);
+            for ((_, slot), len) in slots.zip(lens) {
+                self.log.dead_bytes += slot.record.len;
+                slot.record = Record { offset, len };
+                slot.dirty = false;
+                offset += len;
+            }
+        }
+        self.log.file.sync_data()
+    }
+
+    /// Rewrites the log with only the latest record for each key, in a new
+    /// file that then replaces the old one. This also flushes the map.
+    pub fn compact(&mut self) -> io::Result<()> {
+        let mut compacted = self.log.path.clone().into_os_string();
+        compacted.push(".compact");
+        let compacted = PathBuf::from(compacted);
+        let records = match self.write_compacted(&compacted) {
+            Ok(records) => records,
+            Err(e) => {
+                let _ = fs::remove_file(&compacted);
+                return Err(e);
+            }
+        };
+        fs::rename(&compacted, &self.log.path)?;
+        self.log.file = open_log(&self.log.path, false)?;
+        self.log.end = records.last().map_or(0, |r| r.offset + r.len);
+        self.log.dead_bytes = 0;
+        for ((_, slot), record) in self.index.iter_mut().zip(records) {
+            slot.record = record;
+            slot.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Writes the latest record for each key to path, returning where they are in it
+    fn write_compacted(&self, path: &Path) -> io::Result<Vec<Record>> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let mut records = Vec::new();
+        let mut offset = 0;
+        for (key, slot) in self.index.iter() {
+            let bytes = match slot.value.get() {
+                Some(value) => {
+                    let mut bytes = Vec::new();
+                    encode_record(key, Some(value), &mut bytes);
+                    bytes
+                }
+                None => self.log.read(slot.record)?,
+            };
+            writer.write_all(&bytes)?;
+            let len = bytes.len() as u64;
+            records.push(Record { offset, len });
+            offset += len;
+        }
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        Ok(records)
+    }
+}
+
+impl<K: Codec, V: Codec> Drop for DiskMap<K, V> {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = fs::remove_file(&self.log.path);
+        } else {
+            // errors can't be returned from here, so call flush first to see them
+            let _ = self.flush();
+        }
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    slots: chashmap::Iter<'a, K, Slot<V>>,
+    log: &'a Log,
+}
+
+impl<'a, K, V: Codec> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, slot) = self.slots.next()?;
+        Some((key, load(self.log, slot)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.slots.size_hint()
+    }
+}
+
+pub struct IterMut<'a, K, V> {
+    slots: chashmap::IterMut<'a, K, Slot<V>>,
+    log: &'a Log,
+}
+
+impl<'a, K, V: Codec> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, slot) = self.slots.next()?;
+        Some((key, load_mut(self.log, slot)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.slots.size_hint()
+    }
+}
+
+impl<'a, K: Codec + Hash + Eq, V: Codec> IntoIterator for &'a DiskMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: Codec + Hash + Eq, V: Codec> IntoIterator for &'a mut DiskMap<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K: Codec, V: Codec> IntoIterator for DiskMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    /// Returns the entries, leaving the file as it is
+    fn into_iter(mut self) -> Self::IntoIter {
+        if !self.temporary {
+            self.flush()
+                .unwrap_or_else(|e| panic!("failed to flush {}: {}", self.log.path.display(), e));
+        }
+        let index = std::mem::replace(
+            &mut self.index,
+            CHashMap::with_hasher(SipBuildHasher::default()),
+        );
+        index
+            .into_iter()
+            .map(|(key, slot)| (key, take(&self.log, slot)))
+            .collect::<Vec<(K, V)>>()
+            .into_iter()
+    }
+}
+
+impl<K: Codec + Hash + Eq, V: Codec> Map61B for DiskMap<K, V> {
+    type Key = K;
+    type Value = V;
+    type Iter<'a>
+        = Iter<'a, K, V>
+    where
+        Self: 'a;
+    type IterMut<'a>
+        = IterMut<'a, K, V>
+    where
+        Self: 'a;
+
+    /// Returns a temporary map. Use open for one that's kept.
+    fn new() -> Self {
+        Self::temporary().unwrap_or_else(|e| panic!("failed to make a temporary DiskMap: {}", e))
+    }
+
+    fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    fn clear(&mut self) {
+        self.index.clear();
+        self.log
+            .file
+            .set_len(0)
+            .unwrap_or_else(|e| panic!("failed to clear {}: {}", self.log.path.display(), e));
+        self.log.end = 0;
+        self.log.dead_bytes = 0;
+    }
+
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.index.contains_key(key)
+    }
+
+    fn insert(&mut self, key: Self::Key, value: Self::Value) -> Option<Self::Value> {
+        let record = self.write_record(&key, Some(&value));
+        // the value is read back if it's looked at, rather than kept in memory
+        let slot = Slot {
+            record,
+            value: OnceCell::new(),
+            dirty: false,
+        };
+        // the old value has to be read before compacting drops its record
+        let old = self.index.insert(key, slot).map(|old| {
+            self.log.dead_bytes += old.record.len;
+            take(&self.log, old)
+        });
+        self.compact_if_wasteful();
+        old
+    }
+
+    fn get<Q>(&self, key: &Q) -> Option<&Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = self.index.get(key)?;
+        Some(load(&self.log, slot))
+    }
+
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = self.index.get_mut(key)?;
+        Some(load_mut(&self.log, slot))
+    }
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (key, slot) = self.index.remove_entry(key)?;
+        let tombstone = self.write_record(&key, None);
+        self.log.dead_bytes += slot.record.len + tombstone.len;
+        let value = take(&self.log, slot);
+        self.compact_if_wasteful();
+        Some(value)
+    }
+
+    fn insert_vacant(&mut self, key: Self::Key, value: Self::Value) -> &mut Self::Value {
+        // the caller can change the value through the reference this returns,
+        // so it's left for flush to write, once
+        let slot = Slot {
+            record: UNWRITTEN,
+            value: OnceCell::from(value),
+            dirty: true,
+        };
+        self.index.insert_vacant(key, slot).value.get_mut().unwrap()
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        Iter {
+            slots: self.index.iter(),
+            log: &self.log,
+        }
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        IterMut {
+            slots: self.index.iter_mut(),
+            log: &self.log,
+        }
+    }
+
+    /// Keeps only the entries for which f returns true, writing a tombstone
+    /// for each of the others. The log is never emptied, unlike by drain, so
+    /// a crash part way through leaves the map as it was before, or after.
+    fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        // the tombstones are written all at once after the index is done with the log
+        let mut tombstones = Vec::new();
+        let mut dead_bytes = 0;
+        let log = &self.log;
+        Map61B::retain(&mut self.index, |key, slot| {
+            if f(key, load_mut(log, slot)) {
+                return true;
+            }
+            let start = tombstones.len();
+            encode_record::<K, V>(key, None, &mut tombstones);
+            dead_bytes += slot.record.len + (tombstones.len() - start) as u64;
+            false
+        });
+        if !tombstones.is_empty() {
+            self.log.append(&tombstones).unwrap_or_else(|e| {
+                panic!("failed to write to {}: {}", self.log.path.display(), e)
+            });
+            self.log.dead_bytes += dead_bytes;
+            self.compact_if_wasteful();
+        }
+    }
+
+    /// Removes every entry, emptying the file
+    fn drain(&mut self) -> Self::IntoIter {
+        let entries: Vec<(K, V)> = Map61B::drain(&mut self.index)
+            .map(|(key, slot)| (key, take(&self.log, slot)))
+            .collect();
+        self.clear();
+        entries.into_iter()
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{BuildHasherDefault, Hasher},
+};
+
+/*
+Hashers for the hashmaps in this crate, which are generic over a BuildHasher
+just like std::collections::HashMap. A BuildHasher makes a new Hasher for
+every key, the key feeds its bytes into the Hasher, and finish returns the hash.
+
+SipHash is the default, since it mixes every bit of the key into the hash.
+With secret random keys, like std's RandomState gives it, it's also hard to
+find collisions for even when an attacker chooses the map's keys, but
+SipBuildHasher always uses the same keys, so the maps here don't get that
+protection unless they're made with_hasher(RandomState::new()). FxHash and FNV
+are much faster on small keys, but mix the bits less thoroughly. BadHasher is
+deliberately terrible, to see how each map copes with lots of collisions.
+*/
+
+/// SipHash with fixed keys, which is what the maps used before they took a hasher
+pub type SipBuildHasher = BuildHasherDefault<DefaultHasher>;
+pub type FxBuildHasher = BuildHasherDefault<FxHasher>;
+pub type FnvBuildHasher = BuildHasherDefault<FnvHasher>;
+pub type BadBuildHasher = BuildHasherDefault<BadHasher>;
+
+/// The hash used inside rustc: each word is mixed in with a rotate, an xor and a multiply
+#[derive(Default)]
+pub struct FxHasher {
+    hash: u64,
+}
+
+const FX_SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+impl FxHasher {
+    fn add_to_hash(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(FX_SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in chunks.by_ref() {
+            self.add_to_hash(u64::from_le_bytes(chunk.try_into().unwrap()));
+        }
+        for &byte in chunks.remainder() {
+            self.add_to_hash(byte as u64);
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.add_to_hash(i);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// 64-bit FNV-1a: each byte is xored in and then multiplied by a prime
+pub struct FnvHasher {
+    hash: u64,
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x100_0000_01b3;
+
+impl Default for FnvHasher {
+    fn default() -> Self {
+        FnvHasher {
+            hash: FNV_OFFSET_BASIS,
+        }
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.hash ^= byte as u64;
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// Hashes a key to the sum of its bytes, so that a usize key has at most
+/// 8 * 255 + 1 different hashes, and keys like 1 and 256 collide
+#[derive(Default)]
+pub struct BadHasher {
+    hash: u64,
+}
+
+impl Hasher for BadHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.hash = self.hash.wrapping_add(byte as u64);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
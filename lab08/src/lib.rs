@@ -1,14 +1,39 @@
+use std::borrow::Borrow;
+use std::hash::Hash;
+
+use entry::{Entry, OccupiedEntry, VacantEntry};
+
 pub mod chashmap;
+pub mod codec;
+pub mod diskmap;
+pub mod entry;
+pub mod hashers;
 pub mod myhashmap;
 pub mod ohashmap;
+pub mod policy;
+pub mod probing;
+pub mod rhhashmap;
+pub mod sharded;
+pub mod shashmap;
 mod std_types;
 #[cfg(test)]
 mod tests;
 pub mod ullmap;
 
+/*
+Lookups take any borrowed form of the key, like a &str for a map with String keys,
+as long as it hashes and compares the same way as the key. The maps here hash
+their keys rather than ordering them, so lookups need Hash and Eq, like HashMap's.
+*/
 pub trait Map61B: IntoIterator<Item = (Self::Key, Self::Value)> {
     type Key;
     type Value;
+    type Iter<'a>: Iterator<Item = (&'a Self::Key, &'a Self::Value)>
+    where
+        Self: 'a;
+    type IterMut<'a>: Iterator<Item = (&'a Self::Key, &'a mut Self::Value)>
+    where
+        Self: 'a;
 
     fn new() -> Self;
     fn len(&self) -> usize;
@@ -16,12 +41,85 @@ pub trait Map61B: IntoIterator<Item = (Self::Key, Self::Value)> {
         self.len() == 0
     }
     fn clear(&mut self);
-    fn contains_key(&self, key: &Self::Key) -> bool;
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Self::Key: Borrow<Q>,
+        Q: Hash + Eq + ?Sized;
 
     fn insert(&mut self, key: Self::Key, value: Self::Value) -> Option<Self::Value>;
 
-    fn get(&self, key: &Self::Key) -> Option<&Self::Value>;
-    fn get_mut(&mut self, key: &Self::Key) -> Option<&mut Self::Value>;
+    fn get<Q>(&self, key: &Q) -> Option<&Self::Value>
+    where
+        Self::Key: Borrow<Q>,
+        Q: Hash + Eq + ?Sized;
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Self::Value>
+    where
+        Self::Key: Borrow<Q>,
+        Q: Hash + Eq + ?Sized;
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<Self::Value>
+    where
+        Self::Key: Borrow<Q>,
+        Q: Hash + Eq + ?Sized;
+
+    /// Inserts a key that isn't in the map yet, returning a reference to its value.
+    /// This is what VacantEntry::insert uses.
+    fn insert_vacant(&mut self, key: Self::Key, value: Self::Value) -> &mut Self::Value;
+
+    /// Returns the entry for key, to look at or change its value in place.
+    /// By default this looks the key up twice, with contains_key and get_mut, and
+    /// a vacant key is looked up a third time when the entry is filled in, so maps
+    /// that can find a key's position in one lookup override it.
+    fn entry(&mut self, key: Self::Key) -> Entry<'_, Self>
+    where
+        Self::Key: Hash + Eq,
+    {
+        // the borrow checker won't let one lookup return the value in one branch
+        // and the map in the other
+        if self.contains_key(&key) {
+            let value = self.get_mut(&key).unwrap();
+            Entry::Occupied(OccupiedEntry { key, value })
+        } else {
+            Entry::Vacant(VacantEntry { key, map: self })
+        }
+    }
+
+    /// Returns an iterator over the entries, in the same order as into_iter
+    fn iter(&self) -> Self::Iter<'_>;
+    /// Returns an iterator over the entries that allows the values to be modified
+    fn iter_mut(&mut self) -> Self::IterMut<'_>;
 
-    fn remove(&mut self, key: &Self::Key) -> Option<Self::Value>;
+    fn keys(&self) -> impl Iterator<Item = &Self::Key> {
+        self.iter().map(|(k, _)| k)
+    }
+    fn values(&self) -> impl Iterator<Item = &Self::Value> {
+        self.iter().map(|(_, v)| v)
+    }
+    fn values_mut(&mut self) -> impl Iterator<Item = &mut Self::Value> {
+        self.iter_mut().map(|(_, v)| v)
+    }
+
+    /// Removes every entry, and returns them in the order of into_iter. This replaces
+    /// the map with a new one, so maps with a hasher, a resize policy or a reserved
+    /// capacity override it to keep them.
+    fn drain(&mut self) -> Self::IntoIter
+    where
+        Self: Sized,
+    {
+        std::mem::replace(self, Self::new()).into_iter()
+    }
+
+    /// Removes every entry for which f returns false. This drains the map and puts
+    /// back the entries it keeps, so maps that override drain should override this too.
+    fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Self::Key, &mut Self::Value) -> bool,
+        Self: Sized,
+    {
+        for (key, mut value) in self.drain() {
+            if f(&key, &mut value) {
+                self.insert_vacant(key, value);
+            }
+        }
+    }
 }
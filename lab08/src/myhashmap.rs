@@ -1,71 +1,254 @@
 use std::{
-    collections::hash_map::DefaultHasher,
-    hash::{Hash, Hasher},
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+    slice,
 };
 
-use crate::Map61B;
+use crate::{
+    entry::{Entry, OccupiedEntry, VacantEntry},
+    hashers::SipBuildHasher,
+    policy::{LoadFactor, ResizePolicy},
+    Map61B,
+};
 
 const STARTING_BUCKETS: usize = 10;
-const RESIZE_FACTOR: usize = 4; // newlen = r * (# elements)
-const MAX_LOAD_FACTOR: f64 = 0.75;
-const MIN_LOAD_FACTOR: f64 = 0.05; // only applicable when buckets.len() > STARTING_BUCKETS
+// shrinking never goes below min_buckets
+const DEFAULT_POLICY: ResizePolicy = ResizePolicy::new(0.05, 0.25, 0.75);
 
-pub struct MyHashMap<K, V> {
+pub struct MyHashMap<K, V, S = SipBuildHasher> {
     buckets: Vec<Vec<(K, V)>>,
     num_elements: usize,
+    hash_builder: S,
+    policy: ResizePolicy,
+    /// the fewest buckets removing shrinks the map to, which with_capacity and
+    /// reserve raise so the map doesn't shrink below the capacity it was given
+    min_buckets: usize,
 }
 
 /*
 The following helper functions may be useful. Feel free to delete or modify them.
 */
 
-fn hash_index<K: Hash>(key: &K, len: usize) -> usize {
-    let mut h = DefaultHasher::new();
-    key.hash(&mut h);
-    (h.finish() % (len as u64)) as usize
+fn hash_index<Q: Hash + ?Sized, S: BuildHasher>(hash_builder: &S, key: &Q, len: usize) -> usize {
+    (hash_builder.hash_one(key) % (len as u64)) as usize
 }
 
-fn create_buckets<T>(num_buckets: usize) -> Vec<Vec<T>> {
-    (0..num_buckets).map(|_| Vec::new()).collect()
+/// Returns how many buckets are needed to hold capacity entries without resizing
+fn buckets_for(policy: &ResizePolicy, capacity: usize) -> usize {
+    STARTING_BUCKETS.max(policy.buckets_for(capacity))
 }
 
-fn loading<K, V>(h: &MyHashMap<K, V>) -> f64 {
-    h.num_elements as f64 / h.buckets.len() as f64
+/// Returns how many buckets to resize to when there are num_elements entries
+fn resized_len(policy: &ResizePolicy, num_elements: usize) -> usize {
+    STARTING_BUCKETS.max(policy.resized_buckets(num_elements))
+}
+
+fn create_buckets<T>(num_buckets: usize) -> Vec<Vec<T>> {
+    (0..num_buckets).map(|_| Vec::new()).collect()
 }
 
-impl<K: Hash, V> MyHashMap<K, V> {
+impl<K: Hash, V, S: BuildHasher> MyHashMap<K, V, S> {
     fn resize(&mut self, capacity: usize) {
         let mut new_buckets = create_buckets(capacity);
         let cur_buckets = std::mem::take(&mut self.buckets);
         for bucket in cur_buckets.into_iter() {
             for item in bucket.into_iter() {
-                let index = hash_index(&item.0, new_buckets.len());
+                let index = hash_index(&self.hash_builder, &item.0, new_buckets.len());
                 new_buckets[index].push((item.0, item.1));
             }
         }
         self.buckets = new_buckets;
     }
+
+    /// Makes room for at least additional more entries before the map has to resize
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self.num_elements + additional;
+        self.min_buckets = self.min_buckets.max(buckets_for(&self.policy, needed));
+        if needed > self.capacity() {
+            self.resize(buckets_for(&self.policy, needed));
+        }
+    }
+
+    /// Shrinks the map to as few buckets as it can hold its entries in,
+    /// giving up any capacity reserved before
+    pub fn shrink_to_fit(&mut self) {
+        self.min_buckets = STARTING_BUCKETS;
+        let len = buckets_for(&self.policy, self.num_elements);
+        if len < self.buckets.len() {
+            self.resize(len);
+        }
+    }
+
+    /// Changes when the map resizes, growing it straight away if it's too full
+    pub fn set_policy(&mut self, policy: ResizePolicy) {
+        self.policy = policy;
+        if policy.should_grow(self.num_elements, self.buckets.len()) {
+            self.resize(resized_len(&policy, self.num_elements));
+        }
+    }
+}
+
+impl<K, V> MyHashMap<K, V> {
+    /// Returns an empty map with room for capacity entries before it has to resize
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, SipBuildHasher::default())
+    }
 }
 
-impl<K, V> IntoIterator for MyHashMap<K, V> {
+impl<K, V, S> MyHashMap<K, V, S> {
+    /// Returns an empty map that hashes its keys with hash_builder
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self::with_capacity_and_hasher(0, hash_builder)
+    }
+
+    /// Returns an empty map with room for capacity entries before it has to resize,
+    /// that hashes its keys with hash_builder
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        let len = buckets_for(&DEFAULT_POLICY, capacity);
+        MyHashMap {
+            buckets: create_buckets(len),
+            num_elements: 0,
+            hash_builder,
+            policy: DEFAULT_POLICY,
+            min_buckets: len,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    pub fn policy(&self) -> &ResizePolicy {
+        &self.policy
+    }
+
+    /// Returns how many entries the map can hold before it has to resize
+    pub fn capacity(&self) -> usize {
+        self.policy.capacity(self.buckets.len())
+    }
+
+    /// Returns an iterator over the entries, in no particular order
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            buckets: self.buckets.iter(),
+            bucket: [].iter(),
+            remaining: self.num_elements,
+        }
+    }
+
+    /// Returns an iterator over the entries, in no particular order,
+    /// that allows the values to be modified
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            buckets: self.buckets.iter_mut(),
+            bucket: [].iter_mut(),
+            remaining: self.num_elements,
+        }
+    }
+}
+
+impl<K, V, S> LoadFactor for MyHashMap<K, V, S> {
+    fn load_factor(&self) -> f64 {
+        self.num_elements as f64 / self.buckets.len() as f64
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    /// the buckets after the current one
+    buckets: slice::Iter<'a, Vec<(K, V)>>,
+    /// the rest of the current bucket
+    bucket: slice::Iter<'a, (K, V)>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((k, v)) = self.bucket.next() {
+                self.remaining -= 1;
+                return Some((k, v));
+            }
+            self.bucket = self.buckets.next()?.iter();
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+pub struct IterMut<'a, K, V> {
+    buckets: slice::IterMut<'a, Vec<(K, V)>>,
+    bucket: slice::IterMut<'a, (K, V)>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((k, v)) = self.bucket.next() {
+                self.remaining -= 1;
+                return Some((k, v));
+            }
+            self.bucket = self.buckets.next()?.iter_mut();
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a MyHashMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a mut MyHashMap<K, V, S> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K, V, S> IntoIterator for MyHashMap<K, V, S> {
     type Item = (K, V);
     type IntoIter = std::vec::IntoIter<(K, V)>;
 
     fn into_iter(self) -> Self::IntoIter {
-        unimplemented!()
+        self.buckets
+            .into_iter()
+            .flatten()
+            .collect::<Vec<(K, V)>>()
+            .into_iter()
     }
 }
 
-impl<K: Hash + Eq, V> Map61B for MyHashMap<K, V> {
+impl<K: Hash + Eq, V, S: BuildHasher + Default> Map61B for MyHashMap<K, V, S> {
     type Key = K;
 
     type Value = V;
+    type Iter<'a>
+        = Iter<'a, K, V>
+    where
+        Self: 'a;
+    type IterMut<'a>
+        = IterMut<'a, K, V>
+    where
+        Self: 'a;
 
     fn new() -> Self {
-        MyHashMap {
-            buckets: create_buckets(STARTING_BUCKETS),
-            num_elements: 0,
-        }
+        Self::with_hasher(S::default())
     }
 
     fn len(&self) -> usize {
@@ -73,20 +256,22 @@ impl<K: Hash + Eq, V> Map61B for MyHashMap<K, V> {
     }
 
     fn clear(&mut self) {
-        self.buckets.clear();
-        self.buckets = create_buckets(STARTING_BUCKETS);
+        for bucket in self.buckets.iter_mut() {
+            bucket.clear();
+        }
         self.num_elements = 0;
     }
 
-    fn contains_key(&self, key: &Self::Key) -> bool {
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
         self.get(key).is_some()
     }
 
     fn insert(&mut self, key: Self::Key, value: Self::Value) -> Option<Self::Value> {
-        if loading(self) > MAX_LOAD_FACTOR {
-            self.resize(self.num_elements * RESIZE_FACTOR);
-        }
-        let index = hash_index(&key, self.buckets.len());
+        let index = hash_index(&self.hash_builder, &key, self.buckets.len());
         let bucket = self.buckets.get_mut(index).unwrap();
         for item in bucket.iter_mut() {
             if key == item.0 {
@@ -96,16 +281,26 @@ impl<K: Hash + Eq, V> Map61B for MyHashMap<K, V> {
         }
         bucket.push((key, value));
         self.num_elements += 1;
+        if self
+            .policy
+            .should_grow(self.num_elements, self.buckets.len())
+        {
+            self.resize(resized_len(&self.policy, self.num_elements));
+        }
         None
     }
 
-    fn get(&self, key: &Self::Key) -> Option<&Self::Value> {
-        let index = hash_index(&key, self.buckets.len());
+    fn get<Q>(&self, key: &Q) -> Option<&Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let index = hash_index(&self.hash_builder, key, self.buckets.len());
         let bucket = self.buckets.get(index);
         match bucket {
             Some(b) => {
                 for item in b.iter() {
-                    if *key == item.0 {
+                    if item.0.borrow() == key {
                         return Some(&item.1);
                     }
                 }
@@ -115,13 +310,17 @@ impl<K: Hash + Eq, V> Map61B for MyHashMap<K, V> {
         }
     }
 
-    fn get_mut(&mut self, key: &Self::Key) -> Option<&mut Self::Value> {
-        let index = hash_index(&key, self.buckets.len());
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let index = hash_index(&self.hash_builder, key, self.buckets.len());
         let bucket = self.buckets.get_mut(index);
         match bucket {
             Some(b) => {
                 for item in b.iter_mut() {
-                    if *key == item.0 {
+                    if item.0.borrow() == key {
                         return Some(&mut item.1);
                     }
                 }
@@ -131,7 +330,79 @@ impl<K: Hash + Eq, V> Map61B for MyHashMap<K, V> {
         }
     }
 
-    fn remove(&mut self, key: &Self::Key) -> Option<Self::Value> {
-        unimplemented!()
+    fn remove<Q>(&mut self, key: &Q) -> Option<Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let index = hash_index(&self.hash_builder, key, self.buckets.len());
+        let bucket = &mut self.buckets[index];
+        let position = bucket.iter().position(|item| item.0.borrow() == key)?;
+        let (_, value) = bucket.swap_remove(position);
+        self.num_elements -= 1;
+        if self.buckets.len() > self.min_buckets
+            && self
+                .policy
+                .should_shrink(self.num_elements, self.buckets.len())
+        {
+            self.resize(resized_len(&self.policy, self.num_elements).max(self.min_buckets));
+        }
+        Some(value)
+    }
+
+    fn insert_vacant(&mut self, key: Self::Key, value: Self::Value) -> &mut Self::Value {
+        // resize first, since resizing moves every entry
+        self.num_elements += 1;
+        if self
+            .policy
+            .should_grow(self.num_elements, self.buckets.len())
+        {
+            self.resize(resized_len(&self.policy, self.num_elements));
+        }
+        let index = hash_index(&self.hash_builder, &key, self.buckets.len());
+        let bucket = &mut self.buckets[index];
+        bucket.push((key, value));
+        &mut bucket.last_mut().unwrap().1
+    }
+
+    fn entry(&mut self, key: K) -> Entry<'_, Self> {
+        // finding the key's position rather than a reference to its value lets
+        // the vacant branch have the map back, so the key is only looked up once
+        let index = hash_index(&self.hash_builder, &key, self.buckets.len());
+        match self.buckets[index].iter().position(|item| item.0 == key) {
+            Some(position) => Entry::Occupied(OccupiedEntry {
+                key,
+                value: &mut self.buckets[index][position].1,
+            }),
+            None => Entry::Vacant(VacantEntry { key, map: self }),
+        }
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        MyHashMap::iter(self)
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        MyHashMap::iter_mut(self)
+    }
+
+    fn drain(&mut self) -> Self::IntoIter {
+        // keeps the buckets, like clear
+        self.num_elements = 0;
+        self.buckets
+            .iter_mut()
+            .flat_map(|bucket| bucket.drain(..))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        for bucket in self.buckets.iter_mut() {
+            bucket.retain_mut(|(k, v)| f(k, v));
+        }
+        self.num_elements = self.buckets.iter().map(Vec::len).sum();
     }
 }
@@ -1,14 +1,19 @@
 use std::{
-    collections::hash_map::DefaultHasher,
-    hash::{Hash, Hasher},
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+    slice,
 };
 
-use crate::Map61B;
+use crate::{
+    hashers::SipBuildHasher,
+    policy::{LoadFactor, ResizePolicy},
+    probing::{ProbeLengths, ProbeStats},
+    Map61B,
+};
 
 const STARTING_BUCKETS: usize = 10;
-const RESIZE_FACTOR: usize = 4; // newlen = r * (# elements)
-const MAX_LOAD_FACTOR: f64 = 0.75;
-const MIN_LOAD_FACTOR: f64 = 0.05;
+// the max load factor counts Removed buckets too
+const DEFAULT_POLICY: ResizePolicy = ResizePolicy::new(0.05, 0.25, 0.75);
 
 enum Bucket<K, V> {
     Full(K, V),
@@ -17,29 +22,48 @@ enum Bucket<K, V> {
 }
 
 // open-addressed, linear-probing hashmap
-pub struct OHashMap<K, V> {
+pub struct OHashMap<K, V, S = SipBuildHasher> {
     buckets: Vec<Bucket<K, V>>,
     num_elements: usize,
     num_removed: usize,
+    hash_builder: S,
+    policy: ResizePolicy,
+    /// the fewest buckets removing shrinks the map to, which with_capacity and
+    /// reserve raise so the map doesn't shrink below the capacity it was given
+    min_buckets: usize,
+}
+
+fn hash_index<Q: Hash + ?Sized, S: BuildHasher>(hash_builder: &S, key: &Q, len: usize) -> usize {
+    (hash_builder.hash_one(key) % (len as u64)) as usize
 }
 
-fn hash_index<K: Hash>(key: &K, len: usize) -> usize {
-    let mut h = DefaultHasher::new();
-    key.hash(&mut h);
-    (h.finish() % (len as u64)) as usize
+/// Returns how many buckets are needed to hold capacity entries without resizing
+fn buckets_for(policy: &ResizePolicy, capacity: usize) -> usize {
+    STARTING_BUCKETS.max(policy.buckets_for(capacity))
+}
+
+/// Returns how many buckets to resize to when there are num_elements entries
+fn resized_len(policy: &ResizePolicy, num_elements: usize) -> usize {
+    STARTING_BUCKETS.max(policy.resized_buckets(num_elements))
 }
 
 fn create_buckets<K, V>(num_buckets: usize) -> Vec<Bucket<K, V>> {
     (0..num_buckets).map(|_| Bucket::Empty).collect()
 }
 
-impl<K: Hash + Eq, V> OHashMap<K, V> {
-    fn find_index(&self, key: &K) -> usize {
-        let mut i = hash_index(key, self.buckets.len());
+impl<K: Hash + Eq, V, S: BuildHasher> OHashMap<K, V, S> {
+    fn find_index<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut i = hash_index(&self.hash_builder, key, self.buckets.len());
+        // this ends, because Removed buckets count towards the load factor and are never
+        // reused, and the max load factor is below 1, so some buckets are always Empty
         loop {
             match &self.buckets[i] {
                 Bucket::Full(k, _) => {
-                    if k == key {
+                    if k.borrow() == key {
                         break i;
                     }
                 }
@@ -51,18 +75,202 @@ impl<K: Hash + Eq, V> OHashMap<K, V> {
     }
 
     fn resize(&mut self, new_len: usize) {
+        self.rebuild(new_len, |_, _| true);
+    }
+
+    /// Moves the entries for which keep returns true into new_len new buckets
+    fn rebuild(&mut self, new_len: usize, mut keep: impl FnMut(&K, &mut V) -> bool) {
         let old_buckets = std::mem::replace(&mut self.buckets, create_buckets(new_len));
+        self.num_elements = 0;
+        self.num_removed = 0;
         for b in old_buckets {
+            if let Bucket::Full(k, mut v) = b {
+                if keep(&k, &mut v) {
+                    let i = self.find_index(&k);
+                    self.buckets[i] = Bucket::Full(k, v);
+                    self.num_elements += 1;
+                }
+            }
+        }
+    }
+
+    /// Makes room for at least additional more entries before the map has to resize
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self.num_elements + additional;
+        self.min_buckets = self.min_buckets.max(buckets_for(&self.policy, needed));
+        if needed + self.num_removed > self.capacity() {
+            self.resize(buckets_for(&self.policy, needed));
+        }
+    }
+
+    /// Shrinks the map to as few buckets as it can hold its entries in, giving up
+    /// any capacity reserved before, and clearing out the Removed buckets
+    pub fn shrink_to_fit(&mut self) {
+        self.min_buckets = STARTING_BUCKETS;
+        self.resize(buckets_for(&self.policy, self.num_elements));
+    }
+
+    /// Changes when the map resizes, growing it straight away if it's too full.
+    /// Panics if the max load factor isn't below 1.
+    pub fn set_policy(&mut self, policy: ResizePolicy) {
+        assert!(
+            policy.max_load_factor() < 1.0,
+            "OHashMap needs empty buckets"
+        );
+        self.policy = policy;
+        if policy.should_grow(self.num_elements + self.num_removed, self.buckets.len()) {
+            self.resize(resized_len(&policy, self.num_elements));
+        }
+    }
+}
+
+impl<K, V> OHashMap<K, V> {
+    /// Returns an empty map with room for capacity entries before it has to resize
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, SipBuildHasher::default())
+    }
+}
+
+impl<K, V, S> OHashMap<K, V, S> {
+    /// Returns an empty map that hashes its keys with hash_builder
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self::with_capacity_and_hasher(0, hash_builder)
+    }
+
+    /// Returns an empty map with room for capacity entries before it has to resize,
+    /// that hashes its keys with hash_builder
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        let len = buckets_for(&DEFAULT_POLICY, capacity);
+        Self {
+            buckets: create_buckets(len),
+            num_elements: 0,
+            num_removed: 0,
+            hash_builder,
+            policy: DEFAULT_POLICY,
+            min_buckets: len,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    pub fn policy(&self) -> &ResizePolicy {
+        &self.policy
+    }
+
+    /// Returns how many entries the map can hold before it has to resize,
+    /// if nothing is removed
+    pub fn capacity(&self) -> usize {
+        self.policy.capacity(self.buckets.len())
+    }
+
+    /// Returns an iterator over the entries, in the order of their buckets
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            buckets: self.buckets.iter(),
+            remaining: self.num_elements,
+        }
+    }
+
+    /// Returns an iterator over the entries, in the order of their buckets,
+    /// that allows the values to be modified
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            buckets: self.buckets.iter_mut(),
+            remaining: self.num_elements,
+        }
+    }
+}
+
+impl<K: Hash, V, S: BuildHasher> ProbeLengths for OHashMap<K, V, S> {
+    fn probe_stats(&self) -> ProbeStats {
+        let len = self.buckets.len();
+        let lengths = self
+            .buckets
+            .iter()
+            .enumerate()
+            .filter_map(|(i, b)| match b {
+                Bucket::Full(k, _) => {
+                    Some((i + len - hash_index(&self.hash_builder, k, len)) % len + 1)
+                }
+                _ => None,
+            });
+        ProbeStats::new(len, lengths)
+    }
+}
+
+impl<K, V, S> LoadFactor for OHashMap<K, V, S> {
+    fn load_factor(&self) -> f64 {
+        self.num_elements as f64 / self.buckets.len() as f64
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    buckets: slice::Iter<'a, Bucket<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for b in self.buckets.by_ref() {
             if let Bucket::Full(k, v) = b {
-                let i = self.find_index(&k);
-                self.buckets[i] = Bucket::Full(k, v);
+                self.remaining -= 1;
+                return Some((k, v));
             }
         }
-        self.num_removed = 0;
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+pub struct IterMut<'a, K, V> {
+    buckets: slice::IterMut<'a, Bucket<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for b in self.buckets.by_ref() {
+            if let Bucket::Full(k, v) = b {
+                self.remaining -= 1;
+                return Some((k, v));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a OHashMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
     }
 }
 
-impl<K, V> IntoIterator for OHashMap<K, V> {
+impl<'a, K, V, S> IntoIterator for &'a mut OHashMap<K, V, S> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K, V, S> IntoIterator for OHashMap<K, V, S> {
     type Item = (K, V);
     type IntoIter = std::vec::IntoIter<(K, V)>;
 
@@ -77,16 +285,20 @@ impl<K, V> IntoIterator for OHashMap<K, V> {
     }
 }
 
-impl<K: Hash + Eq, V> Map61B for OHashMap<K, V> {
+impl<K: Hash + Eq, V, S: BuildHasher + Default> Map61B for OHashMap<K, V, S> {
     type Key = K;
     type Value = V;
+    type Iter<'a>
+        = Iter<'a, K, V>
+    where
+        Self: 'a;
+    type IterMut<'a>
+        = IterMut<'a, K, V>
+    where
+        Self: 'a;
 
     fn new() -> Self {
-        Self {
-            buckets: create_buckets(STARTING_BUCKETS),
-            num_elements: 0,
-            num_removed: 0,
-        }
+        Self::with_hasher(S::default())
     }
 
     fn len(&self) -> usize {
@@ -94,12 +306,16 @@ impl<K: Hash + Eq, V> Map61B for OHashMap<K, V> {
     }
 
     fn clear(&mut self) {
-        self.buckets = create_buckets(STARTING_BUCKETS);
+        self.buckets.fill_with(|| Bucket::Empty);
         self.num_elements = 0;
         self.num_removed = 0;
     }
 
-    fn contains_key(&self, key: &Self::Key) -> bool {
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
         self.get(key).is_some()
     }
 
@@ -110,14 +326,18 @@ impl<K: Hash + Eq, V> Map61B for OHashMap<K, V> {
         }
         self.buckets[i] = Bucket::Full(key, value);
         self.num_elements += 1;
-        let loading = (self.num_elements + self.num_removed) as f64 / self.buckets.len() as f64;
-        if loading > MAX_LOAD_FACTOR {
-            self.resize(self.num_elements * RESIZE_FACTOR);
+        let used = self.num_elements + self.num_removed;
+        if self.policy.should_grow(used, self.buckets.len()) {
+            self.resize(resized_len(&self.policy, self.num_elements));
         }
         None
     }
 
-    fn get(&self, key: &Self::Key) -> Option<&Self::Value> {
+    fn get<Q>(&self, key: &Q) -> Option<&Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
         let i = self.find_index(key);
         match self.buckets[i] {
             Bucket::Full(_, ref v) => Some(v),
@@ -125,7 +345,11 @@ impl<K: Hash + Eq, V> Map61B for OHashMap<K, V> {
         }
     }
 
-    fn get_mut(&mut self, key: &Self::Key) -> Option<&mut Self::Value> {
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
         let i = self.find_index(key);
         match self.buckets[i] {
             Bucket::Full(_, ref mut v) => Some(v),
@@ -133,15 +357,22 @@ impl<K: Hash + Eq, V> Map61B for OHashMap<K, V> {
         }
     }
 
-    fn remove(&mut self, key: &Self::Key) -> Option<Self::Value> {
+    fn remove<Q>(&mut self, key: &Q) -> Option<Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
         let i = self.find_index(key);
         if let Bucket::Full(_, _) = self.buckets[i] {
             let prev = std::mem::replace(&mut self.buckets[i], Bucket::Removed);
             self.num_elements -= 1;
             self.num_removed += 1;
-            let loading = self.num_elements as f64 / self.buckets.len() as f64;
-            if loading < MIN_LOAD_FACTOR {
-                self.resize(STARTING_BUCKETS.max(self.num_elements * RESIZE_FACTOR));
+            if self.buckets.len() > self.min_buckets
+                && self
+                    .policy
+                    .should_shrink(self.num_elements, self.buckets.len())
+            {
+                self.resize(resized_len(&self.policy, self.num_elements).max(self.min_buckets));
             }
             if let Bucket::Full(_, v) = prev {
                 return Some(v);
@@ -149,4 +380,53 @@ impl<K: Hash + Eq, V> Map61B for OHashMap<K, V> {
         }
         None
     }
+
+    fn insert_vacant(&mut self, key: Self::Key, value: Self::Value) -> &mut Self::Value {
+        // resize first, since resizing moves every entry
+        let num_elements = self.num_elements + 1;
+        if self
+            .policy
+            .should_grow(num_elements + self.num_removed, self.buckets.len())
+        {
+            self.resize(resized_len(&self.policy, num_elements));
+        }
+        let i = self.find_index(&key);
+        self.num_elements = num_elements;
+        self.buckets[i] = Bucket::Full(key, value);
+        match self.buckets[i] {
+            Bucket::Full(_, ref mut v) => v,
+            _ => unreachable!(),
+        }
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        OHashMap::iter(self)
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        OHashMap::iter_mut(self)
+    }
+
+    fn drain(&mut self) -> Self::IntoIter {
+        // keeps the buckets, like clear
+        let len = self.buckets.len();
+        let buckets = std::mem::replace(&mut self.buckets, create_buckets(len));
+        self.num_elements = 0;
+        self.num_removed = 0;
+        let mut storage = Vec::new();
+        for b in buckets {
+            if let Bucket::Full(k, v) = b {
+                storage.push((k, v));
+            }
+        }
+        storage.into_iter()
+    }
+
+    fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        // rebuilding at the same size also clears out the Removed buckets
+        self.rebuild(self.buckets.len(), f);
+    }
 }
@@ -0,0 +1,77 @@
+/*
+How a hashmap decides when to resize, and how big to make itself. It grows once
+there are more than max_load_factor entries per bucket, and shrinks once there
+are fewer than min_load_factor, unless it's already at its starting size. Either
+way, it resizes to target_load_factor entries per bucket, so that after a resize
+it can grow or shrink a long way before it has to resize again. Resizing to a
+multiple of the number of entries, rather than of the old number of buckets,
+means a map that shrinks after lots of removals ends up the right size.
+
+The open-addressing maps also need max_load_factor to be below 1, since they
+need empty buckets to end their probes.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResizePolicy {
+    min_load_factor: f64,
+    target_load_factor: f64,
+    max_load_factor: f64,
+}
+
+impl ResizePolicy {
+    /// Panics unless 0 <= min_load_factor < target_load_factor < max_load_factor
+    pub const fn new(min_load_factor: f64, target_load_factor: f64, max_load_factor: f64) -> Self {
+        assert!(
+            0.0 <= min_load_factor
+                && min_load_factor < target_load_factor
+                && target_load_factor < max_load_factor,
+            "load factors must satisfy 0 <= min < target < max"
+        );
+        ResizePolicy {
+            min_load_factor,
+            target_load_factor,
+            max_load_factor,
+        }
+    }
+
+    pub fn min_load_factor(&self) -> f64 {
+        self.min_load_factor
+    }
+
+    pub fn target_load_factor(&self) -> f64 {
+        self.target_load_factor
+    }
+
+    pub fn max_load_factor(&self) -> f64 {
+        self.max_load_factor
+    }
+
+    /// Returns how many buckets are needed to hold capacity entries without growing
+    pub fn buckets_for(&self, capacity: usize) -> usize {
+        (capacity as f64 / self.max_load_factor).ceil() as usize
+    }
+
+    /// Returns how many buckets to resize to, for a map with entries entries
+    pub fn resized_buckets(&self, entries: usize) -> usize {
+        (entries as f64 / self.target_load_factor).ceil() as usize
+    }
+
+    /// Returns how many entries num_buckets buckets hold before the map grows
+    pub fn capacity(&self, num_buckets: usize) -> usize {
+        (num_buckets as f64 * self.max_load_factor).floor() as usize
+    }
+
+    /// Returns whether a map with entries entries in num_buckets buckets should grow
+    pub fn should_grow(&self, entries: usize, num_buckets: usize) -> bool {
+        entries as f64 / num_buckets as f64 > self.max_load_factor
+    }
+
+    /// Returns whether a map with entries entries in num_buckets buckets should shrink
+    pub fn should_shrink(&self, entries: usize, num_buckets: usize) -> bool {
+        (entries as f64 / num_buckets as f64) < self.min_load_factor
+    }
+}
+
+/// The hashmaps' number of entries per bucket
+pub trait LoadFactor {
+    fn load_factor(&self) -> f64;
+}
@@ -0,0 +1,538 @@
+use std::{
+    borrow::Borrow,
+    fmt,
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+    slice,
+};
+
+use crate::{
+    hashers::SipBuildHasher,
+    policy::{LoadFactor, ResizePolicy},
+    Map61B,
+};
+
+// must be a power of two
+const STARTING_BUCKETS: usize = 16;
+// the max load factor counts Removed buckets too. Bucket counts are rounded up
+// to a power of two, so the load factor after resizing is at most the target.
+const DEFAULT_POLICY: ResizePolicy = ResizePolicy::new(0.05, 0.375, 0.75);
+
+/// How far entries are from their home bucket in an open-addressing map
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProbeStats {
+    pub entries: usize,
+    pub buckets: usize,
+    /// average number of buckets a successful lookup looks at
+    pub mean: f64,
+    pub variance: f64,
+    pub max: usize,
+}
+
+impl ProbeStats {
+    /// Returns the stats for a map with the given number of buckets, whose entries
+    /// are found after looking at probe_lengths buckets each
+    pub fn new(buckets: usize, probe_lengths: impl IntoIterator<Item = usize>) -> Self {
+        let lengths: Vec<usize> = probe_lengths.into_iter().collect();
+        let entries = lengths.len();
+        if entries == 0 {
+            return ProbeStats {
+                buckets,
+                ..Default::default()
+            };
+        }
+        let mean = lengths.iter().sum::<usize>() as f64 / entries as f64;
+        let variance = lengths
+            .iter()
+            .map(|&l| (l as f64 - mean).powi(2))
+            .sum::<f64>()
+            / entries as f64;
+        ProbeStats {
+            entries,
+            buckets,
+            mean,
+            variance,
+            max: lengths.into_iter().max().unwrap(),
+        }
+    }
+}
+
+impl fmt::Display for ProbeStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} entries in {} buckets, probe length mean {:.2}, variance {:.2}, max {}",
+            self.entries, self.buckets, self.mean, self.variance, self.max
+        )
+    }
+}
+
+/// Open-addressing maps that can report how long their probe sequences are
+pub trait ProbeLengths {
+    fn probe_stats(&self) -> ProbeStats;
+}
+
+/// The order in which an open-addressing map looks at buckets for a key
+pub trait ProbeSequence {
+    /// Returns the i-th bucket to look at for hash, in a table with len buckets.
+    /// len is a power of two, and the first len buckets must all be different.
+    fn bucket(hash: u64, i: usize, len: usize) -> usize;
+}
+
+/// Looks at the buckets hash + 0, hash + 1, hash + 3, hash + 6, ...: the steps grow by
+/// one each time, which spreads out keys with the same home bucket better than linear
+/// probing does, and visits every bucket when len is a power of two
+pub struct Quadratic;
+
+impl ProbeSequence for Quadratic {
+    fn bucket(hash: u64, i: usize, len: usize) -> usize {
+        (hash as usize).wrapping_add(i * (i + 1) / 2) & (len - 1)
+    }
+}
+
+/// Steps by a second hash from the top half of the hash, so keys with the same home
+/// bucket usually go different ways. The step is odd, so it visits every bucket when
+/// len is a power of two.
+pub struct DoubleHashing;
+
+impl ProbeSequence for DoubleHashing {
+    fn bucket(hash: u64, i: usize, len: usize) -> usize {
+        let step = (hash >> 32) as usize | 1;
+        (hash as usize).wrapping_add(i.wrapping_mul(step)) & (len - 1)
+    }
+}
+
+enum Bucket<K, V> {
+    Full(K, V),
+    Removed,
+    Empty,
+}
+
+/*
+An open-addressing hashmap with tombstones, like OHashMap, but generic over the
+order in which it probes buckets. Unlike OHashMap it reuses Removed buckets for
+new entries, and always has a power of two buckets so that every probe sequence
+covers the whole table.
+*/
+pub struct ProbingHashMap<K, V, P, S = SipBuildHasher> {
+    buckets: Vec<Bucket<K, V>>,
+    num_elements: usize,
+    num_removed: usize,
+    hash_builder: S,
+    policy: ResizePolicy,
+    /// the fewest buckets removing shrinks the map to, which with_capacity and
+    /// reserve raise so the map doesn't shrink below the capacity it was given
+    min_buckets: usize,
+    probe: PhantomData<P>,
+}
+
+pub type QHashMap<K, V, S = SipBuildHasher> = ProbingHashMap<K, V, Quadratic, S>;
+pub type DHashMap<K, V, S = SipBuildHasher> = ProbingHashMap<K, V, DoubleHashing, S>;
+
+/// Returns how many buckets are needed to hold capacity entries without resizing
+fn buckets_for(policy: &ResizePolicy, capacity: usize) -> usize {
+    STARTING_BUCKETS.max(policy.buckets_for(capacity).next_power_of_two())
+}
+
+/// Returns how many buckets to resize to when there are num_elements entries
+fn resized_len(policy: &ResizePolicy, num_elements: usize) -> usize {
+    STARTING_BUCKETS.max(policy.resized_buckets(num_elements).next_power_of_two())
+}
+
+fn create_buckets<K, V>(num_buckets: usize) -> Vec<Bucket<K, V>> {
+    (0..num_buckets).map(|_| Bucket::Empty).collect()
+}
+
+impl<K: Hash + Eq, V, P: ProbeSequence, S: BuildHasher> ProbingHashMap<K, V, P, S> {
+    /// Returns Ok with the bucket of key, or Err with the bucket to put it in
+    fn find_index<Q>(&self, key: &Q) -> Result<usize, usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hash_builder.hash_one(key);
+        let len = self.buckets.len();
+        let mut first_removed = None;
+        for i in 0..len {
+            let b = P::bucket(hash, i, len);
+            match &self.buckets[b] {
+                Bucket::Full(k, _) if k.borrow() == key => return Ok(b),
+                Bucket::Full(_, _) => {}
+                Bucket::Removed => {
+                    first_removed.get_or_insert(b);
+                }
+                Bucket::Empty => return Err(first_removed.unwrap_or(b)),
+            }
+        }
+        // the load factor keeps some buckets Empty or Removed, and the probe
+        // sequence has looked at all of them
+        Err(first_removed.expect("the table is full"))
+    }
+
+    fn resize(&mut self, new_len: usize) {
+        self.rebuild(new_len, |_, _| true);
+    }
+
+    /// Moves the entries for which keep returns true into new_len new buckets
+    fn rebuild(&mut self, new_len: usize, mut keep: impl FnMut(&K, &mut V) -> bool) {
+        let old_buckets = std::mem::replace(&mut self.buckets, create_buckets(new_len));
+        self.num_elements = 0;
+        self.num_removed = 0;
+        for b in old_buckets {
+            if let Bucket::Full(k, mut v) = b {
+                if keep(&k, &mut v) {
+                    let Err(i) = self.find_index(&k) else {
+                        unreachable!("keys are unique");
+                    };
+                    self.buckets[i] = Bucket::Full(k, v);
+                    self.num_elements += 1;
+                }
+            }
+        }
+    }
+
+    /// Makes room for at least additional more entries before the map has to resize
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self.num_elements + additional;
+        self.min_buckets = self.min_buckets.max(buckets_for(&self.policy, needed));
+        if needed + self.num_removed > self.capacity() {
+            self.resize(buckets_for(&self.policy, needed));
+        }
+    }
+
+    /// Shrinks the map to as few buckets as it can hold its entries in, giving up
+    /// any capacity reserved before, and clearing out the Removed buckets
+    pub fn shrink_to_fit(&mut self) {
+        self.min_buckets = STARTING_BUCKETS;
+        self.resize(buckets_for(&self.policy, self.num_elements));
+    }
+
+    /// Changes when the map resizes, growing it straight away if it's too full.
+    /// Panics if the max load factor isn't below 1.
+    pub fn set_policy(&mut self, policy: ResizePolicy) {
+        assert!(
+            policy.max_load_factor() < 1.0,
+            "ProbingHashMap needs empty buckets"
+        );
+        self.policy = policy;
+        if policy.should_grow(self.num_elements + self.num_removed, self.buckets.len()) {
+            self.resize(resized_len(&policy, self.num_elements));
+        }
+    }
+
+    /// Returns how many buckets a lookup of the key in bucket b looks at
+    fn probe_length(&self, key: &K, b: usize) -> usize {
+        let hash = self.hash_builder.hash_one(key);
+        let len = self.buckets.len();
+        (0..len).position(|i| P::bucket(hash, i, len) == b).unwrap() + 1
+    }
+}
+
+impl<K, V, P> ProbingHashMap<K, V, P> {
+    /// Returns an empty map with room for capacity entries before it has to resize
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, SipBuildHasher::default())
+    }
+}
+
+impl<K, V, P, S> ProbingHashMap<K, V, P, S> {
+    /// Returns an empty map that hashes its keys with hash_builder
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self::with_capacity_and_hasher(0, hash_builder)
+    }
+
+    /// Returns an empty map with room for capacity entries before it has to resize,
+    /// that hashes its keys with hash_builder
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        let len = buckets_for(&DEFAULT_POLICY, capacity);
+        Self {
+            buckets: create_buckets(len),
+            num_elements: 0,
+            num_removed: 0,
+            hash_builder,
+            policy: DEFAULT_POLICY,
+            min_buckets: len,
+            probe: PhantomData,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    pub fn policy(&self) -> &ResizePolicy {
+        &self.policy
+    }
+
+    /// Returns how many entries the map can hold before it has to resize,
+    /// if nothing is removed
+    pub fn capacity(&self) -> usize {
+        self.policy.capacity(self.buckets.len())
+    }
+
+    /// Returns an iterator over the entries, in the order of their buckets
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            buckets: self.buckets.iter(),
+            remaining: self.num_elements,
+        }
+    }
+
+    /// Returns an iterator over the entries, in the order of their buckets,
+    /// that allows the values to be modified
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            buckets: self.buckets.iter_mut(),
+            remaining: self.num_elements,
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, P: ProbeSequence, S: BuildHasher> ProbeLengths
+    for ProbingHashMap<K, V, P, S>
+{
+    fn probe_stats(&self) -> ProbeStats {
+        let lengths = self.buckets.iter().enumerate().filter_map(|(b, bucket)| {
+            if let Bucket::Full(k, _) = bucket {
+                Some(self.probe_length(k, b))
+            } else {
+                None
+            }
+        });
+        ProbeStats::new(self.buckets.len(), lengths)
+    }
+}
+
+impl<K, V, P, S> LoadFactor for ProbingHashMap<K, V, P, S> {
+    fn load_factor(&self) -> f64 {
+        self.num_elements as f64 / self.buckets.len() as f64
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    buckets: slice::Iter<'a, Bucket<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for b in self.buckets.by_ref() {
+            if let Bucket::Full(k, v) = b {
+                self.remaining -= 1;
+                return Some((k, v));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+pub struct IterMut<'a, K, V> {
+    buckets: slice::IterMut<'a, Bucket<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for b in self.buckets.by_ref() {
+            if let Bucket::Full(k, v) = b {
+                self.remaining -= 1;
+                return Some((k, v));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V, P, S> IntoIterator for &'a ProbingHashMap<K, V, P, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V, P, S> IntoIterator for &'a mut ProbingHashMap<K, V, P, S> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K, V, P, S> IntoIterator for ProbingHashMap<K, V, P, S> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let mut storage = Vec::with_capacity(self.num_elements);
+        for b in self.buckets {
+            if let Bucket::Full(k, v) = b {
+                storage.push((k, v));
+            }
+        }
+        storage.into_iter()
+    }
+}
+
+impl<K: Hash + Eq, V, P: ProbeSequence, S: BuildHasher + Default> Map61B
+    for ProbingHashMap<K, V, P, S>
+{
+    type Key = K;
+    type Value = V;
+    type Iter<'a>
+        = Iter<'a, K, V>
+    where
+        Self: 'a;
+    type IterMut<'a>
+        = IterMut<'a, K, V>
+    where
+        Self: 'a;
+
+    fn new() -> Self {
+        Self::with_hasher(S::default())
+    }
+
+    fn len(&self) -> usize {
+        self.num_elements
+    }
+
+    fn clear(&mut self) {
+        self.buckets.fill_with(|| Bucket::Empty);
+        self.num_elements = 0;
+        self.num_removed = 0;
+    }
+
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find_index(key).is_ok()
+    }
+
+    fn insert(&mut self, key: Self::Key, value: Self::Value) -> Option<Self::Value> {
+        match self.find_index(&key) {
+            Ok(i) => match self.buckets[i] {
+                Bucket::Full(_, ref mut v) => Some(std::mem::replace(v, value)),
+                _ => unreachable!(),
+            },
+            Err(_) => {
+                self.insert_vacant(key, value);
+                None
+            }
+        }
+    }
+
+    fn get<Q>(&self, key: &Q) -> Option<&Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.buckets[self.find_index(key).ok()?] {
+            Bucket::Full(_, ref v) => Some(v),
+            _ => unreachable!(),
+        }
+    }
+
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let i = self.find_index(key).ok()?;
+        match self.buckets[i] {
+            Bucket::Full(_, ref mut v) => Some(v),
+            _ => unreachable!(),
+        }
+    }
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let i = self.find_index(key).ok()?;
+        let Bucket::Full(_, v) = std::mem::replace(&mut self.buckets[i], Bucket::Removed) else {
+            unreachable!();
+        };
+        self.num_elements -= 1;
+        self.num_removed += 1;
+        if self.buckets.len() > self.min_buckets
+            && self
+                .policy
+                .should_shrink(self.num_elements, self.buckets.len())
+        {
+            self.resize(resized_len(&self.policy, self.num_elements).max(self.min_buckets));
+        }
+        Some(v)
+    }
+
+    fn insert_vacant(&mut self, key: Self::Key, value: Self::Value) -> &mut Self::Value {
+        // resize first, since resizing moves every entry
+        let num_elements = self.num_elements + 1;
+        if self
+            .policy
+            .should_grow(num_elements + self.num_removed, self.buckets.len())
+        {
+            // if most of the load is Removed buckets, this only clears them out
+            self.resize(resized_len(&self.policy, num_elements));
+        }
+        let Err(i) = self.find_index(&key) else {
+            panic!("insert_vacant called with a key that is already in the map");
+        };
+        if let Bucket::Removed = self.buckets[i] {
+            self.num_removed -= 1;
+        }
+        self.num_elements = num_elements;
+        self.buckets[i] = Bucket::Full(key, value);
+        match self.buckets[i] {
+            Bucket::Full(_, ref mut v) => v,
+            _ => unreachable!(),
+        }
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        ProbingHashMap::iter(self)
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        ProbingHashMap::iter_mut(self)
+    }
+
+    fn drain(&mut self) -> Self::IntoIter {
+        // keeps the buckets, like clear
+        let len = self.buckets.len();
+        let buckets = std::mem::replace(&mut self.buckets, create_buckets(len));
+        self.num_elements = 0;
+        self.num_removed = 0;
+        let mut storage = Vec::new();
+        for b in buckets {
+            if let Bucket::Full(k, v) = b {
+                storage.push((k, v));
+            }
+        }
+        storage.into_iter()
+    }
+
+    fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        // rebuilding at the same size also clears out the Removed buckets
+        self.rebuild(self.buckets.len(), f);
+    }
+}
@@ -0,0 +1,440 @@
+use std::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+    slice,
+};
+
+use crate::{
+    hashers::SipBuildHasher,
+    policy::{LoadFactor, ResizePolicy},
+    probing::{ProbeLengths, ProbeStats},
+    Map61B,
+};
+
+const STARTING_BUCKETS: usize = 10;
+const DEFAULT_POLICY: ResizePolicy = ResizePolicy::new(0.05, 0.25, 0.9);
+
+struct Slot<K, V> {
+    /// the full hash of key, so that it doesn't have to be hashed again
+    hash: u64,
+    key: K,
+    value: V,
+}
+
+/*
+An open-addressing, linear-probing hashmap with Robin Hood hashing: when a new
+entry has probed further from its home bucket than the entry in its way, it
+takes that bucket, and the entry it displaced carries on probing instead. This
+evens out how far entries are from home, so it can run at a higher load factor
+than OHashMap. It also means a lookup can stop as soon as it reaches an entry
+that is closer to home than the key would be.
+
+Removals shift the entries after the removed one back a bucket, until one is
+in its home bucket, so there are no tombstones.
+*/
+pub struct RHashMap<K, V, S = SipBuildHasher> {
+    buckets: Vec<Option<Slot<K, V>>>,
+    num_elements: usize,
+    hash_builder: S,
+    policy: ResizePolicy,
+    /// the fewest buckets removing shrinks the map to, which with_capacity and
+    /// reserve raise so the map doesn't shrink below the capacity it was given
+    min_buckets: usize,
+}
+
+/// Returns how many buckets are needed to hold capacity entries without resizing
+fn buckets_for(policy: &ResizePolicy, capacity: usize) -> usize {
+    STARTING_BUCKETS.max(policy.buckets_for(capacity))
+}
+
+/// Returns how many buckets to resize to when there are num_elements entries
+fn resized_len(policy: &ResizePolicy, num_elements: usize) -> usize {
+    STARTING_BUCKETS.max(policy.resized_buckets(num_elements))
+}
+
+fn create_buckets<K, V>(num_buckets: usize) -> Vec<Option<Slot<K, V>>> {
+    (0..num_buckets).map(|_| None).collect()
+}
+
+impl<K, V, S> RHashMap<K, V, S> {
+    fn home(&self, hash: u64) -> usize {
+        (hash % self.buckets.len() as u64) as usize
+    }
+
+    /// Returns how many buckets the entry with hash in bucket i is past its home
+    fn distance(&self, hash: u64, i: usize) -> usize {
+        let len = self.buckets.len();
+        (i + len - self.home(hash)) % len
+    }
+
+    /// Puts slot into the table, and returns the bucket it ends up in.
+    /// There must be at least one empty bucket.
+    fn place(&mut self, slot: Slot<K, V>) -> usize {
+        let len = self.buckets.len();
+        let mut i = self.home(slot.hash);
+        let mut dist = 0;
+        let mut carried = slot;
+        let mut placed = None;
+        loop {
+            match &mut self.buckets[i] {
+                None => {
+                    self.buckets[i] = Some(carried);
+                    return placed.unwrap_or(i);
+                }
+                Some(other) => {
+                    let other_dist = (i + len - (other.hash % len as u64) as usize) % len;
+                    if other_dist < dist {
+                        // take from the rich to give to the poor
+                        std::mem::swap(other, &mut carried);
+                        placed.get_or_insert(i);
+                        dist = other_dist;
+                    }
+                }
+            }
+            i = (i + 1) % len;
+            dist += 1;
+        }
+    }
+
+    fn resize(&mut self, new_len: usize) {
+        self.rebuild(new_len, |_, _| true);
+    }
+
+    /// Moves the entries for which keep returns true into new_len new buckets
+    fn rebuild(&mut self, new_len: usize, mut keep: impl FnMut(&K, &mut V) -> bool) {
+        let old_buckets = std::mem::replace(&mut self.buckets, create_buckets(new_len));
+        self.num_elements = 0;
+        for mut slot in old_buckets.into_iter().flatten() {
+            if keep(&slot.key, &mut slot.value) {
+                self.place(slot);
+                self.num_elements += 1;
+            }
+        }
+    }
+
+    /// Makes room for at least additional more entries before the map has to resize
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self.num_elements + additional;
+        self.min_buckets = self.min_buckets.max(buckets_for(&self.policy, needed));
+        if needed > self.capacity() {
+            self.resize(buckets_for(&self.policy, needed));
+        }
+    }
+
+    /// Shrinks the map to as few buckets as it can hold its entries in,
+    /// giving up any capacity reserved before
+    pub fn shrink_to_fit(&mut self) {
+        self.min_buckets = STARTING_BUCKETS;
+        let len = buckets_for(&self.policy, self.num_elements);
+        if len < self.buckets.len() {
+            self.resize(len);
+        }
+    }
+
+    /// Changes when the map resizes, growing it straight away if it's too full.
+    /// Panics if the max load factor isn't below 1.
+    pub fn set_policy(&mut self, policy: ResizePolicy) {
+        assert!(
+            policy.max_load_factor() < 1.0,
+            "RHashMap needs empty buckets"
+        );
+        self.policy = policy;
+        if policy.should_grow(self.num_elements, self.buckets.len()) {
+            self.resize(resized_len(&policy, self.num_elements));
+        }
+    }
+}
+
+impl<K, V> RHashMap<K, V> {
+    /// Returns an empty map with room for capacity entries before it has to resize
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, SipBuildHasher::default())
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> RHashMap<K, V, S> {
+    fn find_index<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hash_builder.hash_one(key);
+        let mut i = self.home(hash);
+        let mut dist = 0;
+        // ends at an empty bucket at the latest, since the load factor is below 1
+        while let Some(slot) = &self.buckets[i] {
+            if self.distance(slot.hash, i) < dist {
+                // the key would have taken this bucket if it were in the map
+                return None;
+            }
+            if slot.hash == hash && slot.key.borrow() == key {
+                return Some(i);
+            }
+            i = (i + 1) % self.buckets.len();
+            dist += 1;
+        }
+        None
+    }
+}
+
+impl<K, V, S> RHashMap<K, V, S> {
+    /// Returns an empty map that hashes its keys with hash_builder
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self::with_capacity_and_hasher(0, hash_builder)
+    }
+
+    /// Returns an empty map with room for capacity entries before it has to resize,
+    /// that hashes its keys with hash_builder
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        let len = buckets_for(&DEFAULT_POLICY, capacity);
+        Self {
+            buckets: create_buckets(len),
+            num_elements: 0,
+            hash_builder,
+            policy: DEFAULT_POLICY,
+            min_buckets: len,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    pub fn policy(&self) -> &ResizePolicy {
+        &self.policy
+    }
+
+    /// Returns how many entries the map can hold before it has to resize
+    pub fn capacity(&self) -> usize {
+        self.policy.capacity(self.buckets.len())
+    }
+
+    /// Returns an iterator over the entries, in the order of their buckets
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            buckets: self.buckets.iter(),
+            remaining: self.num_elements,
+        }
+    }
+
+    /// Returns an iterator over the entries, in the order of their buckets,
+    /// that allows the values to be modified
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            buckets: self.buckets.iter_mut(),
+            remaining: self.num_elements,
+        }
+    }
+}
+
+impl<K, V, S> ProbeLengths for RHashMap<K, V, S> {
+    fn probe_stats(&self) -> ProbeStats {
+        let lengths =
+            self.buckets.iter().enumerate().filter_map(|(i, bucket)| {
+                bucket.as_ref().map(|slot| self.distance(slot.hash, i) + 1)
+            });
+        ProbeStats::new(self.buckets.len(), lengths)
+    }
+}
+
+impl<K, V, S> LoadFactor for RHashMap<K, V, S> {
+    fn load_factor(&self) -> f64 {
+        self.num_elements as f64 / self.buckets.len() as f64
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    buckets: slice::Iter<'a, Option<Slot<K, V>>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let slot = self.buckets.by_ref().flatten().next()?;
+        self.remaining -= 1;
+        Some((&slot.key, &slot.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+pub struct IterMut<'a, K, V> {
+    buckets: slice::IterMut<'a, Option<Slot<K, V>>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let slot = self.buckets.by_ref().flatten().next()?;
+        self.remaining -= 1;
+        Some((&slot.key, &mut slot.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a RHashMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a mut RHashMap<K, V, S> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K, V, S> IntoIterator for RHashMap<K, V, S> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.buckets
+            .into_iter()
+            .flatten()
+            .map(|slot| (slot.key, slot.value))
+            .collect::<Vec<(K, V)>>()
+            .into_iter()
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Default> Map61B for RHashMap<K, V, S> {
+    type Key = K;
+    type Value = V;
+    type Iter<'a>
+        = Iter<'a, K, V>
+    where
+        Self: 'a;
+    type IterMut<'a>
+        = IterMut<'a, K, V>
+    where
+        Self: 'a;
+
+    fn new() -> Self {
+        Self::with_hasher(S::default())
+    }
+
+    fn len(&self) -> usize {
+        self.num_elements
+    }
+
+    fn clear(&mut self) {
+        self.buckets.fill_with(|| None);
+        self.num_elements = 0;
+    }
+
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find_index(key).is_some()
+    }
+
+    fn insert(&mut self, key: Self::Key, value: Self::Value) -> Option<Self::Value> {
+        if let Some(i) = self.find_index(&key) {
+            let slot = self.buckets[i].as_mut().unwrap();
+            return Some(std::mem::replace(&mut slot.value, value));
+        }
+        self.insert_vacant(key, value);
+        None
+    }
+
+    fn get<Q>(&self, key: &Q) -> Option<&Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let i = self.find_index(key)?;
+        self.buckets[i].as_ref().map(|slot| &slot.value)
+    }
+
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let i = self.find_index(key)?;
+        self.buckets[i].as_mut().map(|slot| &mut slot.value)
+    }
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut i = self.find_index(key)?;
+        let removed = self.buckets[i].take().unwrap();
+        self.num_elements -= 1;
+        // backward shift: move the following entries one bucket closer to home
+        let len = self.buckets.len();
+        loop {
+            let next = (i + 1) % len;
+            match &self.buckets[next] {
+                Some(slot) if self.distance(slot.hash, next) > 0 => {
+                    self.buckets[i] = self.buckets[next].take();
+                    i = next;
+                }
+                _ => break,
+            }
+        }
+        if len > self.min_buckets && self.policy.should_shrink(self.num_elements, len) {
+            self.resize(resized_len(&self.policy, self.num_elements).max(self.min_buckets));
+        }
+        Some(removed.value)
+    }
+
+    fn insert_vacant(&mut self, key: Self::Key, value: Self::Value) -> &mut Self::Value {
+        // resize first, since resizing moves every entry
+        let num_elements = self.num_elements + 1;
+        if self.policy.should_grow(num_elements, self.buckets.len()) {
+            self.resize(resized_len(&self.policy, num_elements));
+        }
+        self.num_elements = num_elements;
+        let hash = self.hash_builder.hash_one(&key);
+        let i = self.place(Slot { hash, key, value });
+        &mut self.buckets[i].as_mut().unwrap().value
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        RHashMap::iter(self)
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        RHashMap::iter_mut(self)
+    }
+
+    fn drain(&mut self) -> Self::IntoIter {
+        // keeps the buckets, like clear
+        self.num_elements = 0;
+        self.buckets
+            .iter_mut()
+            .filter_map(Option::take)
+            .map(|slot| (slot.key, slot.value))
+            .collect::<Vec<(K, V)>>()
+            .into_iter()
+    }
+
+    fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        // rebuilding at the same size puts the entries left back near their homes
+        self.rebuild(self.buckets.len(), f);
+    }
+}
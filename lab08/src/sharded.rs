@@ -0,0 +1,264 @@
+use std::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+    ops::Deref,
+    panic::{self, AssertUnwindSafe},
+    sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    thread,
+};
+
+use map61b_common::bench::ConcurrentMap;
+
+use crate::{chashmap::CHashMap, hashers::SipBuildHasher, Map61B};
+
+const SHARDS_PER_THREAD: usize = 4;
+/// 2^64 divided by the golden ratio, which is odd, so multiplying by it mixes
+/// every bit of a hash into the top half without losing any
+const MIX: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/*
+A hashmap that can be shared between threads. The entries are split between
+several CHashMap shards by their hash, and each shard has its own RwLock, so
+threads working on different shards never wait for each other, and any number
+of threads can read the same shard at once. With more shards than threads,
+two threads rarely want the same shard.
+
+The shard is picked with the top half of the hash after it's multiplied by
+MIX. The shards pick a bucket with the whole hash modulo their number of
+buckets, so picking the shard with the hash modulo the number of shards would
+leave some buckets in every shard that none of its keys could go in, and the
+top half alone would put every entry in the first shard for a hash that leaves
+it zero, like the bad hasher's.
+
+A poisoned lock is used as if it wasn't, so whatever panics while a shard is
+locked has to leave it usable for the next thread. CHashMap's own code only
+panics if K's Hash or Eq does, which this map doesn't guard against. The
+closure given to update_with runs under the write lock too, and could leave a
+value half changed, so if it panics, its entry is removed before the panic
+carries on.
+*/
+pub struct ShardedMap<K, V, S = SipBuildHasher> {
+    shards: Box<[RwLock<CHashMap<K, V, S>>]>,
+    hash_builder: S,
+}
+
+/// A shared borrow of a value in a ShardedMap, which holds its shard's read
+/// lock until it's dropped
+pub struct ReadGuard<'a, K, V, S> {
+    shard: RwLockReadGuard<'a, CHashMap<K, V, S>>,
+    position: usize,
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> Deref for ReadGuard<'_, K, V, S> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        // the read lock stops the entry from moving
+        self.shard.value_at(self.position)
+    }
+}
+
+/// Returns how many shards to use by default: a few for each thread the machine can run at once
+fn default_shards() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get()) * SHARDS_PER_THREAD
+}
+
+impl<K, V> ShardedMap<K, V> {
+    pub fn new() -> Self {
+        Self::with_hasher(SipBuildHasher::default())
+    }
+
+    /// Returns an empty map split into num_shards shards. Panics if num_shards is 0.
+    pub fn with_shards(num_shards: usize) -> Self {
+        Self::with_shards_and_hasher(num_shards, SipBuildHasher::default())
+    }
+}
+
+impl<K, V> Default for ShardedMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, S: Clone> ShardedMap<K, V, S> {
+    /// Returns an empty map that hashes its keys with hash_builder
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self::with_shards_and_hasher(default_shards(), hash_builder)
+    }
+
+    /// Returns an empty map split into num_shards shards, that hashes its keys
+    /// with hash_builder. Panics if num_shards is 0.
+    pub fn with_shards_and_hasher(num_shards: usize, hash_builder: S) -> Self {
+        assert!(num_shards > 0, "a ShardedMap needs at least one shard");
+        let shards = (0..num_shards)
+            .map(|_| RwLock::new(CHashMap::with_hasher(hash_builder.clone())))
+            .collect();
+        ShardedMap {
+            shards,
+            hash_builder,
+        }
+    }
+}
+
+impl<K, V, S> ShardedMap<K, V, S> {
+    pub fn num_shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Default> ShardedMap<K, V, S> {
+    fn shard<Q: Hash + ?Sized>(&self, key: &Q) -> &RwLock<CHashMap<K, V, S>> {
+        let hash = self.hash_builder.hash_one(key).wrapping_mul(MIX);
+        &self.shards[(hash >> 32) as usize % self.shards.len()]
+    }
+
+    fn read<Q: Hash + ?Sized>(&self, key: &Q) -> RwLockReadGuard<'_, CHashMap<K, V, S>> {
+        self.shard(key)
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write<Q: Hash + ?Sized>(&self, key: &Q) -> RwLockWriteGuard<'_, CHashMap<K, V, S>> {
+        self.shard(key)
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the value for key, which keeps other threads from changing
+    /// anything in its shard until it's dropped
+    pub fn get<Q>(&self, key: &Q) -> Option<ReadGuard<'_, K, V, S>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let shard = self.read(key);
+        let position = shard.position(key)?;
+        Some(ReadGuard { shard, position })
+    }
+
+    /// Returns a copy of the value for key, without keeping its shard locked
+    pub fn get_cloned<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.read(key).get(key).cloned()
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.read(key).contains_key(key)
+    }
+
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.write(&key).insert(key, value)
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.write(key).remove(key)
+    }
+
+    /// Calls f on the value for key, inserting default() first if the key isn't
+    /// in the map. No other thread can see or change the value in between,
+    /// so this can safely read and then change the value, like incrementing a counter.
+    /// If f panics, the key is removed, since f could have left its value half changed.
+    pub fn update_with<R>(
+        &self,
+        key: K,
+        default: impl FnOnce() -> V,
+        f: impl FnOnce(&mut V) -> R,
+    ) -> R {
+        let mut shard = self.write(&key);
+        let position = match shard.position(&key) {
+            Some(position) => position,
+            None => {
+                shard.insert_vacant(key, default());
+                // CHashMap puts new entries at the end of chains
+                shard.len() - 1
+            }
+        };
+        match panic::catch_unwind(AssertUnwindSafe(|| f(shard.value_at_mut(position)))) {
+            Ok(result) => result,
+            Err(payload) => {
+                shard.remove_at(position);
+                panic::resume_unwind(payload)
+            }
+        }
+    }
+
+    /// Returns the number of entries. Other threads can change it while the
+    /// shards are being counted, so it's only exact if nothing else is using the map.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|s| s.read().unwrap_or_else(PoisonError::into_inner).len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns how many entries are in each shard, to see how evenly the hash
+    /// splits them up
+    pub fn shard_lens(&self) -> Vec<usize> {
+        self.shards
+            .iter()
+            .map(|s| s.read().unwrap_or_else(PoisonError::into_inner).len())
+            .collect()
+    }
+
+    /// Removes every entry, one shard at a time
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            shard
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clear();
+        }
+    }
+}
+
+impl<K, V, S> IntoIterator for ShardedMap<K, V, S> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    /// Returns the entries shard by shard
+    fn into_iter(self) -> Self::IntoIter {
+        self.shards
+            .into_vec()
+            .into_iter()
+            .flat_map(|s| s.into_inner().unwrap_or_else(PoisonError::into_inner))
+            .collect::<Vec<(K, V)>>()
+            .into_iter()
+    }
+}
+
+impl<S> ConcurrentMap for ShardedMap<usize, usize, S>
+where
+    S: BuildHasher + Default + Clone + Send + Sync,
+{
+    fn new() -> Self {
+        Self::with_hasher(S::default())
+    }
+
+    fn insert(&self, key: usize, value: usize) {
+        ShardedMap::insert(self, key, value);
+    }
+
+    fn get(&self, key: usize) -> Option<usize> {
+        self.get_cloned(&key)
+    }
+}
@@ -0,0 +1,516 @@
+use std::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+    iter::Zip,
+    slice,
+};
+
+use crate::{
+    hashers::SipBuildHasher,
+    policy::{LoadFactor, ResizePolicy},
+    Map61B,
+};
+
+const GROUP_WIDTH: usize = 16;
+// must be a power of two, at least GROUP_WIDTH
+const STARTING_BUCKETS: usize = GROUP_WIDTH;
+// the max load factor counts deleted buckets too. Bucket counts are rounded up
+// to a power of two, so the load factor after resizing is at most the target.
+const DEFAULT_POLICY: ResizePolicy = ResizePolicy::new(0.05, 0.4375, 0.875);
+
+/// control byte of a bucket that has never been used
+const EMPTY: u8 = 0b1111_1111;
+/// control byte of a bucket whose entry was removed
+const DELETED: u8 = 0b1000_0000;
+// a full bucket's control byte is 0, followed by the top 7 bits of its key's hash
+
+/// Returns the control byte for a key with hash
+fn h2(hash: u64) -> u8 {
+    (hash >> 57) as u8
+}
+
+/// Returns a u128 with byte in each of its 16 bytes
+const fn repeat(byte: u8) -> u128 {
+    u128::from_ne_bytes([byte; GROUP_WIDTH])
+}
+
+/// The control bytes of GROUP_WIDTH buckets, packed into one integer so that they
+/// can all be checked at once
+#[derive(Clone, Copy)]
+pub(crate) struct Group(u128);
+
+impl Group {
+    pub(crate) fn load(ctrl: &[u8]) -> Group {
+        Group(u128::from_le_bytes(ctrl[..GROUP_WIDTH].try_into().unwrap()))
+    }
+
+    /// Returns the buckets whose control byte is byte. Like hashbrown's portable
+    /// version, this can have false positives just after a real match, so the keys
+    /// still have to be compared.
+    pub(crate) fn match_byte(self, byte: u8) -> BitMask {
+        // a byte of x is zero exactly where the control byte matches, and
+        // subtracting 1 from a zero byte sets its top bit
+        let x = self.0 ^ repeat(byte);
+        BitMask(x.wrapping_sub(repeat(0x01)) & !x & repeat(0x80))
+    }
+
+    /// Returns the EMPTY buckets: the only control bytes with their top two bits set
+    pub(crate) fn match_empty(self) -> BitMask {
+        BitMask(self.0 & (self.0 << 1) & repeat(0x80))
+    }
+
+    /// Returns the EMPTY and DELETED buckets: the only control bytes with their top bit set
+    pub(crate) fn match_empty_or_deleted(self) -> BitMask {
+        BitMask(self.0 & repeat(0x80))
+    }
+}
+
+/// A set of buckets in a Group, as the top bit of each of their bytes
+pub(crate) struct BitMask(u128);
+
+impl BitMask {
+    pub(crate) fn any(&self) -> bool {
+        self.0 != 0
+    }
+}
+
+impl Iterator for BitMask {
+    type Item = usize;
+
+    /// Returns the index of the next bucket in the group
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+        let i = self.0.trailing_zeros() as usize / 8;
+        self.0 &= self.0 - 1;
+        Some(i)
+    }
+}
+
+/*
+A Swiss table, like hashbrown's, which std::collections::HashMap is built on.
+The buckets are split into groups of 16, and each bucket has a control byte,
+kept in its own array, that says whether it's empty, deleted, or full, and if
+it's full, 7 bits of its key's hash. A lookup probes a whole group at a time:
+it finds every bucket in the group whose control byte matches with a few integer
+operations, and only compares keys in those buckets, which rules out almost
+every collision without touching the entries at all. The groups themselves are
+probed quadratically.
+
+The real thing uses SIMD instructions to compare the 16 control bytes; this
+uses the same bit tricks as hashbrown does without SIMD, on a u128.
+*/
+pub struct SHashMap<K, V, S = SipBuildHasher> {
+    /// one control byte for each bucket
+    ctrl: Vec<u8>,
+    buckets: Vec<Option<(K, V)>>,
+    num_elements: usize,
+    num_deleted: usize,
+    hash_builder: S,
+    policy: ResizePolicy,
+    /// the fewest buckets removing shrinks the map to, which with_capacity and
+    /// reserve raise so the map doesn't shrink below the capacity it was given
+    min_buckets: usize,
+}
+
+/// Returns how many buckets are needed to hold capacity entries without resizing
+fn buckets_for(policy: &ResizePolicy, capacity: usize) -> usize {
+    STARTING_BUCKETS.max(policy.buckets_for(capacity).next_power_of_two())
+}
+
+/// Returns how many buckets to resize to when there are num_elements entries
+fn resized_len(policy: &ResizePolicy, num_elements: usize) -> usize {
+    STARTING_BUCKETS.max(policy.resized_buckets(num_elements).next_power_of_two())
+}
+
+/// Returns the groups to probe for hash, in a table with num_groups groups
+fn probe_sequence(hash: u64, num_groups: usize) -> impl Iterator<Item = usize> {
+    // num_groups is a power of two, so steps of 1, 2, 3, ... visit every group once
+    let mut group = hash as usize & (num_groups - 1);
+    (0..num_groups).map(move |stride| {
+        group = (group + stride) & (num_groups - 1);
+        group
+    })
+}
+
+impl<K, V, S> SHashMap<K, V, S> {
+    fn num_groups(&self) -> usize {
+        self.buckets.len() / GROUP_WIDTH
+    }
+
+    fn group(&self, g: usize) -> Group {
+        Group::load(&self.ctrl[g * GROUP_WIDTH..])
+    }
+
+    /// Returns the first bucket that is empty or deleted in hash's probe sequence
+    fn find_insert_slot(&self, hash: u64) -> usize {
+        for g in probe_sequence(hash, self.num_groups()) {
+            if let Some(i) = self.group(g).match_empty_or_deleted().next() {
+                return g * GROUP_WIDTH + i;
+            }
+        }
+        unreachable!("the load factor keeps some buckets free")
+    }
+
+    /// Puts an entry into the bucket i, which must be empty or deleted
+    fn set(&mut self, i: usize, hash: u64, key: K, value: V) -> &mut V {
+        if self.ctrl[i] == DELETED {
+            self.num_deleted -= 1;
+        }
+        self.ctrl[i] = h2(hash);
+        self.num_elements += 1;
+        let (_, v) = self.buckets[i].insert((key, value));
+        v
+    }
+}
+
+impl<K: Hash, V, S: BuildHasher> SHashMap<K, V, S> {
+    fn find_index<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hash_builder.hash_one(key);
+        for g in probe_sequence(hash, self.num_groups()) {
+            let group = self.group(g);
+            for i in group.match_byte(h2(hash)) {
+                let i = g * GROUP_WIDTH + i;
+                if let Some((k, _)) = &self.buckets[i] {
+                    if k.borrow() == key {
+                        return Some(i);
+                    }
+                }
+            }
+            // an insert would have stopped at this group, so the key can't be further on
+            if group.match_empty().any() {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn resize(&mut self, new_len: usize) {
+        self.rebuild(new_len, |_, _| true);
+    }
+
+    /// Moves the entries for which keep returns true into new_len new buckets
+    fn rebuild(&mut self, new_len: usize, mut keep: impl FnMut(&K, &mut V) -> bool) {
+        let old_buckets = std::mem::replace(&mut self.buckets, create_buckets(new_len));
+        self.ctrl = vec![EMPTY; new_len];
+        self.num_elements = 0;
+        self.num_deleted = 0;
+        for (k, mut v) in old_buckets.into_iter().flatten() {
+            if keep(&k, &mut v) {
+                let hash = self.hash_builder.hash_one(&k);
+                let i = self.find_insert_slot(hash);
+                self.set(i, hash, k, v);
+            }
+        }
+    }
+
+    /// Makes room for at least additional more entries before the map has to resize
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self.num_elements + additional;
+        self.min_buckets = self.min_buckets.max(buckets_for(&self.policy, needed));
+        if needed + self.num_deleted > self.capacity() {
+            self.resize(buckets_for(&self.policy, needed));
+        }
+    }
+
+    /// Shrinks the map to as few buckets as it can hold its entries in, giving up
+    /// any capacity reserved before, and clearing out the deleted buckets
+    pub fn shrink_to_fit(&mut self) {
+        self.min_buckets = STARTING_BUCKETS;
+        self.resize(buckets_for(&self.policy, self.num_elements));
+    }
+
+    /// Changes when the map resizes, growing it straight away if it's too full.
+    /// Panics if the max load factor isn't below 1.
+    pub fn set_policy(&mut self, policy: ResizePolicy) {
+        assert!(
+            policy.max_load_factor() < 1.0,
+            "SHashMap needs empty buckets"
+        );
+        self.policy = policy;
+        if policy.should_grow(self.num_elements + self.num_deleted, self.buckets.len()) {
+            self.resize(resized_len(&policy, self.num_elements));
+        }
+    }
+}
+
+impl<K, V> SHashMap<K, V> {
+    /// Returns an empty map with room for capacity entries before it has to resize
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, SipBuildHasher::default())
+    }
+}
+
+fn create_buckets<K, V>(num_buckets: usize) -> Vec<Option<(K, V)>> {
+    (0..num_buckets).map(|_| None).collect()
+}
+
+impl<K, V, S> SHashMap<K, V, S> {
+    /// Returns an empty map that hashes its keys with hash_builder
+    pub fn with_hasher(hash_builder: S) -> Self {
+        Self::with_capacity_and_hasher(0, hash_builder)
+    }
+
+    /// Returns an empty map with room for capacity entries before it has to resize,
+    /// that hashes its keys with hash_builder
+    pub fn with_capacity_and_hasher(capacity: usize, hash_builder: S) -> Self {
+        let len = buckets_for(&DEFAULT_POLICY, capacity);
+        Self {
+            ctrl: vec![EMPTY; len],
+            buckets: create_buckets(len),
+            num_elements: 0,
+            num_deleted: 0,
+            hash_builder,
+            policy: DEFAULT_POLICY,
+            min_buckets: len,
+        }
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    pub fn policy(&self) -> &ResizePolicy {
+        &self.policy
+    }
+
+    /// Returns how many entries the map can hold before it has to resize,
+    /// if nothing is removed
+    pub fn capacity(&self) -> usize {
+        self.policy.capacity(self.buckets.len())
+    }
+
+    /// Returns an iterator over the entries, in the order of their buckets
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            buckets: self.ctrl.iter().zip(self.buckets.iter()),
+            remaining: self.num_elements,
+        }
+    }
+
+    /// Returns an iterator over the entries, in the order of their buckets,
+    /// that allows the values to be modified
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            buckets: self.ctrl.iter().zip(self.buckets.iter_mut()),
+            remaining: self.num_elements,
+        }
+    }
+}
+
+impl<K, V, S> LoadFactor for SHashMap<K, V, S> {
+    fn load_factor(&self) -> f64 {
+        self.num_elements as f64 / self.buckets.len() as f64
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    /// the control byte and entry of each bucket
+    buckets: Zip<slice::Iter<'a, u8>, slice::Iter<'a, Option<(K, V)>>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // only full buckets' control bytes have a 0 top bit
+        let (_, bucket) = self.buckets.find(|&(&ctrl, _)| ctrl & 0x80 == 0)?;
+        let (k, v) = bucket.as_ref().unwrap();
+        self.remaining -= 1;
+        Some((k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+pub struct IterMut<'a, K, V> {
+    buckets: Zip<slice::Iter<'a, u8>, slice::IterMut<'a, Option<(K, V)>>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (_, bucket) = self.buckets.find(|&(&ctrl, _)| ctrl & 0x80 == 0)?;
+        let (k, v) = bucket.as_mut().unwrap();
+        self.remaining -= 1;
+        Some((&*k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a SHashMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a mut SHashMap<K, V, S> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K, V, S> IntoIterator for SHashMap<K, V, S> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.buckets
+            .into_iter()
+            .flatten()
+            .collect::<Vec<(K, V)>>()
+            .into_iter()
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Default> Map61B for SHashMap<K, V, S> {
+    type Key = K;
+    type Value = V;
+    type Iter<'a>
+        = Iter<'a, K, V>
+    where
+        Self: 'a;
+    type IterMut<'a>
+        = IterMut<'a, K, V>
+    where
+        Self: 'a;
+
+    fn new() -> Self {
+        Self::with_hasher(S::default())
+    }
+
+    fn len(&self) -> usize {
+        self.num_elements
+    }
+
+    fn clear(&mut self) {
+        self.ctrl.fill(EMPTY);
+        self.buckets.fill_with(|| None);
+        self.num_elements = 0;
+        self.num_deleted = 0;
+    }
+
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find_index(key).is_some()
+    }
+
+    fn insert(&mut self, key: Self::Key, value: Self::Value) -> Option<Self::Value> {
+        if let Some(i) = self.find_index(&key) {
+            let (_, v) = self.buckets[i].as_mut().unwrap();
+            return Some(std::mem::replace(v, value));
+        }
+        self.insert_vacant(key, value);
+        None
+    }
+
+    fn get<Q>(&self, key: &Q) -> Option<&Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let i = self.find_index(key)?;
+        self.buckets[i].as_ref().map(|(_, v)| v)
+    }
+
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let i = self.find_index(key)?;
+        self.buckets[i].as_mut().map(|(_, v)| v)
+    }
+
+    fn remove<Q>(&mut self, key: &Q) -> Option<Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let i = self.find_index(key)?;
+        let (_, v) = self.buckets[i].take().unwrap();
+        self.num_elements -= 1;
+        // if the group still has an empty bucket, no lookup has ever probed past it,
+        // so this bucket can be empty again rather than deleted
+        let group = i / GROUP_WIDTH;
+        if self.group(group).match_empty().any() {
+            self.ctrl[i] = EMPTY;
+        } else {
+            self.ctrl[i] = DELETED;
+            self.num_deleted += 1;
+        }
+        if self.buckets.len() > self.min_buckets
+            && self
+                .policy
+                .should_shrink(self.num_elements, self.buckets.len())
+        {
+            self.resize(resized_len(&self.policy, self.num_elements).max(self.min_buckets));
+        }
+        Some(v)
+    }
+
+    fn insert_vacant(&mut self, key: Self::Key, value: Self::Value) -> &mut Self::Value {
+        // resize first, since resizing moves every entry
+        let num_elements = self.num_elements + 1;
+        if self
+            .policy
+            .should_grow(num_elements + self.num_deleted, self.buckets.len())
+        {
+            // if most of the load is deleted buckets, this only clears them out
+            self.resize(resized_len(&self.policy, num_elements));
+        }
+        let hash = self.hash_builder.hash_one(&key);
+        let i = self.find_insert_slot(hash);
+        self.set(i, hash, key, value)
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        SHashMap::iter(self)
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        SHashMap::iter_mut(self)
+    }
+
+    fn drain(&mut self) -> Self::IntoIter {
+        // keeps the buckets, like clear
+        self.ctrl.fill(EMPTY);
+        self.num_elements = 0;
+        self.num_deleted = 0;
+        self.buckets
+            .iter_mut()
+            .filter_map(Option::take)
+            .collect::<Vec<(K, V)>>()
+            .into_iter()
+    }
+
+    fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        // rebuilding at the same size also clears out the deleted buckets
+        self.rebuild(self.buckets.len(), f);
+    }
+}
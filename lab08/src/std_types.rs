@@ -1,20 +1,30 @@
-use std::collections::{BTreeMap, HashMap};
-use std::hash::Hash;
+use std::borrow::Borrow;
+use std::collections::{hash_map, HashMap};
+use std::hash::{BuildHasher, Hash};
 
 use crate::Map61B;
 
 /*
-Implement the Map61B trait for the standard library types
-HashMap and BTreeMap. Very straightforward since all the methods
-in Map61B are named the same way they are in the standard library.
+Implement the Map61B trait for the standard library's HashMap. Very
+straightforward since all the methods in Map61B are named the same way they are
+in the standard library. BTreeMap can't implement it, since its lookups need
+Ord rather than Hash.
 */
 
-impl<K: Eq + Hash, V> Map61B for HashMap<K, V> {
+impl<K: Eq + Hash, V, S: BuildHasher + Default> Map61B for HashMap<K, V, S> {
     type Key = K;
     type Value = V;
+    type Iter<'a>
+        = hash_map::Iter<'a, K, V>
+    where
+        Self: 'a;
+    type IterMut<'a>
+        = hash_map::IterMut<'a, K, V>
+    where
+        Self: 'a;
 
     fn new() -> Self {
-        Self::new()
+        Self::default()
     }
 
     fn len(&self) -> usize {
@@ -25,7 +35,11 @@ impl<K: Eq + Hash, V> Map61B for HashMap<K, V> {
         self.clear()
     }
 
-    fn contains_key(&self, key: &Self::Key) -> bool {
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
         self.contains_key(key)
     }
 
@@ -33,52 +47,46 @@ impl<K: Eq + Hash, V> Map61B for HashMap<K, V> {
         self.insert(key, value)
     }
 
-    fn get(&self, key: &Self::Key) -> Option<&Self::Value> {
+    fn get<Q>(&self, key: &Q) -> Option<&Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
         self.get(key)
     }
 
-    fn get_mut(&mut self, key: &Self::Key) -> Option<&mut Self::Value> {
+    fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
         self.get_mut(key)
     }
 
-    fn remove(&mut self, key: &Self::Key) -> Option<Self::Value> {
+    fn remove<Q>(&mut self, key: &Q) -> Option<Self::Value>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
         self.remove(key)
     }
-}
-
-impl<K: Ord, V> Map61B for BTreeMap<K, V> {
-    type Key = K;
-    type Value = V;
-
-    fn new() -> Self {
-        Self::new()
-    }
 
-    fn len(&self) -> usize {
-        self.len()
+    fn insert_vacant(&mut self, key: Self::Key, value: Self::Value) -> &mut Self::Value {
+        self.entry(key).or_insert(value)
     }
 
-    fn clear(&mut self) {
-        self.clear()
+    fn iter(&self) -> Self::Iter<'_> {
+        self.iter()
     }
 
-    fn contains_key(&self, key: &Self::Key) -> bool {
-        self.contains_key(key)
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        self.iter_mut()
     }
 
-    fn insert(&mut self, key: Self::Key, value: Self::Value) -> Option<Self::Value> {
-        self.insert(key, value)
-    }
-
-    fn get(&self, key: &Self::Key) -> Option<&Self::Value> {
-        self.get(key)
-    }
-
-    fn get_mut(&mut self, key: &Self::Key) -> Option<&mut Self::Value> {
-        self.get_mut(key)
-    }
-
-    fn remove(&mut self, key: &Self::Key) -> Option<Self::Value> {
-        self.remove(key)
+    fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        self.retain(f)
     }
 }
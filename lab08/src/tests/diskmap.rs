@@ -0,0 +1,337 @@
+use std::{
+    env, fs,
+    io::{ErrorKind, Write},
+    path::PathBuf,
+    process,
+};
+
+use crate::{codec::Codec, diskmap::DiskMap, Map61B};
+
+// the shared tests whose keys and values have a Codec
+mod suite {
+    use map61b_tests::{
+        conformance,
+        differential::{self, Methods},
+    };
+
+    use crate::diskmap::DiskMap;
+
+    map61b_tests::map61b_tests!(@subject crate::Map61B, hash);
+
+    #[test]
+    fn sanity_clear() {
+        conformance::sanity_clear::<Subject<DiskMap<String, usize>>>();
+    }
+
+    #[test]
+    fn sanity_contains_key() {
+        conformance::sanity_contains_key::<Subject<DiskMap<String, i32>>>();
+    }
+
+    #[test]
+    fn sanity_len_test() {
+        conformance::sanity_len_test::<Subject<DiskMap<String, i32>>>();
+    }
+
+    #[test]
+    fn contains_none_test() {
+        conformance::contains_none_test::<Subject<DiskMap<String, Option<i32>>>>();
+    }
+
+    #[test]
+    fn tree_test() {
+        conformance::tree_test::<Subject<DiskMap<String, String>>>();
+    }
+
+    #[test]
+    fn entry_test() {
+        conformance::entry_test::<Subject<DiskMap<String, usize>>>();
+    }
+
+    #[test]
+    fn borrowing_iter_test() {
+        conformance::borrowing_iter_test::<Subject<DiskMap<String, usize>>>();
+    }
+
+    #[test]
+    fn iter_test() {
+        conformance::iter_test::<Subject<DiskMap<String, String>>>();
+    }
+
+    #[test]
+    fn test_remove_root() {
+        conformance::test_remove_root::<Subject<DiskMap<String, String>>>();
+    }
+
+    #[test]
+    fn test_remove_three_cases() {
+        conformance::test_remove_three_cases::<Subject<DiskMap<String, String>>>();
+    }
+
+    #[test]
+    fn test_remove_root_edge() {
+        conformance::test_remove_root_edge::<Subject<DiskMap<char, char>>>();
+    }
+
+    #[test]
+    fn drain_retain_test() {
+        conformance::drain_retain_test::<Subject<DiskMap<usize, usize>>>();
+    }
+
+    #[test]
+    fn differential_test() {
+        differential::check::<Subject<DiskMap<u16, u32>>>(Methods::All);
+    }
+}
+
+/// Returns a path in the temporary directory for a test's map, with nothing there yet
+fn log_path(test: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("lab08-test-{}-{}.log", test, process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn reopen() {
+    let path = log_path("reopen");
+    let mut m: DiskMap<String, Vec<u8>> = DiskMap::open(&path).unwrap();
+    m.insert("a".to_string(), vec![1, 2, 3]);
+    m.insert("b".to_string(), vec![4]);
+    m.insert("c".to_string(), vec![]);
+    m.insert("a".to_string(), vec![5]);
+    assert_eq!(m.remove("b"), Some(vec![4]));
+    // only written again by flush, when m is dropped
+    m.get_mut("c").unwrap().push(6);
+    drop(m);
+
+    let mut m: DiskMap<String, Vec<u8>> = DiskMap::open(&path).unwrap();
+    let mut entries: Vec<_> = m.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+    entries.sort();
+    assert_eq!(
+        entries,
+        [("a".to_string(), vec![5]), ("c".to_string(), vec![6])]
+    );
+    assert!(m.dead_bytes() > 0);
+    *m.entry("d".to_string()).or_insert(vec![]) = vec![7, 8];
+    m.flush().unwrap();
+    drop(m);
+
+    let m: DiskMap<String, Vec<u8>> = DiskMap::open(&path).unwrap();
+    assert_eq!(m.get("d"), Some(&vec![7, 8]));
+    assert_eq!(m.len(), 3);
+    drop(m);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn torn_last_record() {
+    let path = log_path("torn");
+    let mut m: DiskMap<u32, String> = DiskMap::open(&path).unwrap();
+    m.insert(1, "one".to_string());
+    let len = m.log_len();
+    m.insert(2, "two".to_string());
+    drop(m);
+
+    // a crash part way through writing the second record
+    let file = fs::OpenOptions::new().write(true).open(&path).unwrap();
+    file.set_len(len + 10).unwrap();
+    drop(file);
+    let mut m: DiskMap<u32, String> = DiskMap::open(&path).unwrap();
+    assert_eq!(m.get(&1).map(String::as_str), Some("one"));
+    assert_eq!(m.get(&2), None);
+    assert_eq!(fs::metadata(&path).unwrap().len(), len);
+    m.insert(3, "three".to_string());
+    drop(m);
+
+    // a crash that wrote the second record's length, but not all of its bytes
+    let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(&[0xff; 20]).unwrap();
+    drop(file);
+    let m: DiskMap<u32, String> = DiskMap::open(&path).unwrap();
+    assert_eq!(m.len(), 2);
+    assert_eq!(m.get(&3).map(String::as_str), Some("three"));
+    drop(m);
+    fs::remove_file(&path).unwrap();
+}
+
+// a crash that wrote a record's header, and then left the file padded with zeros,
+// which are the shortest records that fit anywhere in it
+#[test]
+fn torn_record_before_zeros() {
+    let path = log_path("torn-zeros");
+    let mut m: DiskMap<u32, Vec<u8>> = DiskMap::open(&path).unwrap();
+    m.insert(1, vec![1]);
+    let len = m.log_len();
+    m.insert(2, vec![2; 100_000]);
+    drop(m);
+
+    let mut bytes = fs::read(&path).unwrap();
+    // everything after the second record's 16 byte header
+    bytes[len as usize + 16..].fill(0);
+    fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+    let m: DiskMap<u32, Vec<u8>> = DiskMap::open(&path).unwrap();
+    assert_eq!(m.len(), 1);
+    assert_eq!(m.get(&1), Some(&vec![1]));
+    assert_eq!(fs::metadata(&path).unwrap().len(), len);
+    drop(m);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn corrupt_record() {
+    let path = log_path("corrupt");
+    let mut m: DiskMap<u32, u32> = DiskMap::open(&path).unwrap();
+    m.insert(1, 1);
+    m.insert(2, 2);
+    drop(m);
+    let mut bytes = fs::read(&path).unwrap();
+    // the first record's value
+    bytes[20] ^= 1;
+    fs::write(&path, &bytes).unwrap();
+    let err = DiskMap::<u32, u32>::open(&path).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    // a log of the wrong type
+    bytes[20] ^= 1;
+    fs::write(&path, &bytes).unwrap();
+    let err = DiskMap::<bool, u32>::open(&path).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn corrupt_length() {
+    let path = log_path("corrupt-length");
+    let mut m: DiskMap<u32, u32> = DiskMap::open(&path).unwrap();
+    for k in 0..3 {
+        m.insert(k, k);
+    }
+    drop(m);
+    let mut bytes = fs::read(&path).unwrap();
+    // the first record's key_len, which now runs past the end of the file
+    bytes[11] = 0xff;
+    fs::write(&path, &bytes).unwrap();
+    let err = DiskMap::<u32, u32>::open(&path).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    // the records after it are still there
+    assert_eq!(fs::read(&path).unwrap(), bytes);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn retain_survives_a_crash() {
+    let path = log_path("retain");
+    let mut m: DiskMap<u32, u32> = DiskMap::open(&path).unwrap();
+    for k in 0..10 {
+        m.insert(k, k);
+    }
+    m.retain(|k, _| k % 2 == 0);
+    // a crash before the map is dropped, so flush never runs
+    std::mem::forget(m);
+
+    let m: DiskMap<u32, u32> = DiskMap::open(&path).unwrap();
+    let mut keys: Vec<_> = m.keys().copied().collect();
+    keys.sort();
+    assert_eq!(keys, [0, 2, 4, 6, 8]);
+    drop(m);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn entries_are_written_once() {
+    let mut inserted: DiskMap<u32, u32> = DiskMap::new();
+    inserted.insert(1, 1);
+    inserted.flush().unwrap();
+
+    let mut m: DiskMap<u32, u32> = DiskMap::new();
+    m.entry(1).or_insert(1);
+    assert_eq!(m.log_len(), 0);
+    m.flush().unwrap();
+    assert_eq!(m.log_len(), inserted.log_len());
+    // nothing has changed since
+    m.flush().unwrap();
+    assert_eq!(m.log_len(), inserted.log_len());
+}
+
+#[test]
+fn compaction() {
+    let path = log_path("compaction");
+    let mut m: DiskMap<u32, u64> = DiskMap::open(&path).unwrap();
+    for round in 0..10 {
+        for k in 0..100 {
+            m.insert(k, round);
+        }
+    }
+    for k in 50..100 {
+        m.remove(&k);
+    }
+    let before = m.log_len();
+    assert!(m.dead_bytes() > before / 2);
+    m.compact().unwrap();
+    assert_eq!(m.dead_bytes(), 0);
+    assert!(m.log_len() < before / 10);
+    assert_eq!(m.log_len(), fs::metadata(&path).unwrap().len());
+    m.insert(100, 100);
+    drop(m);
+
+    let m: DiskMap<u32, u64> = DiskMap::open(&path).unwrap();
+    assert_eq!(m.len(), 51);
+    assert!((0..50).all(|k| m.get(&k) == Some(&9)));
+    assert_eq!(m.get(&100), Some(&100));
+    drop(m);
+    fs::remove_file(&path).unwrap();
+}
+
+// overwriting the same key over and over never lets the log get much bigger
+// than the point where it's compacted
+#[test]
+fn compacts_automatically() {
+    let mut m: DiskMap<u32, String> = DiskMap::new();
+    for i in 0..5000 {
+        m.insert(i % 10, "x".repeat(100 + i as usize % 7));
+    }
+    assert!(m.log_len() < 1 << 18, "log is {} bytes", m.log_len());
+    assert!((0..10).all(|k| m.get(&k).is_some()));
+}
+
+#[test]
+fn temporary_maps_are_deleted() {
+    let mut m: DiskMap<u8, u8> = DiskMap::new();
+    m.insert(1, 1);
+    let path = m.path().to_path_buf();
+    assert!(path.exists());
+    drop(m);
+    assert!(!path.exists());
+}
+
+fn round_trip<T: Codec + PartialEq + std::fmt::Debug>(value: T) {
+    let mut buf = Vec::new();
+    value.encode(&mut buf);
+    assert_eq!(T::decode(&buf), Some(value));
+}
+
+#[test]
+fn codec_round_trips() {
+    round_trip(0u8);
+    round_trip(-12345i32);
+    round_trip(u128::MAX);
+    round_trip(usize::MAX);
+    round_trip(-1isize);
+    round_trip(true);
+    round_trip('λ');
+    round_trip(());
+    round_trip("hello".to_string());
+    round_trip(vec![0u8, 255]);
+    round_trip(Some(3u16));
+    round_trip(None::<u16>);
+    round_trip(Some(None::<bool>));
+    round_trip(("key".to_string(), (1u32, "value".to_string())));
+    round_trip((String::new(), String::new()));
+
+    assert_eq!(u32::decode(&[1, 2, 3]), None);
+    assert_eq!(bool::decode(&[2]), None);
+    assert_eq!(char::decode(&0xd800u32.to_le_bytes()), None);
+    assert_eq!(String::decode(&[0xff]), None);
+    assert_eq!(Option::<u8>::decode(&[0, 0]), None);
+    assert_eq!(<(u8, u8)>::decode(&[5, 0, 0, 0, 1]), None);
+}
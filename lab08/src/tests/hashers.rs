@@ -0,0 +1,68 @@
+use std::hash::{BuildHasher, Hasher};
+
+use crate::{
+    chashmap::CHashMap,
+    hashers::{BadBuildHasher, BadHasher, FnvBuildHasher, FnvHasher, FxBuildHasher, FxHasher},
+    myhashmap::MyHashMap,
+    ohashmap::OHashMap,
+    Map61B,
+};
+
+#[test]
+fn fnv_known_values() {
+    // from the FNV reference test vectors
+    assert_eq!(FnvHasher::default().finish(), 0xcbf29ce484222325);
+    let mut h = FnvHasher::default();
+    h.write(b"a");
+    assert_eq!(h.finish(), 0xaf63dc4c8601ec8c);
+    let mut h = FnvHasher::default();
+    h.write(b"foobar");
+    assert_eq!(h.finish(), 0x85944171f73967e8);
+}
+
+#[test]
+fn fx_mixes_every_word() {
+    let hash = |bytes: &[u8]| {
+        let mut h = FxHasher::default();
+        h.write(bytes);
+        h.finish()
+    };
+    assert_eq!(hash(b""), 0);
+    // a byte past the first 8 still changes the hash
+    assert_ne!(hash(b"abcdefgh1"), hash(b"abcdefgh2"));
+    assert_ne!(hash(b"ab"), hash(b"ba"));
+    let fx = FxBuildHasher::default();
+    assert_eq!(fx.hash_one(12345usize), fx.hash_one(12345usize));
+    assert_ne!(fx.hash_one(1usize), fx.hash_one(2usize));
+}
+
+#[test]
+fn bad_hasher_collides() {
+    let bad = BadBuildHasher::default();
+    assert_eq!(bad.hash_one(1usize), bad.hash_one(256usize));
+    assert_eq!(bad.hash_one("ab"), bad.hash_one("ba"));
+    let mut h = BadHasher::default();
+    h.write(&[255; 8]);
+    assert_eq!(h.finish(), 8 * 255);
+}
+
+#[test]
+fn with_capacity_and_hasher() {
+    let mut m = MyHashMap::with_capacity_and_hasher(1000, FnvBuildHasher::default());
+    let mut c = CHashMap::with_capacity_and_hasher(1000, FxBuildHasher::default());
+    let mut o = OHashMap::with_hasher(BadBuildHasher::default());
+    for i in 0..1000 {
+        m.insert(i, i);
+        c.insert(i, i);
+        o.insert(i, i);
+    }
+    for i in 0..1000 {
+        assert_eq!(m.get(&i), Some(&i));
+        assert_eq!(c.get(&i), Some(&i));
+        assert_eq!(o.get(&i), Some(&i));
+    }
+    assert_eq!(
+        m.hasher().hash_one("x"),
+        FnvBuildHasher::default().hash_one("x")
+    );
+}
@@ -1,7 +1,131 @@
-use crate::myhashmap::MyHashMap;
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    hash::{Hash, Hasher},
+};
 
-mod extra;
-mod map;
+use crate::{
+    chashmap::CHashMap,
+    hashers::{BadBuildHasher, FnvBuildHasher, FxBuildHasher},
+    myhashmap::MyHashMap,
+    ohashmap::OHashMap,
+    probing::{DHashMap, QHashMap},
+    rhhashmap::RHashMap,
+    shashmap::SHashMap,
+    ullmap::ULLMap,
+    Map61B,
+};
 
-// This can be changed to any Map61B type that you want to test
-type TestMap<K, V> = MyHashMap<K, V>;
+mod diskmap;
+mod hashers;
+mod policy;
+mod probing;
+mod sharded;
+mod swiss;
+
+// the hashmaps with other hashers, which should behave exactly the same
+type FxMyHashMap<K, V> = MyHashMap<K, V, FxBuildHasher>;
+type FnvCHashMap<K, V> = CHashMap<K, V, FnvBuildHasher>;
+type FxOHashMap<K, V> = OHashMap<K, V, FxBuildHasher>;
+type BadMyHashMap<K, V> = MyHashMap<K, V, BadBuildHasher>;
+type BadCHashMap<K, V> = CHashMap<K, V, BadBuildHasher>;
+type BadOHashMap<K, V> = OHashMap<K, V, BadBuildHasher>;
+type BadRHashMap<K, V> = RHashMap<K, V, BadBuildHasher>;
+type BadQHashMap<K, V> = QHashMap<K, V, BadBuildHasher>;
+type BadDHashMap<K, V> = DHashMap<K, V, BadBuildHasher>;
+type BadSHashMap<K, V> = SHashMap<K, V, BadBuildHasher>;
+
+/// Runs the tests from map61b_tests against a Map61B type. Add `required_only`
+/// to skip the tests of remove and into_iter.
+macro_rules! map_tests {
+    ($name: ident, $map: ident $(, $required_only: ident)?) => {
+        mod $name {
+            use super::$map;
+
+            map61b_tests::map61b_tests!($map, crate::Map61B, hash $(, $required_only)?);
+        }
+    };
+}
+
+map_tests!(myhashmap, MyHashMap);
+map_tests!(ullmap, ULLMap);
+map_tests!(chashmap, CHashMap);
+map_tests!(ohashmap, OHashMap);
+map_tests!(rhhashmap, RHashMap);
+map_tests!(qhashmap, QHashMap);
+map_tests!(dhashmap, DHashMap);
+map_tests!(shashmap, SHashMap);
+map_tests!(hashmap, HashMap);
+map_tests!(fx_myhashmap, FxMyHashMap);
+map_tests!(fnv_chashmap, FnvCHashMap);
+map_tests!(fx_ohashmap, FxOHashMap);
+map_tests!(bad_myhashmap, BadMyHashMap);
+map_tests!(bad_chashmap, BadCHashMap);
+map_tests!(bad_ohashmap, BadOHashMap);
+map_tests!(bad_rhhashmap, BadRHashMap);
+map_tests!(bad_qhashmap, BadQHashMap);
+map_tests!(bad_dhashmap, BadDHashMap);
+map_tests!(bad_shashmap, BadSHashMap);
+
+// MyHashMap has its own drain and retain, since the default ones use into_iter
+#[test]
+fn myhashmap_drain_and_retain() {
+    let mut m: MyHashMap<u32, u32> = MyHashMap::new();
+    for i in 0..100 {
+        m.insert(i, i);
+    }
+    m.retain(|k, v| {
+        *v *= 2;
+        k.is_multiple_of(10)
+    });
+    assert_eq!(m.len(), 10);
+    assert_eq!(m.get(&30), Some(&60));
+    assert!(!m.contains_key(&31));
+
+    let mut drained: Vec<_> = m.drain().collect();
+    drained.sort();
+    let expected: Vec<_> = (0..100).step_by(10).map(|k| (k, 2 * k)).collect();
+    assert_eq!(drained, expected);
+    assert!(m.is_empty());
+    assert_eq!(m.iter().count(), 0);
+    m.insert(1, 1);
+    assert_eq!(m.len(), 1);
+}
+
+/// A key that counts how many times it's compared, to see how many lookups a map does
+struct CountedKey<'a>(u32, &'a Cell<usize>);
+
+impl Hash for CountedKey<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl PartialEq for CountedKey<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.1.set(self.1.get() + 1);
+        self.0 == other.0
+    }
+}
+
+impl Eq for CountedKey<'_> {}
+
+// the chaining maps have their own entry, which only looks the key up once
+#[test]
+fn entry_looks_up_once() {
+    let compares = Cell::new(0);
+    let mut m: BadMyHashMap<CountedKey, u32> = MyHashMap::new();
+    let mut c: BadCHashMap<CountedKey, u32> = CHashMap::new();
+    for i in 0..3 {
+        m.insert(CountedKey(i, &compares), i);
+        c.insert(CountedKey(i, &compares), i);
+    }
+    // with the bad hasher, keys 0, 1 and 2 are in different buckets
+    compares.set(0);
+    *m.entry(CountedKey(1, &compares)).or_insert(0) += 1;
+    assert_eq!(compares.get(), 1);
+    *c.entry(CountedKey(1, &compares)).or_insert(0) += 1;
+    assert_eq!(compares.get(), 2);
+    assert_eq!(m.get(&CountedKey(1, &compares)), Some(&2));
+    assert_eq!(c.get(&CountedKey(1, &compares)), Some(&2));
+}
@@ -0,0 +1,253 @@
+use crate::{
+    chashmap::CHashMap,
+    myhashmap::MyHashMap,
+    ohashmap::OHashMap,
+    policy::{LoadFactor, ResizePolicy},
+    probing::{DHashMap, QHashMap},
+    rhhashmap::RHashMap,
+    shashmap::SHashMap,
+    Map61B,
+};
+
+#[test]
+fn policy_sizes() {
+    let p = ResizePolicy::new(0.1, 0.5, 0.75);
+    assert_eq!(p.buckets_for(75), 100);
+    assert_eq!(p.buckets_for(76), 102);
+    assert_eq!(p.resized_buckets(10), 20);
+    assert_eq!(p.capacity(100), 75);
+    assert!(p.should_grow(76, 100));
+    assert!(!p.should_grow(75, 100));
+    assert!(p.should_shrink(9, 100));
+    assert!(!p.should_shrink(10, 100));
+}
+
+#[test]
+#[should_panic]
+fn policy_out_of_order() {
+    ResizePolicy::new(0.5, 0.25, 0.75);
+}
+
+#[test]
+#[should_panic]
+fn open_addressing_needs_empty_buckets() {
+    let mut m: OHashMap<u32, u32> = OHashMap::new();
+    m.set_policy(ResizePolicy::new(0.5, 0.75, 1.5));
+}
+
+/// Checks with_capacity, reserve and shrink_to_fit on one of the map types,
+/// which all have them as inherent methods rather than through a trait
+macro_rules! check_capacity {
+    ($map: ident) => {{
+        // nothing resizes while filling a map up to its capacity
+        let mut m: $map<usize, usize> = $map::with_capacity(500);
+        let capacity = m.capacity();
+        assert!(
+            capacity >= 500,
+            "{}: capacity {}",
+            stringify!($map),
+            capacity
+        );
+        for i in 0..500 {
+            m.insert(i, i);
+        }
+        assert_eq!(m.capacity(), capacity, "{}", stringify!($map));
+
+        m.reserve(1000);
+        let capacity = m.capacity();
+        assert!(
+            capacity >= 1500,
+            "{}: capacity {}",
+            stringify!($map),
+            capacity
+        );
+        for i in 500..1500 {
+            m.insert(i, i);
+        }
+        assert_eq!(m.capacity(), capacity, "{}", stringify!($map));
+
+        // removing 90% isn't sparse enough to shrink, until shrink_to_fit
+        for i in 150..1500 {
+            assert_eq!(m.remove(&i), Some(i), "{}", stringify!($map));
+        }
+        assert_eq!(m.capacity(), capacity, "{}", stringify!($map));
+        m.shrink_to_fit();
+        assert!(m.capacity() >= 150, "{}", stringify!($map));
+        assert!(
+            m.capacity() < 500,
+            "{}: capacity {}",
+            stringify!($map),
+            m.capacity()
+        );
+        for i in 0..1500 {
+            assert_eq!(
+                m.get(&i).copied(),
+                (i < 150).then_some(i),
+                "{}",
+                stringify!($map)
+            );
+        }
+    }};
+}
+
+#[test]
+fn capacity_and_reserve() {
+    check_capacity!(MyHashMap);
+    check_capacity!(CHashMap);
+    check_capacity!(OHashMap);
+    check_capacity!(RHashMap);
+    check_capacity!(QHashMap);
+    check_capacity!(DHashMap);
+    check_capacity!(SHashMap);
+}
+
+#[test]
+fn myhashmap_shrinks_when_sparse() {
+    let mut m: MyHashMap<u32, u32> = MyHashMap::new();
+    let starting = m.capacity();
+    for i in 0..10000 {
+        m.insert(i, i);
+    }
+    let full = m.capacity();
+    for i in 10..10000 {
+        assert_eq!(m.remove(&i), Some(i));
+        // never shrinks so far that it has to grow again straight away
+        assert!(m.load_factor() <= m.policy().max_load_factor());
+    }
+    assert!(m.capacity() < full / 100, "capacity {}", m.capacity());
+    assert_eq!(m.remove(&10), None);
+    for i in 0..10 {
+        assert_eq!(m.remove(&i), Some(i));
+    }
+    assert!(m.is_empty());
+    assert_eq!(m.capacity(), starting);
+}
+
+#[test]
+fn chashmap_shrinks_when_sparse() {
+    let mut m: CHashMap<u32, u32> = CHashMap::new();
+    for i in 0..10000 {
+        m.insert(i, i);
+    }
+    let full = m.capacity();
+    for i in 10..10000 {
+        assert_eq!(m.remove(&i), Some(i));
+    }
+    assert!(m.capacity() < full / 100, "capacity {}", m.capacity());
+    let mut left: Vec<_> = m.into_iter().collect();
+    left.sort();
+    assert_eq!(left, (0..10).map(|i| (i, i)).collect::<Vec<_>>());
+}
+
+#[test]
+fn myhashmap_into_iter() {
+    let mut m: MyHashMap<u32, String> = MyHashMap::new();
+    for i in 0..100 {
+        m.insert(i, i.to_string());
+    }
+    let mut entries: Vec<_> = m.into_iter().collect();
+    entries.sort();
+    let expected: Vec<_> = (0..100).map(|i| (i, i.to_string())).collect();
+    assert_eq!(entries, expected);
+}
+
+// the chaining maps can run with several entries per bucket
+#[test]
+fn set_policy() {
+    let policy = ResizePolicy::new(0.5, 2.0, 4.0);
+    let mut m: MyHashMap<u32, u32> = MyHashMap::new();
+    let mut c: CHashMap<u32, u32> = CHashMap::new();
+    m.set_policy(policy);
+    c.set_policy(policy);
+    assert_eq!(m.policy(), &policy);
+    for i in 0..1000 {
+        m.insert(i, i);
+        c.insert(i, i);
+        assert!(m.load_factor() <= 4.0);
+        assert!(c.load_factor() <= 4.0);
+    }
+    assert!(m.load_factor() >= 0.5);
+    assert!(c.load_factor() >= 0.5);
+
+    // a stricter policy grows the map straight away
+    let mut o: OHashMap<u32, u32> = OHashMap::new();
+    for i in 0..1000 {
+        o.insert(i, i);
+    }
+    o.set_policy(ResizePolicy::new(0.01, 0.1, 0.2));
+    assert!(o.load_factor() <= 0.2);
+    assert!((0..1000).all(|i| o.get(&i) == Some(&i)));
+}
+
+#[test]
+fn load_factors() {
+    let mut c: CHashMap<usize, usize> = CHashMap::new();
+    let mut s: SHashMap<usize, usize> = SHashMap::new();
+    for i in 0..5 {
+        c.insert(i, i);
+        s.insert(i, i);
+    }
+    assert_eq!(c.load_factor(), 0.5);
+    assert_eq!(s.load_factor(), 5.0 / 16.0);
+}
+
+/// Checks that drain, retain and clear keep a map's policy and buckets, and that
+/// removing entries never shrinks it below the capacity it was created with
+macro_rules! check_keeps_settings {
+    ($map: ident) => {{
+        let mut m: $map<usize, usize> = $map::with_capacity(1000);
+        let capacity = m.capacity();
+        for i in 0..1000 {
+            m.insert(i, i);
+        }
+        // sparse enough to shrink, if the capacity weren't reserved
+        for i in 10..1000 {
+            m.remove(&i);
+        }
+        assert_eq!(m.capacity(), capacity, "{}", stringify!($map));
+
+        let policy = ResizePolicy::new(0.1, 0.3, 0.6);
+        m.set_policy(policy);
+        for i in 10..2000 {
+            m.insert(i, i);
+        }
+        let capacity = m.capacity();
+        m.retain(|&k, _| k % 10 == 0);
+        assert_eq!(m.len(), 200, "{}", stringify!($map));
+        assert!(
+            (0..2000).all(|i| m.get(&i).copied() == (i % 10 == 0).then_some(i)),
+            "{}",
+            stringify!($map)
+        );
+        assert_eq!(m.policy(), &policy, "{}", stringify!($map));
+        assert_eq!(m.capacity(), capacity, "{}", stringify!($map));
+
+        let mut drained: Vec<_> = m.drain().collect();
+        drained.sort();
+        assert_eq!(
+            drained,
+            (0..2000).step_by(10).map(|i| (i, i)).collect::<Vec<_>>()
+        );
+        assert!(m.is_empty(), "{}", stringify!($map));
+        assert_eq!(m.policy(), &policy, "{}", stringify!($map));
+        assert_eq!(m.capacity(), capacity, "{}", stringify!($map));
+
+        m.insert(1, 1);
+        m.clear();
+        assert!(m.is_empty(), "{}", stringify!($map));
+        assert_eq!(m.capacity(), capacity, "{}", stringify!($map));
+        m.insert(1, 1);
+        assert_eq!(m.get(&1), Some(&1), "{}", stringify!($map));
+    }};
+}
+
+#[test]
+fn drain_and_retain_keep_settings() {
+    check_keeps_settings!(MyHashMap);
+    check_keeps_settings!(CHashMap);
+    check_keeps_settings!(OHashMap);
+    check_keeps_settings!(RHashMap);
+    check_keeps_settings!(QHashMap);
+    check_keeps_settings!(DHashMap);
+    check_keeps_settings!(SHashMap);
+}
@@ -0,0 +1,94 @@
+use crate::{
+    hashers::BadBuildHasher,
+    ohashmap::OHashMap,
+    probing::{
+        DHashMap, DoubleHashing, ProbeLengths, ProbeSequence, ProbeStats, QHashMap, Quadratic,
+    },
+    rhhashmap::RHashMap,
+    Map61B,
+};
+
+/// Checks that the first len buckets of P's probe sequences are all different
+fn covers_every_bucket<P: ProbeSequence>() {
+    for len in [16, 64, 1024] {
+        for hash in [0, 1, 7, 0xdead_beef_1234_5678, u64::MAX] {
+            let mut seen = vec![false; len];
+            for i in 0..len {
+                let b = P::bucket(hash, i, len);
+                assert!(!seen[b], "bucket {} visited twice for hash {}", b, hash);
+                seen[b] = true;
+            }
+        }
+    }
+}
+
+#[test]
+fn probe_sequences_cover_every_bucket() {
+    covers_every_bucket::<Quadratic>();
+    covers_every_bucket::<DoubleHashing>();
+}
+
+#[test]
+fn probe_stats_of_lengths() {
+    let stats = ProbeStats::new(10, [1, 2, 3]);
+    assert_eq!(stats.entries, 3);
+    assert_eq!(stats.buckets, 10);
+    assert_eq!(stats.mean, 2.0);
+    assert!((stats.variance - 2.0 / 3.0).abs() < 1e-9);
+    assert_eq!(stats.max, 3);
+    assert_eq!(ProbeStats::new(16, []).max, 0);
+}
+
+// with the bad hasher, 1, 256 and 65536 all hash to 1 and collide
+#[test]
+fn linear_probe_lengths() {
+    let mut o = OHashMap::with_hasher(BadBuildHasher::default());
+    let mut r = RHashMap::with_hasher(BadBuildHasher::default());
+    for k in [1usize, 256, 65536] {
+        o.insert(k, k);
+        r.insert(k, k);
+    }
+    assert_eq!(o.probe_stats(), ProbeStats::new(10, [1, 2, 3]));
+    assert_eq!(r.probe_stats(), ProbeStats::new(10, [1, 2, 3]));
+
+    // Robin Hood shifts the other two back instead of leaving a tombstone
+    assert_eq!(r.remove(&1), Some(1));
+    assert_eq!(r.probe_stats(), ProbeStats::new(10, [1, 2]));
+    assert_eq!(r.get(&65536), Some(&65536));
+}
+
+#[test]
+fn robin_hood_evens_out_probe_lengths() {
+    // keys 0, 1, 2, ... land in consecutive home buckets with the bad hasher,
+    // so clusters run into each other
+    let mut o = OHashMap::with_capacity_and_hasher(2000, BadBuildHasher::default());
+    let mut r = RHashMap::with_capacity_and_hasher(2000, BadBuildHasher::default());
+    for k in 0..2000usize {
+        o.insert(k, k);
+        r.insert(k, k);
+    }
+    let (o, r) = (o.probe_stats(), r.probe_stats());
+    // the total distance is the same, but Robin Hood shares it out
+    assert!(r.variance < o.variance);
+    assert!(r.max < o.max);
+}
+
+#[test]
+fn tombstones_are_reused() {
+    let mut q: QHashMap<usize, usize> = QHashMap::new();
+    let mut d: DHashMap<usize, usize> = DHashMap::new();
+    // churning through many keys never leaves the table without free buckets
+    for k in 0..10_000 {
+        q.insert(k, k);
+        d.insert(k, k);
+        if k >= 8 {
+            assert_eq!(q.remove(&(k - 8)), Some(k - 8));
+            assert_eq!(d.remove(&(k - 8)), Some(k - 8));
+        }
+    }
+    assert_eq!(q.len(), 8);
+    assert_eq!(d.len(), 8);
+    // and clearing out tombstones doesn't grow the table
+    assert!(q.probe_stats().buckets <= 32);
+    assert_eq!(d.probe_stats().entries, 8);
+}
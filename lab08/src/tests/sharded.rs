@@ -0,0 +1,183 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::Barrier,
+    thread,
+};
+
+use map61b_common::bench::{bench_concurrent, ConcurrentConfig, Dist};
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{hashers::BadBuildHasher, sharded::ShardedMap};
+
+const THREADS: usize = 8;
+
+#[test]
+fn single_thread() {
+    let m: ShardedMap<String, u32> = ShardedMap::with_shards(4);
+    assert!(m.is_empty());
+    assert_eq!(m.insert("a".to_string(), 1), None);
+    assert_eq!(m.insert("b".to_string(), 2), None);
+    assert_eq!(m.insert("a".to_string(), 3), Some(1));
+    assert_eq!(m.len(), 2);
+    assert_eq!(m.get_cloned("a"), Some(3));
+    assert_eq!(*m.get("b").unwrap(), 2);
+    assert!(m.get("c").is_none());
+    assert!(m.contains_key("b"));
+
+    m.update_with("c".to_string(), || 10, |v| *v += 1);
+    assert_eq!(
+        m.update_with("c".to_string(), || 10, |v| std::mem::replace(v, 0)),
+        11
+    );
+    assert_eq!(m.get_cloned("c"), Some(0));
+
+    assert_eq!(m.remove("a"), Some(3));
+    assert_eq!(m.remove("a"), None);
+    let mut entries: Vec<_> = m.into_iter().collect();
+    entries.sort();
+    assert_eq!(entries, [("b".to_string(), 2), ("c".to_string(), 0)]);
+}
+
+#[test]
+#[should_panic]
+fn no_shards() {
+    let _: ShardedMap<u32, u32> = ShardedMap::with_shards(0);
+}
+
+// the bad hasher's hashes all fit in the bottom half, but still get spread out
+#[test]
+fn bad_hasher() {
+    let m = ShardedMap::with_shards_and_hasher(16, BadBuildHasher::default());
+    for i in 0..1000u32 {
+        m.insert(i, i);
+    }
+    assert_eq!(m.len(), 1000);
+    let lens = m.shard_lens();
+    assert!(lens.iter().all(|&len| len < 250), "shard lengths {lens:?}");
+    assert!((0..1000).all(|i| m.get_cloned(&i) == Some(i)));
+    m.clear();
+    assert!(m.is_empty());
+}
+
+// each thread inserts and removes its own keys, and none of them get lost
+#[test]
+fn threads_insert_and_remove() {
+    let m: ShardedMap<usize, usize> = ShardedMap::with_shards(4);
+    thread::scope(|s| {
+        for t in 0..THREADS {
+            let m = &m;
+            s.spawn(move || {
+                for i in (t..10000).step_by(THREADS) {
+                    assert_eq!(m.insert(i, i * 2), None);
+                }
+                for i in (t..10000).step_by(THREADS * 2) {
+                    assert_eq!(m.remove(&i), Some(i * 2));
+                }
+            });
+        }
+    });
+    // thread t removes the keys that are t mod 2 * THREADS, half of them
+    assert_eq!(m.len(), 5000);
+    for i in 0..10000 {
+        let removed = i % (THREADS * 2) < THREADS;
+        assert_eq!(m.get_cloned(&i), (!removed).then_some(i * 2), "{}", i);
+    }
+}
+
+// increments from every thread on the same few keys have to all count
+#[test]
+fn update_with_is_atomic() {
+    let m: ShardedMap<u32, u64> = ShardedMap::with_shards(3);
+    let barrier = Barrier::new(THREADS);
+    thread::scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|| {
+                barrier.wait();
+                for i in 0..5000 {
+                    m.update_with(i % 10, || 0, |v| *v += 1);
+                }
+            });
+        }
+    });
+    for k in 0..10 {
+        assert_eq!(m.get_cloned(&k), Some(500 * THREADS as u64));
+    }
+}
+
+// readers never see a value that wasn't written as a whole: every value is a
+// vector of one number repeated, and writers replace it with a longer one
+#[test]
+fn readers_see_whole_values() {
+    let m: ShardedMap<u32, Vec<u32>> = ShardedMap::with_shards(2);
+    for k in 0..50 {
+        m.insert(k, vec![0]);
+    }
+    thread::scope(|s| {
+        for t in 0..THREADS / 2 {
+            let m = &m;
+            s.spawn(move || {
+                for round in 1..200 {
+                    for k in (t as u32..50).step_by(THREADS / 2) {
+                        m.insert(k, vec![round; round as usize]);
+                    }
+                }
+            });
+        }
+        for _ in 0..THREADS / 2 {
+            s.spawn(|| {
+                for k in (0..50).cycle().take(20000) {
+                    // the guard has to go before locking the shard again,
+                    // or a waiting writer could block both
+                    {
+                        let v = m.get(&k).unwrap();
+                        assert!(v.iter().all(|&x| x == v.len() as u32) || *v == [0]);
+                    }
+                    let v = m.get_cloned(&k).unwrap();
+                    assert!(v.iter().all(|&x| x == v.len() as u32) || v == [0]);
+                }
+            });
+        }
+    });
+    assert!((0..50).all(|k| m.get_cloned(&k) == Some(vec![199; 199])));
+}
+
+#[test]
+fn concurrent_benchmark() {
+    let config = ConcurrentConfig {
+        threads: vec![1, 4],
+        dist: Dist::Zipf,
+        sizes: "1e3".parse().unwrap(),
+        repeat: 2,
+        read_percent: 50,
+    };
+    let mut lines = Vec::new();
+    bench_concurrent::<ShardedMap<usize, usize>, _>(
+        "ShardedMap",
+        &config,
+        &mut StdRng::seed_from_u64(61),
+        |row| lines.push(row.to_string()),
+    );
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("ShardedMap,1,zipf,1000,2,50,"));
+    assert!(lines[1].starts_with("ShardedMap,4,zipf,1000,2,50,"));
+}
+
+// a panic in update_with's closure takes its entry with it, and the map carries on
+#[test]
+fn update_with_panics() {
+    let m: ShardedMap<u32, Vec<u32>> = ShardedMap::with_shards(2);
+    m.insert(1, vec![1]);
+    for k in [1, 2] {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            m.update_with(k, Vec::new, |v| {
+                v.push(0);
+                panic!("half done");
+            })
+        }));
+        assert!(result.is_err());
+        assert!(!m.contains_key(&k));
+    }
+    assert!(m.is_empty());
+    assert_eq!(m.update_with(1, Vec::new, |v| v.len()), 0);
+    assert_eq!(m.get_cloned(&1), Some(vec![]));
+}
@@ -0,0 +1,72 @@
+use crate::{
+    hashers::BadBuildHasher,
+    shashmap::{Group, SHashMap},
+    Map61B,
+};
+
+const EMPTY: u8 = 0xff;
+const DELETED: u8 = 0x80;
+
+fn group(bytes: [u8; 16]) -> Group {
+    Group::load(&bytes)
+}
+
+#[test]
+fn group_matches() {
+    let mut bytes = [0x55; 16];
+    bytes[0] = EMPTY;
+    bytes[3] = DELETED;
+    bytes[7] = 0x12;
+    bytes[15] = 0x12;
+    bytes[9] = EMPTY;
+    let g = group(bytes);
+    assert_eq!(g.match_byte(0x12).collect::<Vec<_>>(), [7, 15]);
+    assert_eq!(g.match_empty().collect::<Vec<_>>(), [0, 9]);
+    assert_eq!(g.match_empty_or_deleted().collect::<Vec<_>>(), [0, 3, 9]);
+    assert!(!g.match_byte(0x00).any());
+    assert_eq!(g.match_byte(0x55).count(), 11);
+}
+
+#[test]
+fn full_group_has_no_free_buckets() {
+    let g = group([0x7f; 16]);
+    assert!(!g.match_empty().any());
+    assert!(!g.match_empty_or_deleted().any());
+    assert_eq!(g.match_byte(0x7f).count(), 16);
+    // the only control byte that looks empty is EMPTY itself
+    let g = group([DELETED; 16]);
+    assert!(!g.match_empty().any());
+    assert_eq!(g.match_empty_or_deleted().count(), 16);
+}
+
+#[test]
+fn match_byte_false_positives_are_only_after_a_match() {
+    // 0x13 ^ 0x12 is 1, which the borrow from the real match below can turn
+    // into a false positive, so the keys must always be compared
+    let mut bytes = [0x00; 16];
+    bytes[4] = 0x12;
+    bytes[5] = 0x13;
+    let matches: Vec<_> = group(bytes).match_byte(0x12).collect();
+    assert_eq!(matches[0], 4);
+    assert!(matches.iter().all(|&i| i == 4 || i == 5));
+}
+
+// with the bad hasher, every key has the same 7 bits in its control byte,
+// and nearby keys share groups, so removals have to leave tombstones
+#[test]
+fn churn_with_collisions() {
+    let mut m: SHashMap<usize, usize, BadBuildHasher> = SHashMap::new();
+    for round in 0..20 {
+        for i in 0..200 {
+            m.insert(i * 256, round);
+        }
+        for i in (0..200).filter(|i| i % 3 != 0) {
+            assert_eq!(m.remove(&(i * 256)), Some(round));
+        }
+        assert_eq!(m.len(), 67);
+        for i in 0..200 {
+            let expected = if i % 3 == 0 { Some(&round) } else { None };
+            assert_eq!(m.get(&(i * 256)), expected);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+/*
+A global allocator that hands everything to the system allocator, while keeping
+count of how many bytes are allocated and how many allocations have been made.
+The benchmark installs it with #[global_allocator], and works out how much heap
+memory a map uses from the counts before and after building it. Nothing is
+counted unless a binary installs it.
+
+Every reallocation counts as an allocation, since growing a Vec usually means
+copying it somewhere new.
+*/
+pub struct CountingAllocator;
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// The allocator's counts at some point in time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AllocStats {
+    /// bytes allocated and not yet freed
+    pub live_bytes: usize,
+    /// allocations and reallocations made so far
+    pub allocations: usize,
+}
+
+impl AllocStats {
+    /// Returns the allocator's counts, or None if it isn't counting
+    pub fn now() -> Option<Self> {
+        ACTIVE.load(Ordering::Relaxed).then(|| AllocStats {
+            live_bytes: LIVE_BYTES.load(Ordering::Relaxed),
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        })
+    }
+
+    /// Returns the bytes allocated and allocations made between earlier and self
+    pub fn since(&self, earlier: &AllocStats) -> AllocStats {
+        AllocStats {
+            live_bytes: self.live_bytes.saturating_sub(earlier.live_bytes),
+            allocations: self.allocations - earlier.allocations,
+        }
+    }
+}
+
+fn record_alloc(size: usize) {
+    ACTIVE.store(true, Ordering::Relaxed);
+    LIVE_BYTES.fetch_add(size, Ordering::Relaxed);
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+            record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
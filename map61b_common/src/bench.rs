@@ -0,0 +1,545 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::BuildHasher,
+    hint::black_box,
+    iter,
+    str::FromStr,
+    sync::{Mutex, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{allocator::AllocStats, MapUnderTest};
+
+/*
+The pieces of the labs' benchmark binaries that don't depend on which maps they run:
+the operations it times, the keys it times them on, and the statistics it
+reports. Every benchmark makes a Workload of n keys, and times one operation
+over all of them, repeat times. The median and 95th percentile of those times
+go into a CSV row, along with how much the median grew since the previous n,
+like the timing tables in lab03. Rows for inserts also say how much heap memory
+the map took, if the binary counts allocations with CountingAllocator, and how
+full it is, if it's a hashmap.
+
+The concurrent benchmarks are for maps that threads can share. They time some
+number of threads all working through the same Workload's queries at once, and
+report how many operations per second that got through, and how much faster
+that is than with the first number of threads.
+
+The maps are run through MapUnderTest, like in the tests, so a binary wraps its
+lab's maps with map61b_subject!. The standard library's maps can be
+benchmarked as they are.
+*/
+
+/// An operation to time, n times
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    /// inserts the queries into an empty map
+    Insert,
+    /// looks up the queries in a map holding the fill keys
+    Get,
+    /// removes the queries from a map holding the fill keys
+    Remove,
+    /// half gets, a quarter inserts and a quarter removes of the queries,
+    /// interleaved, on a map holding the fill keys
+    Mixed,
+}
+
+impl FromStr for Op {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "insert" => Ok(Op::Insert),
+            "get" => Ok(Op::Get),
+            "remove" => Ok(Op::Remove),
+            "mixed" => Ok(Op::Mixed),
+            _ => Err("Not a valid operation: use insert, get, remove or mixed"),
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Op::Insert => "insert",
+            Op::Get => "get",
+            Op::Remove => "remove",
+            Op::Mixed => "mixed",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Which keys the operations use
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dist {
+    /// 0, 1, 2, ... in order
+    Ordered,
+    /// uniformly random usizes, each used once
+    Random,
+    /// 0..n, with key k queried with probability roughly proportional to 1 / (k + 1),
+    /// so a few keys are used over and over
+    Zipf,
+}
+
+impl FromStr for Dist {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ordered" => Ok(Dist::Ordered),
+            "random" => Ok(Dist::Random),
+            "zipf" => Ok(Dist::Zipf),
+            _ => Err("Not a valid distribution: use ordered, random or zipf"),
+        }
+    }
+}
+
+impl fmt::Display for Dist {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Dist::Ordered => "ordered",
+            Dist::Random => "random",
+            Dist::Zipf => "zipf",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The values of n to benchmark: start, then doubling for as long as it's at most end
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sizes {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Parses a size like 1000 or 1e3
+fn parse_size(s: &str) -> Option<usize> {
+    if let Ok(n) = s.parse() {
+        return Some(n);
+    }
+    let n: f64 = s.parse().ok()?;
+    (n.is_finite() && n >= 0.0 && n.fract() == 0.0 && n <= usize::MAX as f64).then_some(n as usize)
+}
+
+impl FromStr for Sizes {
+    type Err = &'static str;
+
+    /// Parses either a single size, or a range like 1e3..1e7
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s.split_once("..").unwrap_or((s, s));
+        match (parse_size(start), parse_size(end)) {
+            (Some(start), Some(end)) if 0 < start && start <= end => Ok(Sizes { start, end }),
+            _ => Err("Not a valid size: use a number like 1000 or 1e3, or a range like 1e3..1e6"),
+        }
+    }
+}
+
+impl Sizes {
+    pub fn iter(&self) -> impl Iterator<Item = usize> {
+        let end = self.end;
+        iter::successors(Some(self.start), |n| n.checked_mul(2)).take_while(move |&n| n <= end)
+    }
+}
+
+/// Returns a key in 0..n, where k has probability ln((k + 2) / (k + 1)) / ln(n + 1),
+/// which is close to Zipf's law with exponent 1
+pub fn zipf_key(n: usize, rng: &mut impl Rng) -> usize {
+    let x = ((n + 1) as f64).powf(rng.gen::<f64>()) as usize;
+    x.saturating_sub(1).min(n - 1)
+}
+
+/// The keys for one run of a benchmark
+pub struct Workload {
+    /// the keys in the map before the timing starts, for every operation except insert
+    pub fill: Vec<usize>,
+    /// the keys used by the timed operations
+    pub queries: Vec<usize>,
+}
+
+impl Workload {
+    pub fn new(dist: Dist, n: usize, rng: &mut impl Rng) -> Self {
+        match dist {
+            Dist::Ordered => Workload {
+                fill: (0..n).collect(),
+                queries: (0..n).collect(),
+            },
+            Dist::Random => {
+                let fill: Vec<usize> = (0..n).map(|_| rng.gen()).collect();
+                let mut queries = fill.clone();
+                queries.shuffle(rng);
+                Workload { fill, queries }
+            }
+            Dist::Zipf => Workload {
+                fill: (0..n).collect(),
+                queries: (0..n).map(|_| zipf_key(n, rng)).collect(),
+            },
+        }
+    }
+}
+
+/// Runs op on a new T with workload's keys, and returns how long the operation took
+/// and the map it left behind
+pub fn time_op<T: MapUnderTest<usize, usize>>(op: Op, workload: &Workload) -> (Duration, T) {
+    let mut map = T::new();
+    if op != Op::Insert {
+        for &k in &workload.fill {
+            map.insert(k, k);
+        }
+    }
+    let start = Instant::now();
+    match op {
+        Op::Insert => {
+            for &k in &workload.queries {
+                map.insert(k, k);
+            }
+        }
+        Op::Get => {
+            for k in &workload.queries {
+                black_box(map.get(k));
+            }
+        }
+        Op::Remove => {
+            for k in &workload.queries {
+                black_box(map.remove(k));
+            }
+        }
+        Op::Mixed => {
+            for (i, &k) in workload.queries.iter().enumerate() {
+                match i % 4 {
+                    0 => {
+                        black_box(map.insert(k, i));
+                    }
+                    1 => {
+                        black_box(map.remove(&k));
+                    }
+                    _ => {
+                        black_box(map.get(&k));
+                    }
+                }
+            }
+        }
+    }
+    (start.elapsed(), map)
+}
+
+/// The median and 95th percentile of some timings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Summary {
+    pub median: Duration,
+    pub p95: Duration,
+}
+
+impl Summary {
+    /// Summarizes times, which must not be empty. The 95th percentile is the
+    /// smallest time that at least 95% of the times are at most.
+    pub fn new(times: &mut [Duration]) -> Self {
+        times.sort();
+        let len = times.len();
+        let median = if len % 2 == 1 {
+            times[len / 2]
+        } else {
+            (times[len / 2 - 1] + times[len / 2]) / 2
+        };
+        let p95 = times[(len * 95).div_ceil(100) - 1];
+        Summary { median, p95 }
+    }
+}
+
+/// Returns how many times longer time took than prev_time, and the exponent b that
+/// would make that growth fit time = a * n^b
+pub fn doubling_ratio(prev_n: usize, prev_time: Duration, n: usize, time: Duration) -> (f64, f64) {
+    let ratio = time.as_secs_f64() / prev_time.as_secs_f64();
+    let exponent = ratio.ln() / (n as f64 / prev_n as f64).ln();
+    (ratio, exponent)
+}
+
+/// How much heap memory a map took to build
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Memory {
+    /// bytes still allocated once it was built
+    pub bytes: usize,
+    /// allocations and reallocations made while building it
+    pub allocations: usize,
+    pub entries: usize,
+}
+
+/// One line of the benchmark's CSV output
+#[derive(Debug)]
+pub struct Row<'a> {
+    pub map: &'a str,
+    pub op: Op,
+    pub dist: Dist,
+    pub n: usize,
+    pub repeat: usize,
+    pub summary: Summary,
+    /// the doubling ratio and exponent, compared to the previous n
+    pub growth: Option<(f64, f64)>,
+    pub memory: Option<Memory>,
+    pub load_factor: Option<f64>,
+}
+
+impl Row<'_> {
+    pub const HEADER: &'static str =
+        "map,op,dist,n,repeat,median_s,p95_s,median_ns_per_op,ratio,exponent,\
+         heap_bytes,bytes_per_entry,allocations,load_factor";
+}
+
+impl fmt::Display for Row<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let median = self.summary.median.as_secs_f64();
+        write!(
+            f,
+            "{},{},{},{},{},{:.9},{:.9},{:.3},",
+            self.map,
+            self.op,
+            self.dist,
+            self.n,
+            self.repeat,
+            median,
+            self.summary.p95.as_secs_f64(),
+            median * 1e9 / self.n as f64
+        )?;
+        match self.growth {
+            Some((ratio, exponent)) => write!(f, "{:.3},{:.3},", ratio, exponent)?,
+            None => write!(f, ",,")?,
+        }
+        match self.memory {
+            Some(m) => write!(
+                f,
+                "{},{:.1},{},",
+                m.bytes,
+                m.bytes as f64 / m.entries.max(1) as f64,
+                m.allocations
+            )?,
+            None => write!(f, ",,,")?,
+        }
+        match self.load_factor {
+            Some(load_factor) => write!(f, "{:.3}", load_factor),
+            None => Ok(()),
+        }
+    }
+}
+
+/// What to benchmark each map on
+#[derive(Debug)]
+pub struct Config {
+    pub ops: Vec<Op>,
+    pub dist: Dist,
+    pub sizes: Sizes,
+    pub repeat: usize,
+}
+
+/// Benchmarks T on every operation and size in config, passing each row to emit
+/// as soon as it's done. After the last insert run at each size, inspect is
+/// given the size and the map, and load_factor is used for the row's load factor.
+pub fn bench_map<T, R>(
+    name: &str,
+    config: &Config,
+    rng: &mut R,
+    mut emit: impl FnMut(Row),
+    mut inspect: impl FnMut(usize, &T),
+    load_factor: impl Fn(&T) -> Option<f64>,
+) where
+    T: MapUnderTest<usize, usize>,
+    R: Rng,
+{
+    for &op in &config.ops {
+        let mut prev: Option<(usize, Duration)> = None;
+        for n in config.sizes.iter() {
+            let mut times = Vec::with_capacity(config.repeat);
+            let mut memory = None;
+            let mut load = None;
+            for r in 0..config.repeat {
+                let workload = Workload::new(config.dist, n, rng);
+                let before = AllocStats::now();
+                let (time, map) = time_op::<T>(op, &workload);
+                if op == Op::Insert && r + 1 == config.repeat {
+                    memory = AllocStats::now().zip(before).map(|(after, before)| {
+                        let used = after.since(&before);
+                        Memory {
+                            bytes: used.live_bytes,
+                            allocations: used.allocations,
+                            entries: map.len(),
+                        }
+                    });
+                    load = load_factor(&map);
+                    inspect(n, &map);
+                }
+                times.push(time);
+            }
+            let summary = Summary::new(&mut times);
+            emit(Row {
+                map: name,
+                op,
+                dist: config.dist,
+                n,
+                repeat: config.repeat,
+                summary,
+                growth: prev.map(|(prev_n, prev_time)| {
+                    doubling_ratio(prev_n, prev_time, n, summary.median)
+                }),
+                memory,
+                load_factor: load,
+            });
+            prev = Some((n, summary.median));
+        }
+    }
+}
+
+/// A map that threads can share, for the concurrent benchmarks
+pub trait ConcurrentMap: Sync {
+    fn new() -> Self;
+    fn insert(&self, key: usize, value: usize);
+    fn get(&self, key: usize) -> Option<usize>;
+}
+
+// the simplest way to share a map between threads, for a concurrent map to beat
+impl<S: BuildHasher + Default + Send> ConcurrentMap for Mutex<HashMap<usize, usize, S>> {
+    fn new() -> Self {
+        Mutex::default()
+    }
+
+    fn insert(&self, key: usize, value: usize) {
+        let mut map = self.lock().unwrap_or_else(PoisonError::into_inner);
+        map.insert(key, value);
+    }
+
+    fn get(&self, key: usize) -> Option<usize> {
+        let map = self.lock().unwrap_or_else(PoisonError::into_inner);
+        map.get(&key).copied()
+    }
+}
+
+/// What to benchmark each concurrent map on
+#[derive(Debug)]
+pub struct ConcurrentConfig {
+    pub threads: Vec<usize>,
+    pub dist: Dist,
+    pub sizes: Sizes,
+    pub repeat: usize,
+    /// the percentage of operations that are gets, rather than inserts
+    pub read_percent: u32,
+}
+
+/// One line of the concurrent benchmark's CSV output
+#[derive(Debug)]
+pub struct ConcurrentRow<'a> {
+    pub map: &'a str,
+    pub threads: usize,
+    pub dist: Dist,
+    pub n: usize,
+    pub repeat: usize,
+    pub read_percent: u32,
+    pub summary: Summary,
+    /// how many times faster the median run got through its operations
+    /// than the same map with the first number of threads
+    pub speedup: Option<f64>,
+}
+
+impl ConcurrentRow<'_> {
+    pub const HEADER: &'static str =
+        "map,threads,dist,n,repeat,read_percent,median_s,p95_s,mops_per_s,speedup";
+
+    /// Returns how many operations the median run did: n for each thread
+    pub fn ops(&self) -> usize {
+        self.n * self.threads
+    }
+}
+
+impl fmt::Display for ConcurrentRow<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let median = self.summary.median.as_secs_f64();
+        write!(
+            f,
+            "{},{},{},{},{},{},{:.9},{:.9},{:.3},",
+            self.map,
+            self.threads,
+            self.dist,
+            self.n,
+            self.repeat,
+            self.read_percent,
+            median,
+            self.summary.p95.as_secs_f64(),
+            self.ops() as f64 / median / 1e6
+        )?;
+        match self.speedup {
+            Some(speedup) => write!(f, "{:.3}", speedup),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Fills a new T with workload's fill keys, then has threads threads each go through
+/// all of the queries at once, starting at different places, and returns how long
+/// they took. read_percent of the queries are gets, and the rest are inserts.
+pub fn time_concurrent<T: ConcurrentMap>(
+    threads: usize,
+    read_percent: u32,
+    workload: &Workload,
+) -> Duration {
+    let map = T::new();
+    for &k in &workload.fill {
+        map.insert(k, k);
+    }
+    let queries = &workload.queries;
+    let map = &map;
+    let start = Instant::now();
+    thread::scope(|s| {
+        for t in 0..threads {
+            s.spawn(move || {
+                let offset = t * queries.len() / threads;
+                let (tail, head) = queries.split_at(offset);
+                for (i, &k) in head.iter().chain(tail).enumerate() {
+                    if (i % 100) < read_percent as usize {
+                        black_box(map.get(k));
+                    } else {
+                        map.insert(k, i);
+                    }
+                }
+            });
+        }
+    });
+    start.elapsed()
+}
+
+/// Benchmarks T with every number of threads and size in config, passing each row
+/// to emit as soon as it's done
+pub fn bench_concurrent<T, R>(
+    name: &str,
+    config: &ConcurrentConfig,
+    rng: &mut R,
+    mut emit: impl FnMut(ConcurrentRow),
+) where
+    T: ConcurrentMap,
+    R: Rng,
+{
+    for n in config.sizes.iter() {
+        let mut first: Option<f64> = None;
+        for &threads in &config.threads {
+            let mut times: Vec<Duration> = (0..config.repeat)
+                .map(|_| {
+                    let workload = Workload::new(config.dist, n, rng);
+                    time_concurrent::<T>(threads, config.read_percent, &workload)
+                })
+                .collect();
+            let summary = Summary::new(&mut times);
+            let ops_per_s = (n * threads) as f64 / summary.median.as_secs_f64();
+            emit(ConcurrentRow {
+                map: name,
+                threads,
+                dist: config.dist,
+                n,
+                repeat: config.repeat,
+                read_percent: config.read_percent,
+                summary,
+                speedup: first.map(|first| ops_per_s / first),
+            });
+            first.get_or_insert(ops_per_s);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+/*
+The Entry API of Map61B, which is the same in every lab. It works like the Entry
+of the standard library's maps, so a key can be inserted or updated with a
+single call:
+
+    *counts.entry(word).or_insert(0) += 1;
+
+By default, Map61B::entry finds the key with contains_key and then again with
+get_mut, and a vacant key is found a third time by insert_vacant when the entry
+is filled in. A map that can find where a key is in one lookup, like a hashmap
+that finds its bucket and its place in it, overrides entry to save the others.
+A tree map can't, since the borrow checker won't let one walk down the tree end
+with either a node's value or the whole map.
+
+Like map61b_subject!, it's a macro, since each lab has its own Map61B trait:
+
+    map61b_common::map61b_entry!(crate::Map61B);
+
+in a lab's entry module defines Entry, OccupiedEntry and VacantEntry for that
+lab's trait.
+*/
+
+/// Defines Entry, OccupiedEntry and VacantEntry for the Map61B trait at `$trait`
+#[macro_export]
+macro_rules! map61b_entry {
+    ($trait: path) => {
+        /// A key's place in a map, returned by Map61B::entry
+        pub enum Entry<'a, M: $trait + ?Sized> {
+            Occupied(OccupiedEntry<'a, M>),
+            Vacant(VacantEntry<'a, M>),
+        }
+
+        /// An entry for a key that is in the map
+        pub struct OccupiedEntry<'a, M: $trait + ?Sized> {
+            pub(crate) key: <M as $trait>::Key,
+            pub(crate) value: &'a mut <M as $trait>::Value,
+        }
+
+        /// An entry for a key that isn't in the map yet
+        pub struct VacantEntry<'a, M: $trait + ?Sized> {
+            pub(crate) key: <M as $trait>::Key,
+            pub(crate) map: &'a mut M,
+        }
+
+        impl<'a, M: $trait + ?Sized> Entry<'a, M> {
+            pub fn key(&self) -> &<M as $trait>::Key {
+                match self {
+                    Entry::Occupied(entry) => entry.key(),
+                    Entry::Vacant(entry) => entry.key(),
+                }
+            }
+
+            /// Inserts default if the key isn't in the map, and returns its value
+            pub fn or_insert(self, default: <M as $trait>::Value) -> &'a mut <M as $trait>::Value {
+                self.or_insert_with(|| default)
+            }
+
+            /// Inserts the result of default if the key isn't in the map, and returns its value
+            pub fn or_insert_with<F: FnOnce() -> <M as $trait>::Value>(
+                self,
+                default: F,
+            ) -> &'a mut <M as $trait>::Value {
+                match self {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => entry.insert(default()),
+                }
+            }
+
+            /// Calls f on the value if the key is in the map
+            pub fn and_modify<F: FnOnce(&mut <M as $trait>::Value)>(self, f: F) -> Self {
+                match self {
+                    Entry::Occupied(entry) => {
+                        f(entry.value);
+                        Entry::Occupied(entry)
+                    }
+                    Entry::Vacant(entry) => Entry::Vacant(entry),
+                }
+            }
+        }
+
+        impl<'a, M: $trait + ?Sized> Entry<'a, M>
+        where
+            <M as $trait>::Value: Default,
+        {
+            /// Inserts the default value if the key isn't in the map, and returns its value
+            pub fn or_default(self) -> &'a mut <M as $trait>::Value {
+                self.or_insert_with(Default::default)
+            }
+        }
+
+        impl<'a, M: $trait + ?Sized> OccupiedEntry<'a, M> {
+            pub fn key(&self) -> &<M as $trait>::Key {
+                &self.key
+            }
+
+            pub fn get(&self) -> &<M as $trait>::Value {
+                self.value
+            }
+
+            pub fn get_mut(&mut self) -> &mut <M as $trait>::Value {
+                self.value
+            }
+
+            /// Returns the value, with the lifetime of the map rather than of the entry
+            pub fn into_mut(self) -> &'a mut <M as $trait>::Value {
+                self.value
+            }
+
+            /// Replaces the value, returning the old one
+            pub fn insert(&mut self, value: <M as $trait>::Value) -> <M as $trait>::Value {
+                std::mem::replace(self.value, value)
+            }
+        }
+
+        impl<'a, M: $trait + ?Sized> VacantEntry<'a, M> {
+            pub fn key(&self) -> &<M as $trait>::Key {
+                &self.key
+            }
+
+            pub fn into_key(self) -> <M as $trait>::Key {
+                self.key
+            }
+
+            /// Inserts the key with value, and returns the value
+            pub fn insert(self, value: <M as $trait>::Value) -> &'a mut <M as $trait>::Value {
+                <M as $trait>::insert_vacant(self.map, self.key, value)
+            }
+        }
+    };
+}
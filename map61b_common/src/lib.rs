@@ -0,0 +1,179 @@
+/*
+The code that every lab's maps share outside of their tests.
+
+Each lab has its own Map61B trait, so the code here is written against the
+MapUnderTest trait instead, and the map61b_subject! macro wraps a lab's Map61B
+types so that they implement it:
+
+    map61b_subject!(crate::Map61B, tree);
+
+defines Subject<M>, which implements MapUnderTest for every Map61B type M. The
+last argument says what the lab's lookups need from a key: `tree` for Ord, `hash`
+for Hash and Eq. The standard library's maps implement MapUnderTest as they are.
+
+The Entry types that Map61B::entry returns are shared through the map61b_entry!
+macro. The bench module has the timing code of the labs' benchmark binaries, and
+allocator has the global allocator they use to count how much memory each map
+takes. The tests that every map has to pass are in map61b_tests, which the labs
+only use as a dev-dependency.
+*/
+
+pub mod allocator;
+pub mod bench;
+mod entry;
+mod std_maps;
+#[cfg(test)]
+mod tests;
+
+/// The methods of Map61B, which the benchmarks and map61b_tests use
+pub trait MapUnderTest<K, V>: Sized {
+    fn new() -> Self;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool;
+    fn clear(&mut self);
+    fn contains_key(&self, key: &K) -> bool;
+    fn insert(&mut self, key: K, value: V) -> Option<V>;
+    fn get(&self, key: &K) -> Option<&V>;
+    fn get_mut(&mut self, key: &K) -> Option<&mut V>;
+    fn remove(&mut self, key: &K) -> Option<V>;
+    /// Consumes the map, returning its entries in the order of into_iter
+    fn into_entries(self) -> Vec<(K, V)>;
+    /// entry(key).or_insert(value)
+    fn or_insert(&mut self, key: K, value: V) -> &mut V;
+    /// entry(key).and_modify(f).or_insert_with(default)
+    fn and_modify(&mut self, key: K, f: impl FnOnce(&mut V), default: impl FnOnce() -> V)
+        -> &mut V;
+    /// iter().collect()
+    fn iter(&self) -> Vec<(&K, &V)>;
+    fn keys(&self) -> Vec<&K>;
+    fn values(&self) -> Vec<&V>;
+    /// Calls f on every entry through iter_mut
+    fn iter_mut(&mut self, f: impl FnMut(&K, &mut V));
+    /// Calls f on every value through values_mut
+    fn values_mut(&mut self, f: impl FnMut(&mut V));
+    fn drain(&mut self) -> Vec<(K, V)>;
+    fn retain(&mut self, f: impl FnMut(&K, &mut V) -> bool);
+}
+
+/// Defines Subject<M>, which implements MapUnderTest for every type M that
+/// implements the Map61B trait at `$trait`, which is a `tree` or a `hash` map
+#[macro_export]
+macro_rules! map61b_subject {
+    ($trait: path, tree) => {
+        $crate::map61b_subject!(@with $trait, Ord);
+    };
+
+    ($trait: path, hash) => {
+        $crate::map61b_subject!(@with $trait, std::hash::Hash + Eq);
+    };
+
+    (@with $trait: path, $($bounds: tt)+) => {
+        /// A Map61B type, wrapped so that it can implement MapUnderTest
+        struct Subject<M>(M);
+
+        impl<M: $trait> $crate::MapUnderTest<<M as $trait>::Key, <M as $trait>::Value>
+            for Subject<M>
+        where
+            <M as $trait>::Key: $($bounds)+,
+        {
+            fn new() -> Self {
+                Subject(<M as $trait>::new())
+            }
+
+            fn len(&self) -> usize {
+                <M as $trait>::len(&self.0)
+            }
+
+            fn is_empty(&self) -> bool {
+                <M as $trait>::is_empty(&self.0)
+            }
+
+            fn clear(&mut self) {
+                <M as $trait>::clear(&mut self.0)
+            }
+
+            fn contains_key(&self, key: &<M as $trait>::Key) -> bool {
+                <M as $trait>::contains_key(&self.0, key)
+            }
+
+            fn insert(
+                &mut self,
+                key: <M as $trait>::Key,
+                value: <M as $trait>::Value,
+            ) -> Option<<M as $trait>::Value> {
+                <M as $trait>::insert(&mut self.0, key, value)
+            }
+
+            fn get(&self, key: &<M as $trait>::Key) -> Option<&<M as $trait>::Value> {
+                <M as $trait>::get(&self.0, key)
+            }
+
+            fn get_mut(&mut self, key: &<M as $trait>::Key) -> Option<&mut <M as $trait>::Value> {
+                <M as $trait>::get_mut(&mut self.0, key)
+            }
+
+            fn remove(&mut self, key: &<M as $trait>::Key) -> Option<<M as $trait>::Value> {
+                <M as $trait>::remove(&mut self.0, key)
+            }
+
+            fn into_entries(self) -> Vec<(<M as $trait>::Key, <M as $trait>::Value)> {
+                self.0.into_iter().collect()
+            }
+
+            fn or_insert(
+                &mut self,
+                key: <M as $trait>::Key,
+                value: <M as $trait>::Value,
+            ) -> &mut <M as $trait>::Value {
+                <M as $trait>::entry(&mut self.0, key).or_insert(value)
+            }
+
+            fn and_modify(
+                &mut self,
+                key: <M as $trait>::Key,
+                f: impl FnOnce(&mut <M as $trait>::Value),
+                default: impl FnOnce() -> <M as $trait>::Value,
+            ) -> &mut <M as $trait>::Value {
+                <M as $trait>::entry(&mut self.0, key)
+                    .and_modify(f)
+                    .or_insert_with(default)
+            }
+
+            fn iter(&self) -> Vec<(&<M as $trait>::Key, &<M as $trait>::Value)> {
+                <M as $trait>::iter(&self.0).collect()
+            }
+
+            fn keys(&self) -> Vec<&<M as $trait>::Key> {
+                <M as $trait>::keys(&self.0).collect()
+            }
+
+            fn values(&self) -> Vec<&<M as $trait>::Value> {
+                <M as $trait>::values(&self.0).collect()
+            }
+
+            fn iter_mut(
+                &mut self,
+                mut f: impl FnMut(&<M as $trait>::Key, &mut <M as $trait>::Value),
+            ) {
+                for (k, v) in <M as $trait>::iter_mut(&mut self.0) {
+                    f(k, v);
+                }
+            }
+
+            fn values_mut(&mut self, f: impl FnMut(&mut <M as $trait>::Value)) {
+                <M as $trait>::values_mut(&mut self.0).for_each(f)
+            }
+
+            fn drain(&mut self) -> Vec<(<M as $trait>::Key, <M as $trait>::Value)> {
+                <M as $trait>::drain(&mut self.0).collect()
+            }
+
+            fn retain(
+                &mut self,
+                f: impl FnMut(&<M as $trait>::Key, &mut <M as $trait>::Value) -> bool,
+            ) {
+                <M as $trait>::retain(&mut self.0, f)
+            }
+        }
+    };
+}
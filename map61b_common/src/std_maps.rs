@@ -0,0 +1,102 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+
+use crate::MapUnderTest;
+
+/*
+MapUnderTest for the standard library's maps, so they can be benchmarked next to
+the labs' maps without a Subject. Each lab's Map61B only fits one of them: a
+tree map's lookups need Ord and a hashmap's need Hash.
+
+Inherent methods are picked over trait methods with the same name, so self.get
+and the rest call the standard library's versions.
+*/
+macro_rules! std_map_impl {
+    ($map: ty, $($bounds: tt)*) => {
+        impl<$($bounds)*> MapUnderTest<K, V> for $map {
+            fn new() -> Self {
+                Self::default()
+            }
+
+            fn len(&self) -> usize {
+                self.len()
+            }
+
+            fn is_empty(&self) -> bool {
+                self.is_empty()
+            }
+
+            fn clear(&mut self) {
+                self.clear()
+            }
+
+            fn contains_key(&self, key: &K) -> bool {
+                self.contains_key(key)
+            }
+
+            fn insert(&mut self, key: K, value: V) -> Option<V> {
+                self.insert(key, value)
+            }
+
+            fn get(&self, key: &K) -> Option<&V> {
+                self.get(key)
+            }
+
+            fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+                self.get_mut(key)
+            }
+
+            fn remove(&mut self, key: &K) -> Option<V> {
+                self.remove(key)
+            }
+
+            fn into_entries(self) -> Vec<(K, V)> {
+                self.into_iter().collect()
+            }
+
+            fn or_insert(&mut self, key: K, value: V) -> &mut V {
+                self.entry(key).or_insert(value)
+            }
+
+            fn and_modify(
+                &mut self,
+                key: K,
+                f: impl FnOnce(&mut V),
+                default: impl FnOnce() -> V,
+            ) -> &mut V {
+                self.entry(key).and_modify(f).or_insert_with(default)
+            }
+
+            fn iter(&self) -> Vec<(&K, &V)> {
+                self.iter().collect()
+            }
+
+            fn keys(&self) -> Vec<&K> {
+                self.keys().collect()
+            }
+
+            fn values(&self) -> Vec<&V> {
+                self.values().collect()
+            }
+
+            fn iter_mut(&mut self, mut f: impl FnMut(&K, &mut V)) {
+                self.iter_mut().for_each(|(k, v)| f(k, v))
+            }
+
+            fn values_mut(&mut self, f: impl FnMut(&mut V)) {
+                self.values_mut().for_each(f)
+            }
+
+            fn drain(&mut self) -> Vec<(K, V)> {
+                std::mem::take(self).into_iter().collect()
+            }
+
+            fn retain(&mut self, f: impl FnMut(&K, &mut V) -> bool) {
+                self.retain(f)
+            }
+        }
+    };
+}
+
+std_map_impl!(HashMap<K, V, S>, K: Hash + Eq, V, S: BuildHasher + Default);
+std_map_impl!(BTreeMap<K, V>, K: Ord, V);
@@ -0,0 +1,248 @@
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+    time::Duration,
+};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    allocator::{AllocStats, CountingAllocator},
+    bench::{
+        bench_concurrent, bench_map, doubling_ratio, time_op, zipf_key, ConcurrentConfig,
+        ConcurrentRow, Config, Dist, Memory, Op, Row, Sizes, Summary, Workload,
+    },
+};
+
+#[test]
+fn parse_sizes() {
+    assert_eq!(
+        "1e3..1e5".parse(),
+        Ok(Sizes {
+            start: 1000,
+            end: 100000
+        })
+    );
+    assert_eq!(
+        "500".parse(),
+        Ok(Sizes {
+            start: 500,
+            end: 500
+        })
+    );
+    assert!("1e5..1e3".parse::<Sizes>().is_err());
+    assert!("0..10".parse::<Sizes>().is_err());
+    assert!("1.5e0".parse::<Sizes>().is_err());
+    assert!("1e3..".parse::<Sizes>().is_err());
+
+    let sizes: Sizes = "1e3..1e4".parse().unwrap();
+    let ns: Vec<usize> = sizes.iter().collect();
+    assert_eq!(ns, [1000, 2000, 4000, 8000]);
+}
+
+#[test]
+fn parse_ops_and_dists() {
+    assert_eq!("mixed".parse(), Ok(Op::Mixed));
+    assert!("delete".parse::<Op>().is_err());
+    assert_eq!("zipf".parse(), Ok(Dist::Zipf));
+    for op in [Op::Insert, Op::Get, Op::Remove, Op::Mixed] {
+        assert_eq!(op.to_string().parse(), Ok(op));
+    }
+}
+
+fn ms(times: &[u64]) -> Vec<Duration> {
+    times.iter().map(|&t| Duration::from_millis(t)).collect()
+}
+
+#[test]
+fn median_and_p95() {
+    let s = Summary::new(&mut ms(&[5, 1, 3]));
+    assert_eq!(s.median, Duration::from_millis(3));
+    assert_eq!(s.p95, Duration::from_millis(5));
+
+    let s = Summary::new(&mut ms(&[4, 1, 3, 2]));
+    assert_eq!(s.median, Duration::from_micros(2500));
+    assert_eq!(s.p95, Duration::from_millis(4));
+
+    // with 20 times, the 95th percentile is the 19th smallest
+    let s = Summary::new(&mut ms(&(1..=20).rev().collect::<Vec<_>>()));
+    assert_eq!(s.p95, Duration::from_millis(19));
+
+    let s = Summary::new(&mut ms(&[7]));
+    assert_eq!(
+        (s.median, s.p95),
+        (Duration::from_millis(7), Duration::from_millis(7))
+    );
+}
+
+#[test]
+fn doubling_ratio_exponent() {
+    let (ratio, exponent) = doubling_ratio(1000, ms(&[10])[0], 2000, ms(&[40])[0]);
+    assert!((ratio - 4.0).abs() < 1e-9);
+    assert!((exponent - 2.0).abs() < 1e-9);
+    let (ratio, exponent) = doubling_ratio(1000, ms(&[10])[0], 4000, ms(&[40])[0]);
+    assert!((ratio - 4.0).abs() < 1e-9);
+    assert!((exponent - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn zipf_keys_favor_small_keys() {
+    let mut rng = StdRng::seed_from_u64(61);
+    let n = 1000;
+    let keys: Vec<usize> = (0..100_000).map(|_| zipf_key(n, &mut rng)).collect();
+    assert!(keys.iter().all(|&k| k < n));
+    let count = |k| keys.iter().filter(|&&x| x == k).count();
+    // key 0 should come up about ln(2) / ln(1001), or 10%, of the time
+    assert!((8000..12000).contains(&count(0)));
+    assert!(count(0) > count(1) && count(1) > count(10));
+    assert_eq!(zipf_key(1, &mut rng), 0);
+}
+
+#[test]
+fn workloads() {
+    let mut rng = StdRng::seed_from_u64(61);
+    let w = Workload::new(Dist::Ordered, 5, &mut rng);
+    assert_eq!(w.fill, [0, 1, 2, 3, 4]);
+    assert_eq!(w.queries, [0, 1, 2, 3, 4]);
+
+    let w = Workload::new(Dist::Random, 100, &mut rng);
+    let mut queries = w.queries.clone();
+    let mut fill = w.fill.clone();
+    queries.sort();
+    fill.sort();
+    assert_eq!(queries, fill);
+
+    let w = Workload::new(Dist::Zipf, 100, &mut rng);
+    assert_eq!(w.fill, (0..100).collect::<Vec<_>>());
+    assert_eq!(w.queries.len(), 100);
+}
+
+#[test]
+fn ops_leave_the_expected_map() {
+    let w = Workload::new(Dist::Ordered, 100, &mut StdRng::seed_from_u64(61));
+    let (_, m) = time_op::<BTreeMap<usize, usize>>(Op::Insert, &w);
+    assert_eq!(m.len(), 100);
+    let (_, m) = time_op::<BTreeMap<usize, usize>>(Op::Get, &w);
+    assert_eq!(m.len(), 100);
+    let (_, m) = time_op::<BTreeMap<usize, usize>>(Op::Remove, &w);
+    assert!(m.is_empty());
+    // every fourth key is reinserted and the one after it is removed
+    let (_, m) = time_op::<BTreeMap<usize, usize>>(Op::Mixed, &w);
+    assert_eq!(m.len(), 75);
+    assert_eq!(m.get(&4), Some(&4));
+    assert!(!m.contains_key(&5));
+}
+
+/// Returns the value of the column called name in a CSV line
+fn column<'a>(line: &'a str, name: &str) -> &'a str {
+    let i = Row::HEADER.split(',').position(|c| c == name).unwrap();
+    line.split(',').nth(i).unwrap()
+}
+
+#[test]
+fn bench_map_rows() {
+    let config = Config {
+        ops: vec![Op::Insert, Op::Get],
+        dist: Dist::Random,
+        sizes: "100..400".parse().unwrap(),
+        repeat: 3,
+    };
+    let mut lines = Vec::new();
+    let mut inspected = Vec::new();
+    bench_map::<BTreeMap<usize, usize>, _>(
+        "BTreeMap",
+        &config,
+        &mut StdRng::seed_from_u64(61),
+        |row| lines.push(row.to_string()),
+        |n, map| inspected.push((n, map.len())),
+        |map| Some(map.len() as f64 / 1000.0),
+    );
+    assert_eq!(inspected, [(100, 100), (200, 200), (400, 400)]);
+    assert_eq!(lines.len(), 6);
+    let columns = Row::HEADER.split(',').count();
+    for line in &lines {
+        assert_eq!(line.split(',').count(), columns);
+    }
+    assert!(lines[0].starts_with("BTreeMap,insert,random,100,3,"));
+    // the first size has nothing to compare with
+    assert_eq!(column(&lines[0], "ratio"), "");
+    assert_ne!(column(&lines[1], "ratio"), "");
+    assert_eq!(column(&lines[2], "load_factor"), "0.400");
+    assert!(lines[3].starts_with("BTreeMap,get,random,100,3,"));
+    assert_eq!(column(&lines[3], "exponent"), "");
+    assert_eq!(column(&lines[3], "load_factor"), "");
+}
+
+#[test]
+fn memory_columns() {
+    let row = Row {
+        map: "CHashMap",
+        op: Op::Insert,
+        dist: Dist::Ordered,
+        n: 1000,
+        repeat: 1,
+        summary: Summary::new(&mut ms(&[2])),
+        growth: Some((2.0, 1.0)),
+        memory: Some(Memory {
+            bytes: 48000,
+            allocations: 12,
+            entries: 960,
+        }),
+        load_factor: Some(0.75),
+    };
+    let line = row.to_string();
+    assert_eq!(column(&line, "median_ns_per_op"), "2000.000");
+    assert_eq!(column(&line, "heap_bytes"), "48000");
+    assert_eq!(column(&line, "bytes_per_entry"), "50.0");
+    assert_eq!(column(&line, "allocations"), "12");
+    assert_eq!(column(&line, "load_factor"), "0.750");
+}
+
+// calls the allocator directly, since the tests don't install it
+#[test]
+fn counting_allocator() {
+    let layout = Layout::from_size_align(100, 8).unwrap();
+    unsafe {
+        let ptr = CountingAllocator.alloc(layout);
+        let before = AllocStats::now().unwrap();
+        let ptr = CountingAllocator.realloc(ptr, layout, 300);
+        let after = AllocStats::now().unwrap();
+        assert_eq!(after.since(&before).allocations, 1);
+        assert!(after.since(&before).live_bytes >= 200);
+        CountingAllocator.dealloc(ptr, Layout::from_size_align(300, 8).unwrap());
+    }
+}
+
+#[test]
+fn concurrent_rows() {
+    let config = ConcurrentConfig {
+        threads: vec![1, 4],
+        dist: Dist::Zipf,
+        sizes: "1e3..2e3".parse().unwrap(),
+        repeat: 2,
+        read_percent: 50,
+    };
+    let mut lines = Vec::new();
+    bench_concurrent::<Mutex<HashMap<usize, usize>>, _>(
+        "Mutex<HashMap>",
+        &config,
+        &mut StdRng::seed_from_u64(61),
+        |row| {
+            assert_eq!(row.ops(), row.n * row.threads);
+            lines.push(row.to_string());
+        },
+    );
+    assert_eq!(lines.len(), 4);
+    let columns = ConcurrentRow::HEADER.split(',').count();
+    for line in &lines {
+        assert_eq!(line.split(',').count(), columns);
+    }
+    assert!(lines[0].starts_with("Mutex<HashMap>,1,zipf,1000,2,50,"));
+    assert!(lines[1].starts_with("Mutex<HashMap>,4,zipf,1000,2,50,"));
+    assert!(lines[2].starts_with("Mutex<HashMap>,1,zipf,2000,2,50,"));
+    // the speedup is compared with one thread at the same size
+    assert!(lines[0].ends_with(','));
+    assert!(!lines[1].ends_with(','));
+    assert!(lines[2].ends_with(','));
+}
@@ -0,0 +1,358 @@
+use map61b_common::MapUnderTest;
+
+/*
+Fixed tests of each Map61B method, which used to be copied into every lab's
+tests/map.rs and tests/extra.rs. Each test takes the map types to test as
+type parameters.
+*/
+
+/// A value type with no traits, to check that maps don't require any
+pub struct NoTraits {
+    pub t: i32,
+}
+
+/// A key type with only the traits that a tree map needs
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+pub struct JustOrd {
+    pub t: usize,
+}
+
+/// A key type with only the traits that a hashmap needs
+#[derive(PartialEq, Eq, Hash)]
+pub struct JustHash {
+    pub t: usize,
+}
+
+macro_rules! strings {
+    [$($s: expr),*] => {
+        vec![$(($s).to_string()),*]
+    };
+}
+
+// Assumes insert, get, get_mut, len, and containsKey work
+pub fn sanity_clear<M: MapUnderTest<String, usize>>() {
+    const LEN: usize = 455;
+    let mut b = M::new();
+    for i in 0..LEN {
+        let s = format!("hi{}", i);
+        b.insert(s.clone(), i + 1);
+        assert!(b.get(&s) == Some(&(i + 1)));
+        assert!(b.get_mut(&s) == Some(&mut (i + 1)));
+        assert!(b.contains_key(&s));
+    }
+    assert_eq!(LEN, b.len());
+    b.clear();
+    assert_eq!(0, b.len());
+    assert!(b.is_empty());
+    for i in 0..LEN {
+        let s = format!("hi{}", i);
+        assert!(b.get(&s).is_none());
+        assert!(b.get_mut(&s).is_none());
+        assert!(!b.contains_key(&s));
+    }
+}
+
+// Assumes insert works
+pub fn sanity_contains_key<M: MapUnderTest<String, i32>>() {
+    let s = "water_you_doing_here".to_string();
+    let mut b = M::new();
+    assert!(!b.contains_key(&s));
+    b.insert(s.clone(), 123);
+    assert!(b.contains_key(&s));
+}
+
+// Assumes len works
+pub fn sanity_put_get_and_contains<M: MapUnderTest<String, NoTraits>>() {
+    let s1 = "starChild".to_string();
+    let v1 = NoTraits { t: 12345 };
+    let s2 = "KISS".to_string();
+    let v2 = NoTraits { t: 56789 };
+    let mut b = M::new();
+
+    assert!(!b.contains_key(&s1));
+    assert!(b.get(&s1).is_none());
+    assert!(b.get_mut(&s1).is_none());
+    assert!(b.insert(s1.clone(), v1).is_none());
+    assert!(b.contains_key(&s1));
+    assert_eq!(b.get(&s1).unwrap().t, 12345);
+    b.get_mut(&s1).unwrap().t = 111;
+    assert_eq!(b.get(&s1).unwrap().t, 111);
+
+    assert!(b.insert(s2.clone(), v2).is_none());
+    assert!(b.contains_key(&s1));
+    assert!(b.contains_key(&s2));
+    assert_eq!(b.get(&s1).unwrap().t, 111);
+    assert_eq!(b.len(), 2);
+}
+
+// Assumes insert works
+pub fn sanity_len_test<M: MapUnderTest<String, i32>>() {
+    let mut b = M::new();
+    assert_eq!(0, b.len());
+    assert!(b.is_empty());
+    b.insert("hi".to_string(), 0);
+    assert_eq!(1, b.len());
+    assert!(!b.is_empty());
+    for i in 0..455 {
+        b.insert(format!("hi{}", i), 0);
+        assert_eq!(i + 2, b.len());
+    }
+}
+
+pub fn contains_none_test<M: MapUnderTest<String, Option<i32>>>() {
+    let s = "hi".to_string();
+    let mut b = M::new();
+    b.insert(s.clone(), None);
+    assert!(b.contains_key(&s));
+}
+
+pub fn tree_test<M: MapUnderTest<String, String>>() {
+    let (k1, mut v1) = ("d".to_string(), "parmesan".to_string());
+    let (k2, mut v2) = ("a".to_string(), "mozzarella".to_string());
+    let (k3, mut v3) = ("c".to_string(), "swiss".to_string());
+    let (k4, mut v4) = ("b".to_string(), "pepper jack".to_string());
+    let (k5, mut v5) = ("e".to_string(), "gouda".to_string());
+    let mut new_v4 = "provolone".to_string();
+
+    let mut b = M::new();
+    assert!(b.insert(k1.clone(), v1.clone()).is_none());
+    assert!(b.insert(k2.clone(), v2.clone()).is_none());
+    assert!(b.insert(k3.clone(), v3.clone()).is_none());
+    assert!(b.insert(k4.clone(), v4.clone()).is_none());
+    assert!(b.insert(k5.clone(), v5.clone()).is_none());
+    assert_eq!(b.len(), 5);
+    assert_eq!(b.get(&k1), Some(&v1));
+    assert_eq!(b.get(&k2), Some(&v2));
+    assert_eq!(b.get(&k3), Some(&v3));
+    assert_eq!(b.get(&k4), Some(&v4));
+    assert_eq!(b.get(&k5), Some(&v5));
+    assert_eq!(b.get_mut(&k1), Some(&mut v1));
+    assert_eq!(b.get_mut(&k2), Some(&mut v2));
+    assert_eq!(b.get_mut(&k3), Some(&mut v3));
+    assert_eq!(b.get_mut(&k4), Some(&mut v4));
+    assert_eq!(b.get_mut(&k5), Some(&mut v5));
+    assert_eq!(b.insert(k4.clone(), new_v4.clone()), Some(v4.clone()));
+    assert_eq!(b.len(), 5);
+    assert_eq!(b.get(&k4), Some(&new_v4));
+    assert_eq!(b.get_mut(&k4), Some(&mut new_v4));
+}
+
+pub fn functionality_test<D, S>()
+where
+    D: MapUnderTest<&'static str, &'static str>,
+    S: MapUnderTest<&'static str, usize>,
+{
+    let mut dictionary = D::new();
+    let mut student_ids = S::new();
+
+    assert_eq!(dictionary.len(), 0);
+
+    // can put objects in ductionary and get them
+    assert!(dictionary.insert("hello", "world").is_none());
+    assert!(dictionary.contains_key(&"hello"));
+    assert_eq!(dictionary.get(&"hello"), Some(&"world"));
+    assert_eq!(dictionary.get_mut(&"hello"), Some(&mut "world"));
+    assert_eq!(dictionary.len(), 1);
+
+    // putting with existing key updates the value
+    assert_eq!(dictionary.insert("hello", "kevin"), Some("world"));
+    assert_eq!(dictionary.len(), 1);
+    assert!(dictionary.contains_key(&"hello"));
+    assert_eq!(dictionary.get(&"hello"), Some(&"kevin"));
+    assert_eq!(dictionary.get_mut(&"hello"), Some(&mut "kevin"));
+
+    // putting key multiple times does not affect behavior
+    assert!(student_ids.insert("sarah", 12345).is_none());
+    assert_eq!(student_ids.len(), 1);
+    assert_eq!(student_ids.get(&"sarah"), Some(&12345));
+    assert_eq!(student_ids.get_mut(&"sarah"), Some(&mut 12345));
+    assert!(student_ids.insert("alan", 345).is_none());
+    for _ in 0..3 {
+        assert_eq!(student_ids.len(), 2);
+        assert_eq!(student_ids.get(&"sarah"), Some(&12345));
+        assert_eq!(student_ids.get_mut(&"sarah"), Some(&mut 12345));
+        assert_eq!(student_ids.get(&"alan"), Some(&345));
+        assert_eq!(student_ids.get_mut(&"alan"), Some(&mut 345));
+        assert_eq!(student_ids.insert("alan", 345), Some(345));
+    }
+
+    // handle values being the same
+    assert_eq!(student_ids.get(&"alan"), Some(&345));
+    assert_eq!(student_ids.get_mut(&"alan"), Some(&mut 345));
+    assert!(student_ids.insert("evil alan", 345).is_none());
+    assert_eq!(student_ids.get(&"alan"), Some(&345));
+    assert_eq!(student_ids.get_mut(&"alan"), Some(&mut 345));
+    assert_eq!(student_ids.get(&"evil alan"), Some(&345));
+    assert_eq!(student_ids.get_mut(&"evil alan"), Some(&mut 345));
+}
+
+pub fn entry_test<M: MapUnderTest<String, usize>>() {
+    let mut counts = M::new();
+    for word in "the cat and the hat and the bat".split(' ') {
+        *counts.or_insert(word.to_string(), 0) += 1;
+    }
+    assert_eq!(counts.len(), 5);
+    assert_eq!(counts.get(&"the".to_string()), Some(&3));
+    assert_eq!(counts.get(&"and".to_string()), Some(&2));
+    assert_eq!(counts.get(&"cat".to_string()), Some(&1));
+
+    // or_insert leaves a value that is already there alone
+    assert_eq!(*counts.or_insert("cat".to_string(), 100), 1);
+    assert_eq!(*counts.or_insert("dog".to_string(), 100), 100);
+    assert_eq!(counts.len(), 6);
+
+    // and_modify only runs on keys that are there, and or_insert_with only on new keys
+    let hat = counts.and_modify("hat".to_string(), |v| *v *= 10, || unreachable!());
+    assert_eq!(*hat, 10);
+    let eel = counts.and_modify("eel".to_string(), |_| unreachable!(), || 7);
+    *eel += 1;
+    assert_eq!(counts.get(&"eel".to_string()), Some(&8));
+    assert_eq!(counts.len(), 7);
+
+    // many new keys, so that the map has to grow or rebalance
+    for i in 0..455 {
+        assert_eq!(*counts.or_insert(format!("hi{}", i), i), i);
+    }
+    for i in 0..455 {
+        assert_eq!(counts.get(&format!("hi{}", i)), Some(&i));
+    }
+    assert_eq!(counts.len(), 462);
+}
+
+pub fn borrowing_iter_test<M: MapUnderTest<String, usize>>() {
+    let mut b = M::new();
+    assert!(b.iter().is_empty());
+    for i in 0..100 {
+        b.insert(format!("hi{}", i), i);
+    }
+
+    let entries = b.iter();
+    assert_eq!(entries.len(), 100);
+    // keys and values come in the same order as iter
+    let keys: Vec<_> = entries.iter().map(|&(k, _)| k).collect();
+    let values: Vec<_> = entries.iter().map(|&(_, v)| v).collect();
+    assert_eq!(b.keys(), keys);
+    assert_eq!(b.values(), values);
+    let mut entries: Vec<_> = entries.into_iter().map(|(k, &v)| (k.clone(), v)).collect();
+    entries.sort_by_key(|&(_, v)| v);
+    let expected: Vec<_> = (0..100).map(|i| (format!("hi{}", i), i)).collect();
+    assert_eq!(entries, expected);
+
+    b.iter_mut(|k, v| *v += k.len());
+    assert_eq!(b.get(&"hi5".to_string()), Some(&8));
+    assert_eq!(b.get(&"hi50".to_string()), Some(&54));
+    b.values_mut(|v| *v *= 2);
+    assert_eq!(b.get(&"hi5".to_string()), Some(&16));
+
+    // none of it used up the map
+    assert_eq!(b.len(), 100);
+    assert_eq!(b.values().into_iter().sum::<usize>(), 2 * (4950 + 390));
+}
+
+pub fn drain_retain_test<M: MapUnderTest<usize, usize>>() {
+    let mut b = M::new();
+    for i in 0..100 {
+        b.insert(i, i);
+    }
+    b.retain(|k, v| {
+        *v += 1;
+        k.is_multiple_of(3)
+    });
+    assert_eq!(b.len(), 34);
+    assert_eq!(b.get(&3), Some(&4));
+    assert!(!b.contains_key(&4));
+
+    let mut drained = b.drain();
+    drained.sort();
+    let expected: Vec<_> = (0..100).step_by(3).map(|k| (k, k + 1)).collect();
+    assert_eq!(drained, expected);
+    assert!(b.is_empty());
+    assert_eq!(b.get(&3), None);
+
+    // the map still works after being drained
+    b.insert(1, 1);
+    assert_eq!(b.len(), 1);
+    assert_eq!(b.drain(), [(1, 1)]);
+    b.retain(|_, _| unreachable!());
+    assert!(b.is_empty());
+}
+
+pub fn iter_test<M: MapUnderTest<String, String>>() {
+    let mut b = M::new();
+    let keys = strings!["a", "b", "c", "d", "e"];
+    let values = strings!["e", "f", "g", "h", "i"];
+
+    for (k, v) in keys.iter().zip(values.iter()) {
+        b.insert(k.clone(), v.clone());
+    }
+
+    let mut bvec = b.into_entries();
+    bvec.sort();
+    assert_eq!(bvec.len(), keys.len());
+    for i in 0..keys.len() {
+        assert_eq!(bvec[i], (keys[i].clone(), values[i].clone()));
+    }
+}
+
+pub fn test_remove_root<M: MapUnderTest<String, String>>() {
+    let mut b = M::new();
+    let keys = strings!["c", "b", "a", "d", "e"];
+    for k in &keys {
+        b.insert(k.clone(), k.clone());
+    }
+    assert_eq!(b.remove(&keys[0]), Some(keys[0].clone()));
+    assert_eq!(b.get(&keys[0]), None);
+    for k in keys.iter().skip(1) {
+        assert_eq!(b.get(k), Some(k));
+    }
+}
+
+pub fn test_remove_three_cases<M: MapUnderTest<String, String>>() {
+    let mut b = M::new();
+    let keys = strings!["c", "b", "a", "d", "e"];
+    for k in &keys {
+        b.insert(k.clone(), k.clone());
+    }
+    assert_eq!(b.remove(&keys[4]), Some(keys[4].clone())); // a b c d
+    assert_eq!(b.get(&keys[0]), Some(&keys[0]));
+    assert_eq!(b.get(&keys[1]), Some(&keys[1]));
+    assert_eq!(b.get(&keys[2]), Some(&keys[2]));
+    assert_eq!(b.get(&keys[3]), Some(&keys[3]));
+    assert_eq!(b.remove(&keys[0]), Some(keys[0].clone())); // a b d
+    assert_eq!(b.get(&keys[1]), Some(&keys[1]));
+    assert_eq!(b.get(&keys[2]), Some(&keys[2]));
+    assert_eq!(b.get(&keys[3]), Some(&keys[3]));
+    assert_eq!(b.insert("f".to_string(), "f".to_string()), None); // a b d f
+    assert_eq!(b.remove(&keys[3]), Some(keys[3].clone())); // a b f
+    assert_eq!(b.get(&keys[1]), Some(&keys[1]));
+    assert_eq!(b.get(&keys[2]), Some(&keys[2]));
+    assert_eq!(b.get(&"f".to_string()), Some(&"f".to_string()));
+}
+
+pub fn test_remove_root_edge<M: MapUnderTest<char, char>>() {
+    let mut right = M::new();
+    right.insert('A', 'A');
+    right.insert('B', 'B');
+    assert_eq!(right.remove(&'A'), Some('A'));
+    for c in 'C'..'M' {
+        right.insert(c, c);
+    }
+    assert_eq!(right.insert('A', 'a'), None);
+    assert_eq!(right.remove(&'D'), Some('D'));
+    assert_eq!(right.remove(&'G'), Some('G'));
+    assert_eq!(right.remove(&'A'), Some('a'));
+    assert_eq!(right.len(), 9);
+
+    let mut left = M::new();
+    left.insert('B', 'B');
+    left.insert('A', 'A');
+    assert_eq!(left.remove(&'B'), Some('B'));
+    assert_eq!(left.len(), 1);
+    assert_eq!(left.get(&'B'), None);
+
+    let mut no_child = M::new();
+    no_child.insert('Z', '$');
+    assert_eq!(no_child.remove(&'Z'), Some('$'));
+    assert_eq!(no_child.len(), 0);
+    assert_eq!(no_child.get(&'Z'), None);
+}
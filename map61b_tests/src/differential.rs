@@ -0,0 +1,418 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+use map61b_common::MapUnderTest;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/*
+Randomized differential testing: random sequences of operations are applied
+both to the map under test and to a std BTreeMap, and every result is
+compared. When a sequence fails, it is shrunk to a short sequence that still
+fails, which is much easier to debug by hand.
+
+Every run prints nothing unless it fails. The failure message includes the
+seed, and setting the MAP61B_SEED environment variable to it replays the
+same sequences.
+*/
+
+/// Number of random sequences that check tries
+pub const CASES: usize = 200;
+/// Longest random sequence that check tries
+pub const MAX_OPS: usize = 400;
+
+/// One call to a method of the map
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Op {
+    Insert(u16, u32),
+    Get(u16),
+    /// get_mut the key, and if it's there, set its value to the second field
+    GetMut(u16, u32),
+    ContainsKey(u16),
+    Remove(u16),
+    Clear,
+    Len,
+    /// into_iter the map, and then insert its entries into a new one
+    IntoIter,
+    /// entry(key).or_insert(value)
+    OrInsert(u16, u32),
+    /// entry(key).and_modify(|v| *v ^= value).or_insert(value)
+    AndModify(u16, u32),
+    /// iter, keys and values, which must agree with each other
+    Iter,
+    /// iter_mut the map, xoring every value with the field
+    IterMut(u32),
+    /// values_mut the map, adding the field to every value
+    ValuesMut(u32),
+    Drain,
+    /// retain(|_, v| { *v ^= value; *v % 4 != 0 })
+    Retain(u32),
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Op::Insert(k, v) => write!(f, "insert({}, {})", k, v),
+            Op::Get(k) => write!(f, "get(&{})", k),
+            Op::GetMut(k, v) => write!(f, "get_mut(&{}) <- {}", k, v),
+            Op::ContainsKey(k) => write!(f, "contains_key(&{})", k),
+            Op::Remove(k) => write!(f, "remove(&{})", k),
+            Op::Clear => write!(f, "clear()"),
+            Op::Len => write!(f, "len()"),
+            Op::IntoIter => write!(f, "into_iter()"),
+            Op::OrInsert(k, v) => write!(f, "entry({}).or_insert({})", k, v),
+            Op::AndModify(k, v) => write!(f, "entry({}).and_modify(^ {}).or_insert({})", k, v, v),
+            Op::Iter => write!(f, "iter()"),
+            Op::IterMut(v) => write!(f, "iter_mut() ^ {}", v),
+            Op::ValuesMut(v) => write!(f, "values_mut() + {}", v),
+            Op::Drain => write!(f, "drain()"),
+            Op::Retain(v) => write!(f, "retain(^ {}, % 4 != 0)", v),
+        }
+    }
+}
+
+/// Which methods the random sequences use
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Methods {
+    /// everything except remove and into_iter, which are optional in the labs,
+    /// and drain and retain, which use into_iter by default
+    Required,
+    All,
+}
+
+/// Where and how a sequence of ops failed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Failure {
+    /// index of the op that failed
+    pub step: usize,
+    pub message: String,
+}
+
+/// Returns a random sequence of ops on keys in 0..keys
+pub fn random_ops(rng: &mut impl Rng, len: usize, keys: u16, methods: Methods) -> Vec<Op> {
+    let all = methods == Methods::All;
+    (0..len)
+        .map(|_| {
+            let key = rng.gen_range(0..keys);
+            // inserts are the most common, so that the map grows
+            match rng.gen_range(0..100) {
+                0..=14 => Op::Get(key),
+                15..=24 => Op::GetMut(key, rng.gen()),
+                25..=34 => Op::ContainsKey(key),
+                35..=39 => Op::Len,
+                40 => Op::Clear,
+                41..=45 => Op::OrInsert(key, rng.gen()),
+                46..=50 => Op::AndModify(key, rng.gen()),
+                51..=65 if all => Op::Remove(key),
+                66 if all => Op::IntoIter,
+                67 if all => Op::Drain,
+                68..=69 if all => Op::Retain(rng.gen()),
+                70 => Op::Iter,
+                71 => Op::IterMut(rng.gen()),
+                72 => Op::ValuesMut(rng.gen()),
+                _ => Op::Insert(key, rng.gen()),
+            }
+        })
+        .collect()
+}
+
+/// Applies ops to a new M and to a BTreeMap, returning the first difference
+/// between them. A panic in M counts as a difference.
+pub fn run<M: MapUnderTest<u16, u32>>(ops: &[Op]) -> Result<(), Failure> {
+    let mut step = 0;
+    let result = panic::catch_unwind(AssertUnwindSafe(|| apply::<M>(ops, &mut step)));
+    let message = match result {
+        Ok(Ok(())) => return Ok(()),
+        Ok(Err(message)) => message,
+        Err(payload) => {
+            let reason = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            format!("panicked: {}", reason)
+        }
+    };
+    Err(Failure { step, message })
+}
+
+fn apply<M: MapUnderTest<u16, u32>>(ops: &[Op], step: &mut usize) -> Result<(), String> {
+    let mut map = M::new();
+    let mut model = BTreeMap::new();
+    for (i, op) in ops.iter().enumerate() {
+        *step = i;
+        let (got, expected) = match *op {
+            Op::Insert(k, v) => (fmt_option(map.insert(k, v)), fmt_option(model.insert(k, v))),
+            Op::Get(k) => (fmt_option(map.get(&k)), fmt_option(model.get(&k))),
+            Op::GetMut(k, v) => {
+                let set = |old: &mut u32| std::mem::replace(old, v);
+                (
+                    fmt_option(map.get_mut(&k).map(set)),
+                    fmt_option(model.get_mut(&k).map(set)),
+                )
+            }
+            Op::ContainsKey(k) => (
+                map.contains_key(&k).to_string(),
+                model.contains_key(&k).to_string(),
+            ),
+            Op::Remove(k) => (fmt_option(map.remove(&k)), fmt_option(model.remove(&k))),
+            Op::Clear => {
+                map.clear();
+                model.clear();
+                ("()".to_string(), "()".to_string())
+            }
+            Op::Len => (
+                format!("{} (is_empty: {})", map.len(), map.is_empty()),
+                format!("{} (is_empty: {})", model.len(), model.is_empty()),
+            ),
+            Op::IntoIter => {
+                let mut entries = std::mem::replace(&mut map, M::new()).into_entries();
+                entries.sort_unstable();
+                for &(k, v) in &entries {
+                    map.insert(k, v);
+                }
+                let expected: Vec<_> = model.iter().map(|(&k, &v)| (k, v)).collect();
+                (format!("{:?}", entries), format!("{:?}", expected))
+            }
+            Op::OrInsert(k, v) => (
+                map.or_insert(k, v).to_string(),
+                model.entry(k).or_insert(v).to_string(),
+            ),
+            Op::AndModify(k, v) => (
+                map.and_modify(k, |x| *x ^= v, || v).to_string(),
+                model
+                    .entry(k)
+                    .and_modify(|x| *x ^= v)
+                    .or_insert(v)
+                    .to_string(),
+            ),
+            Op::Iter => {
+                let entries = map.iter();
+                let keys: Vec<_> = entries.iter().map(|&(k, _)| k).collect();
+                let values: Vec<_> = entries.iter().map(|&(_, v)| v).collect();
+                if map.keys() != keys || map.values() != values {
+                    return Err(format!(
+                        "keys() and values() don't match iter() {:?}",
+                        entries
+                    ));
+                }
+                (sorted(entries), sorted(model.iter()))
+            }
+            Op::IterMut(x) => {
+                map.iter_mut(|_, v| *v ^= x);
+                model.iter_mut().for_each(|(_, v)| *v ^= x);
+                (sorted(map.iter()), sorted(model.iter()))
+            }
+            Op::ValuesMut(x) => {
+                map.values_mut(|v| *v = v.wrapping_add(x));
+                model.values_mut().for_each(|v| *v = v.wrapping_add(x));
+                (sorted(map.iter()), sorted(model.iter()))
+            }
+            Op::Drain => {
+                let entries = map.drain();
+                let expected = std::mem::take(&mut model);
+                (
+                    sorted(entries.iter().map(|(k, v)| (k, v))),
+                    sorted(expected.iter()),
+                )
+            }
+            Op::Retain(x) => {
+                let keep = |_: &u16, v: &mut u32| {
+                    *v ^= x;
+                    !v.is_multiple_of(4)
+                };
+                map.retain(keep);
+                model.retain(keep);
+                (sorted(map.iter()), sorted(model.iter()))
+            }
+        };
+        if got != expected {
+            return Err(format!("{} returned {}, expected {}", op, got, expected));
+        }
+        // catch a wrong len as soon as it happens, not at the next Op::Len
+        if map.len() != model.len() {
+            return Err(format!(
+                "len() is {} after {}, expected {}",
+                map.len(),
+                op,
+                model.len()
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn fmt_option<T: fmt::Debug>(value: Option<T>) -> String {
+    format!("{:?}", value)
+}
+
+/// Formats entries in increasing order of key, since maps can iterate in any order
+fn sorted<'a>(entries: impl IntoIterator<Item = (&'a u16, &'a u32)>) -> String {
+    let mut entries: Vec<_> = entries.into_iter().collect();
+    entries.sort_unstable();
+    format!("{:?}", entries)
+}
+
+impl Op {
+    fn key(&self) -> Option<u16> {
+        match *self {
+            Op::Insert(k, _)
+            | Op::Get(k)
+            | Op::GetMut(k, _)
+            | Op::ContainsKey(k)
+            | Op::Remove(k)
+            | Op::OrInsert(k, _)
+            | Op::AndModify(k, _) => Some(k),
+            Op::Clear
+            | Op::Len
+            | Op::IntoIter
+            | Op::Iter
+            | Op::IterMut(_)
+            | Op::ValuesMut(_)
+            | Op::Drain
+            | Op::Retain(_) => None,
+        }
+    }
+
+    /// Returns this op with key `from` replaced by `to`
+    fn rename(&self, from: u16, to: u16) -> Op {
+        let swap = |k: u16| if k == from { to } else { k };
+        match *self {
+            Op::Insert(k, v) => Op::Insert(swap(k), v),
+            Op::Get(k) => Op::Get(swap(k)),
+            Op::GetMut(k, v) => Op::GetMut(swap(k), v),
+            Op::ContainsKey(k) => Op::ContainsKey(swap(k)),
+            Op::Remove(k) => Op::Remove(swap(k)),
+            Op::OrInsert(k, v) => Op::OrInsert(swap(k), v),
+            Op::AndModify(k, v) => Op::AndModify(swap(k), v),
+            Op::Clear
+            | Op::Len
+            | Op::IntoIter
+            | Op::Iter
+            | Op::IterMut(_)
+            | Op::ValuesMut(_)
+            | Op::Drain
+            | Op::Retain(_) => self.clone(),
+        }
+    }
+}
+
+/// Returns keys smaller than key to try in its place
+fn smaller_keys(key: u16) -> Vec<u16> {
+    let mut keys = vec![0, key / 2, key.saturating_sub(1)];
+    keys.retain(|&k| k < key);
+    keys.dedup();
+    keys
+}
+
+/// Returns op made simpler in each possible way
+fn simplifications(op: &Op) -> Vec<Op> {
+    let mut simpler = match op.key() {
+        Some(key) => smaller_keys(key)
+            .into_iter()
+            .map(|smaller| op.rename(key, smaller))
+            .collect(),
+        None => Vec::new(),
+    };
+    match *op {
+        Op::Insert(k, v) if v > 0 => simpler.push(Op::Insert(k, 0)),
+        Op::GetMut(k, v) if v > 0 => simpler.push(Op::GetMut(k, 0)),
+        Op::OrInsert(k, v) if v > 0 => simpler.push(Op::OrInsert(k, 0)),
+        Op::AndModify(k, v) if v > 0 => simpler.push(Op::AndModify(k, 0)),
+        Op::IterMut(v) if v > 0 => simpler.push(Op::IterMut(0)),
+        Op::ValuesMut(v) if v > 0 => simpler.push(Op::ValuesMut(0)),
+        Op::Retain(v) if v > 0 => simpler.push(Op::Retain(0)),
+        _ => {}
+    }
+    simpler
+}
+
+/// Returns a shorter or simpler sequence of ops that still fails, by removing
+/// chunks of ops and then making the keys and values smaller.
+/// The panic hook is left alone, since other tests may be running on other
+/// threads, so each of M's panics while shrinking is printed. The test harness
+/// only shows them if the test fails.
+pub fn shrink<M: MapUnderTest<u16, u32>>(mut ops: Vec<Op>) -> Vec<Op> {
+    let Err(failure) = run::<M>(&ops) else {
+        return ops;
+    };
+    ops.truncate(failure.step + 1);
+
+    let mut chunk = ops.len() / 2;
+    while chunk > 0 {
+        let mut removed = false;
+        let mut start = 0;
+        while start + chunk <= ops.len() {
+            let candidate = [&ops[..start], &ops[start + chunk..]].concat();
+            if run::<M>(&candidate).is_err() {
+                ops = candidate;
+                removed = true;
+            } else {
+                start += chunk;
+            }
+        }
+        if !removed {
+            chunk /= 2;
+        }
+    }
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        // a key usually has to change in every op at once to keep failing
+        let mut keys: Vec<_> = ops.iter().filter_map(Op::key).collect();
+        keys.sort_unstable();
+        keys.dedup();
+        for key in keys {
+            for smaller in smaller_keys(key) {
+                let candidate: Vec<_> = ops.iter().map(|op| op.rename(key, smaller)).collect();
+                if run::<M>(&candidate).is_err() {
+                    ops = candidate;
+                    changed = true;
+                    break;
+                }
+            }
+        }
+        for i in 0..ops.len() {
+            for simpler in simplifications(&ops[i]) {
+                let mut candidate = ops.clone();
+                candidate[i] = simpler;
+                if run::<M>(&candidate).is_err() {
+                    ops = candidate;
+                    changed = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    ops
+}
+
+/// Runs M against a BTreeMap on CASES random sequences of ops, and panics with
+/// the shortest failing sequence it can find if any of them fail
+pub fn check<M: MapUnderTest<u16, u32>>(methods: Methods) {
+    let seed = match std::env::var("MAP61B_SEED") {
+        Ok(seed) => seed.parse().expect("MAP61B_SEED should be a u64"),
+        Err(_) => rand::thread_rng().gen(),
+    };
+    let mut rng = StdRng::seed_from_u64(seed);
+    for _ in 0..CASES {
+        // a few keys to hit the same entries often, or many to make the map grow
+        let keys = [4, 64, 2048][rng.gen_range(0..3)];
+        let len = rng.gen_range(1..=MAX_OPS);
+        let ops = random_ops(&mut rng, len, keys, methods);
+        if run::<M>(&ops).is_err() {
+            let ops = shrink::<M>(ops);
+            let failure = run::<M>(&ops).unwrap_err();
+            let ops: Vec<_> = ops.iter().map(|op| format!("    {}", op)).collect();
+            panic!(
+                "map differs from BTreeMap (MAP61B_SEED={}) after:\n{}\n{}",
+                seed,
+                ops.join("\n"),
+                failure.message
+            );
+        }
+    }
+}
@@ -0,0 +1,214 @@
+/*
+Tests that can be run against any implementation of Map61B.
+
+Each lab has its own Map61B trait, so the tests are written against the
+MapUnderTest trait from map61b_common instead. The map61b_tests! macro wraps
+every Map61B type in the crate that calls it with map61b_common's Subject, then
+generates a #[test] for each test in this crate:
+
+    map61b_tests!(TestMap, crate::Map61B, tree);
+
+where TestMap<K, V> is the map type to test. The last argument says what the
+lab's lookups need from a key: `tree` for Ord, `hash` for Hash and Eq. The tests are put in two modules:
+`map` for the methods that every map must have, and `extra` for remove,
+into_iter, and drain and retain (which use into_iter by default), so
+`cargo test map` still runs only the required tests.
+
+A test that only needs Subject, to call the conformance tests itself, can get it
+with map61b_tests!(@subject crate::Map61B, hash).
+*/
+
+pub mod conformance;
+pub mod differential;
+#[cfg(test)]
+mod tests;
+
+// for the macros, so that the crates that use them don't need it as well
+#[doc(hidden)]
+pub use map61b_common;
+
+/// Generates tests for a map type `$map<K, V>` that implements the Map61B
+/// trait at `$trait`, which is a `tree` or a `hash` map. Add `required_only`
+/// to skip the tests of remove and into_iter, for maps that don't have them yet.
+#[macro_export]
+macro_rules! map61b_tests {
+    ($map: ident, $trait: path, $kind: ident) => {
+        $crate::map61b_tests!(@subject $trait, $kind);
+
+        mod map {
+            $crate::map61b_tests!(@required $map, $trait, $kind);
+        }
+
+        mod extra {
+            $crate::map61b_tests!(@optional $map, $trait);
+        }
+    };
+
+    ($map: ident, $trait: path, $kind: ident, required_only) => {
+        $crate::map61b_tests!(@subject $trait, $kind);
+
+        mod map {
+            $crate::map61b_tests!(@required $map, $trait, $kind);
+        }
+    };
+
+    (@subject $trait: path, $kind: ident) => {
+        $crate::map61b_common::map61b_subject!($trait, $kind);
+    };
+
+    // the key type with only the traits that a kind of map needs
+    (@just_key tree) => {
+        $crate::conformance::JustOrd
+    };
+
+    (@just_key hash) => {
+        $crate::conformance::JustHash
+    };
+
+    (@required $map: ident, $trait: path, $kind: ident) => {
+        use super::{$map, Subject};
+        use $crate::conformance::{self, NoTraits};
+        use $crate::differential::{self, Methods};
+        use $crate::map61b_common::MapUnderTest;
+
+        #[test]
+        fn sanity_generics() {
+            let _ = Subject::<$map<u8, ()>>::new();
+            let _ = Subject::<$map<String, Vec<()>>>::new();
+            let _ = Subject::<$map<usize, usize>>::new();
+            let _ = Subject::<$map<bool, $map<bool, String>>>::new();
+            let _ = Subject::<$map<$crate::map61b_tests!(@just_key $kind), NoTraits>>::new();
+        }
+
+        #[test]
+        fn sanity_clear() {
+            conformance::sanity_clear::<Subject<$map<String, usize>>>();
+        }
+
+        #[test]
+        fn sanity_contains_key() {
+            conformance::sanity_contains_key::<Subject<$map<String, i32>>>();
+        }
+
+        #[test]
+        fn sanity_put_get_and_contains() {
+            conformance::sanity_put_get_and_contains::<Subject<$map<String, NoTraits>>>();
+        }
+
+        #[test]
+        fn sanity_len_test() {
+            conformance::sanity_len_test::<Subject<$map<String, i32>>>();
+        }
+
+        #[test]
+        fn contains_none_test() {
+            conformance::contains_none_test::<Subject<$map<String, Option<i32>>>>();
+        }
+
+        #[test]
+        fn tree_test() {
+            conformance::tree_test::<Subject<$map<String, String>>>();
+        }
+
+        #[test]
+        fn functionality_test() {
+            conformance::functionality_test::<
+                Subject<$map<&'static str, &'static str>>,
+                Subject<$map<&'static str, usize>>,
+            >();
+        }
+
+        #[test]
+        fn entry_test() {
+            conformance::entry_test::<Subject<$map<String, usize>>>();
+        }
+
+        #[test]
+        fn borrowed_lookups() {
+            // called through the trait, since std maps have methods with the same names
+            type M = $map<String, usize>;
+            let mut b = <M as $trait>::new();
+            <M as $trait>::insert(&mut b, "hello".to_string(), 1);
+            <M as $trait>::insert(&mut b, "world".to_string(), 2);
+            assert!(<M as $trait>::contains_key(&b, "hello"));
+            assert!(!<M as $trait>::contains_key(&b, "nope"));
+            assert_eq!(<M as $trait>::get(&b, "world"), Some(&2));
+            *<M as $trait>::get_mut(&mut b, "world").unwrap() += 1;
+            assert_eq!(<M as $trait>::get(&b, "world"), Some(&3));
+            assert!(<M as $trait>::get_mut(&mut b, "nope").is_none());
+        }
+
+        #[test]
+        fn borrowing_iter_test() {
+            conformance::borrowing_iter_test::<Subject<$map<String, usize>>>();
+        }
+
+        #[test]
+        fn iter_through_references() {
+            let mut b = <$map<u8, u8> as $trait>::new();
+            for i in 0..10 {
+                <$map<u8, u8> as $trait>::insert(&mut b, i, i);
+            }
+            for (_, v) in &mut b {
+                *v += 1;
+            }
+            let mut total = 0;
+            for (k, v) in &b {
+                assert_eq!(*v, k + 1);
+                total += *v as usize;
+            }
+            assert_eq!(total, 55);
+        }
+
+        #[test]
+        fn differential_test() {
+            differential::check::<Subject<$map<u16, u32>>>(Methods::Required);
+        }
+    };
+
+    (@optional $map: ident, $trait: path) => {
+        use super::{$map, Subject};
+        use $crate::conformance;
+        use $crate::differential::{self, Methods};
+
+        #[test]
+        fn iter_test() {
+            conformance::iter_test::<Subject<$map<String, String>>>();
+        }
+
+        #[test]
+        fn test_remove_root() {
+            conformance::test_remove_root::<Subject<$map<String, String>>>();
+        }
+
+        #[test]
+        fn test_remove_three_cases() {
+            conformance::test_remove_three_cases::<Subject<$map<String, String>>>();
+        }
+
+        #[test]
+        fn test_remove_root_edge() {
+            conformance::test_remove_root_edge::<Subject<$map<char, char>>>();
+        }
+
+        #[test]
+        fn drain_retain_test() {
+            conformance::drain_retain_test::<Subject<$map<usize, usize>>>();
+        }
+
+        #[test]
+        fn borrowed_remove() {
+            type M = $map<String, usize>;
+            let mut b = <M as $trait>::new();
+            <M as $trait>::insert(&mut b, "hello".to_string(), 1);
+            assert_eq!(<M as $trait>::remove(&mut b, "nope"), None);
+            assert_eq!(<M as $trait>::remove(&mut b, "hello"), Some(1));
+            assert!(<M as $trait>::is_empty(&b));
+        }
+
+        #[test]
+        fn differential_test() {
+            differential::check::<Subject<$map<u16, u32>>>(Methods::All);
+        }
+    };
+}
@@ -0,0 +1,298 @@
+use std::collections::BTreeMap;
+
+use map61b_common::MapUnderTest;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::conformance::{self, NoTraits};
+use crate::differential::{self, Methods, Op};
+
+/// A BTreeMap, which should pass every test
+struct Model<K, V>(BTreeMap<K, V>);
+
+impl<K: Ord, V> MapUnderTest<K, V> for Model<K, V> {
+    fn new() -> Self {
+        Model(BTreeMap::new())
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn clear(&mut self) {
+        self.0.clear()
+    }
+
+    fn contains_key(&self, key: &K) -> bool {
+        self.0.contains_key(key)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.0.insert(key, value)
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        self.0.get(key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.0.get_mut(key)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.0.remove(key)
+    }
+
+    fn into_entries(self) -> Vec<(K, V)> {
+        self.0.into_iter().collect()
+    }
+
+    fn or_insert(&mut self, key: K, value: V) -> &mut V {
+        self.0.entry(key).or_insert(value)
+    }
+
+    fn and_modify(
+        &mut self,
+        key: K,
+        f: impl FnOnce(&mut V),
+        default: impl FnOnce() -> V,
+    ) -> &mut V {
+        self.0.entry(key).and_modify(f).or_insert_with(default)
+    }
+
+    fn iter(&self) -> Vec<(&K, &V)> {
+        self.0.iter().collect()
+    }
+
+    fn keys(&self) -> Vec<&K> {
+        self.0.keys().collect()
+    }
+
+    fn values(&self) -> Vec<&V> {
+        self.0.values().collect()
+    }
+
+    fn iter_mut(&mut self, mut f: impl FnMut(&K, &mut V)) {
+        self.0.iter_mut().for_each(|(k, v)| f(k, v))
+    }
+
+    fn values_mut(&mut self, f: impl FnMut(&mut V)) {
+        self.0.values_mut().for_each(f)
+    }
+
+    fn drain(&mut self) -> Vec<(K, V)> {
+        std::mem::take(&mut self.0).into_iter().collect()
+    }
+
+    fn retain(&mut self, f: impl FnMut(&K, &mut V) -> bool) {
+        self.0.retain(f)
+    }
+}
+
+/// inserting a key that is already there counts it again
+const COUNTS_OVERWRITES: u8 = 0;
+/// removing a key that isn't there panics
+const PANICS_ON_MISSING: u8 = 1;
+
+/// A BTreeMap with one of the bugs above
+struct Buggy<const BUG: u8> {
+    map: BTreeMap<u16, u32>,
+    len: usize,
+}
+
+impl<const BUG: u8> MapUnderTest<u16, u32> for Buggy<BUG> {
+    fn new() -> Self {
+        Buggy {
+            map: BTreeMap::new(),
+            len: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+        self.len = 0;
+    }
+
+    fn contains_key(&self, key: &u16) -> bool {
+        self.map.contains_key(key)
+    }
+
+    fn insert(&mut self, key: u16, value: u32) -> Option<u32> {
+        let prev = self.map.insert(key, value);
+        if prev.is_none() || BUG == COUNTS_OVERWRITES {
+            self.len += 1;
+        }
+        prev
+    }
+
+    fn get(&self, key: &u16) -> Option<&u32> {
+        self.map.get(key)
+    }
+
+    fn get_mut(&mut self, key: &u16) -> Option<&mut u32> {
+        self.map.get_mut(key)
+    }
+
+    fn remove(&mut self, key: &u16) -> Option<u32> {
+        let value = self.map.remove(key);
+        if BUG == PANICS_ON_MISSING {
+            value.expect("key is missing");
+        }
+        if value.is_some() {
+            self.len -= 1;
+        }
+        value
+    }
+
+    fn into_entries(self) -> Vec<(u16, u32)> {
+        self.map.into_iter().collect()
+    }
+
+    fn or_insert(&mut self, key: u16, value: u32) -> &mut u32 {
+        self.and_modify(key, |_| {}, || value)
+    }
+
+    fn and_modify(
+        &mut self,
+        key: u16,
+        f: impl FnOnce(&mut u32),
+        default: impl FnOnce() -> u32,
+    ) -> &mut u32 {
+        if !self.map.contains_key(&key) {
+            self.len += 1;
+        }
+        self.map.entry(key).and_modify(f).or_insert_with(default)
+    }
+
+    fn iter(&self) -> Vec<(&u16, &u32)> {
+        self.map.iter().collect()
+    }
+
+    fn keys(&self) -> Vec<&u16> {
+        self.map.keys().collect()
+    }
+
+    fn values(&self) -> Vec<&u32> {
+        self.map.values().collect()
+    }
+
+    fn iter_mut(&mut self, mut f: impl FnMut(&u16, &mut u32)) {
+        self.map.iter_mut().for_each(|(k, v)| f(k, v))
+    }
+
+    fn values_mut(&mut self, f: impl FnMut(&mut u32)) {
+        self.map.values_mut().for_each(f)
+    }
+
+    fn drain(&mut self) -> Vec<(u16, u32)> {
+        self.len = 0;
+        std::mem::take(&mut self.map).into_iter().collect()
+    }
+
+    fn retain(&mut self, f: impl FnMut(&u16, &mut u32) -> bool) {
+        self.map.retain(f);
+        self.len = self.map.len();
+    }
+}
+
+/// Returns random ops that make M fail
+fn failing_ops<M: MapUnderTest<u16, u32>>(methods: Methods) -> Vec<Op> {
+    let mut rng = StdRng::seed_from_u64(61);
+    loop {
+        let ops = differential::random_ops(&mut rng, 200, 64, methods);
+        if differential::run::<M>(&ops).is_err() {
+            return ops;
+        }
+    }
+}
+
+#[test]
+fn model_passes_conformance() {
+    conformance::sanity_clear::<Model<_, _>>();
+    conformance::sanity_contains_key::<Model<_, _>>();
+    conformance::sanity_put_get_and_contains::<Model<String, NoTraits>>();
+    conformance::sanity_len_test::<Model<_, _>>();
+    conformance::contains_none_test::<Model<_, _>>();
+    conformance::tree_test::<Model<_, _>>();
+    conformance::functionality_test::<Model<_, _>, Model<_, _>>();
+    conformance::entry_test::<Model<_, _>>();
+    conformance::borrowing_iter_test::<Model<_, _>>();
+    conformance::drain_retain_test::<Model<_, _>>();
+    conformance::iter_test::<Model<_, _>>();
+    conformance::test_remove_root::<Model<_, _>>();
+    conformance::test_remove_three_cases::<Model<_, _>>();
+    conformance::test_remove_root_edge::<Model<_, _>>();
+}
+
+#[test]
+fn model_passes_differential() {
+    differential::check::<Model<u16, u32>>(Methods::All);
+}
+
+#[test]
+fn required_ops_skip_optional_methods() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let ops = differential::random_ops(&mut rng, 10_000, 64, Methods::Required);
+    assert!(ops
+        .iter()
+        .all(|op| !matches!(op, Op::Remove(_) | Op::IntoIter | Op::Drain | Op::Retain(_))));
+    assert!(ops.iter().any(|op| matches!(op, Op::Iter)));
+    let ops = differential::random_ops(&mut rng, 10_000, 64, Methods::All);
+    assert!(ops.iter().any(|op| matches!(op, Op::Remove(_))));
+    assert!(ops.iter().any(|op| matches!(op, Op::IntoIter)));
+    assert!(ops.iter().any(|op| matches!(op, Op::Drain)));
+    assert!(ops.iter().any(|op| matches!(op, Op::Retain(_))));
+}
+
+#[test]
+fn run_finds_first_difference() {
+    let ops = [Op::Insert(1, 1), Op::Get(1), Op::Insert(1, 2), Op::Len];
+    let failure = differential::run::<Buggy<COUNTS_OVERWRITES>>(&ops).unwrap_err();
+    assert_eq!(failure.step, 2);
+    assert_eq!(failure.message, "len() is 2 after insert(1, 2), expected 1");
+    assert!(differential::run::<Buggy<PANICS_ON_MISSING>>(&ops).is_ok());
+}
+
+#[test]
+fn run_catches_panics() {
+    let ops = [Op::Insert(1, 1), Op::Remove(1), Op::Remove(1)];
+    let failure = differential::run::<Buggy<PANICS_ON_MISSING>>(&ops).unwrap_err();
+    assert_eq!(failure.step, 2);
+    assert_eq!(failure.message, "panicked: key is missing");
+}
+
+#[test]
+fn shrink_finds_minimal_sequence() {
+    let ops = failing_ops::<Buggy<COUNTS_OVERWRITES>>(Methods::Required);
+    assert!(ops.len() > 2);
+    let shrunk = differential::shrink::<Buggy<COUNTS_OVERWRITES>>(ops);
+    assert_eq!(shrunk, [Op::Insert(0, 0), Op::Insert(0, 0)]);
+
+    let ops = failing_ops::<Buggy<PANICS_ON_MISSING>>(Methods::All);
+    let shrunk = differential::shrink::<Buggy<PANICS_ON_MISSING>>(ops);
+    assert_eq!(shrunk, [Op::Remove(0)]);
+}
+
+#[test]
+fn shrink_keeps_passing_sequence() {
+    let ops = vec![Op::Insert(3, 4), Op::Remove(3)];
+    assert_eq!(differential::shrink::<Model<_, _>>(ops.clone()), ops);
+}
+
+#[test]
+#[should_panic(expected = "map differs from BTreeMap")]
+fn check_panics_on_bug() {
+    differential::check::<Buggy<COUNTS_OVERWRITES>>(Methods::Required);
+}
@@ -11,6 +11,15 @@ function showGraphResult(data) {
     document.getElementById("main").replaceChildren(generate_chart(data));
 }
 
+// errors are sent as {"status": 400, "error": "..."}
+async function checkResponse(r) {
+    if (!r.ok) {
+        let body = await r.json();
+        throw new Error(body.error);
+    }
+    return r;
+}
+
 
 
 let form = document.getElementById("main-form");
@@ -21,29 +30,80 @@ form.onsubmit = function(e) {
     let startYear = encodeURIComponent(form.startYear.value);
     let endYear = encodeURIComponent(form.endYear.value);
     let k = encodeURIComponent(form.k.value);
+    let smoothing = encodeURIComponent(form.smoothing.value);
     let queryString = `?words=${words}&start-year=${startYear}&end-year=${endYear}&k=${k}`;
+    let historyString = `${queryString}&smoothing=${smoothing}`;
 
     showTextResult("loading...");
 
     if (queryType == "History (graph)") {
-        fetch("history" + queryString)
+        fetch("history" + historyString)
+            .then(checkResponse)
             .then((r) => r.json())
             .then(showGraphResult)
-            .catch((e) => alert("error! " + e))
+            .catch((e) => showTextResult("error! " + e.message))
     } else if (queryType == "History (text)") {
-        fetch("history" + queryString)
+        fetch("historytext" + historyString)
+            .then(checkResponse)
             .then((r) => r.text())
             .then(showTextResult)
-            .catch((e) => alert("error! " + e))
+            .catch((e) => showTextResult("error! " + e.message))
     } else if (queryType == "Synonyms (text)") {
         fetch("synonyms" + queryString)
+            .then(checkResponse)
             .then((r) => r.text())
             .then(showTextResult)
-            .catch((e) => alert("error! " + e))
+            .catch((e) => showTextResult("error! " + e.message))
     } else if (queryType == "Hyponyms (text)") {
         fetch("hyponyms" + queryString)
+            .then(checkResponse)
             .then((r) => r.text())
             .then(showTextResult)
-            .catch((e) => alert("error! " + e))
+            .catch((e) => showTextResult("error! " + e.message))
+    } else if (queryType == "Hypernyms (text)") {
+        fetch("hypernyms" + queryString)
+            .then(checkResponse)
+            .then((r) => r.text())
+            .then(showTextResult)
+            .catch((e) => showTextResult("error! " + e.message))
+    } else if (queryType == "Common ancestor (text)") {
+        fetch(`ancestor?words=${words}`)
+            .then(checkResponse)
+            .then((r) => r.json())
+            .then((data) => showTextResult(JSON.stringify(data, null, 2)))
+            .catch((e) => showTextResult("error! " + e.message))
+    } else if (queryType == "Distance (text)") {
+        fetch(`distance?words=${words}`)
+            .then(checkResponse)
+            .then((r) => r.json())
+            .then((data) => showTextResult(JSON.stringify(data, null, 2)))
+            .catch((e) => showTextResult("error! " + e.message))
+    }
+}
+
+// suggest completions for the word currently being typed, which is
+// everything after the last "," or "+"
+form.words.oninput = function() {
+    let text = form.words.value;
+    let split = Math.max(text.lastIndexOf(","), text.lastIndexOf("+")) + 1;
+    let head = text.slice(0, split);
+    let prefix = text.slice(split).trim();
+    if (prefix.length == 0) {
+        return;
     }
+    let startYear = encodeURIComponent(form.startYear.value);
+    let endYear = encodeURIComponent(form.endYear.value);
+    let queryString = `?prefix=${encodeURIComponent(prefix)}&k=10&fuzzy=true&start-year=${startYear}&end-year=${endYear}`;
+    fetch("autocomplete" + queryString)
+        .then(checkResponse)
+        .then((r) => r.json())
+        .then((words) => {
+            let options = words.map((word) => {
+                let option = document.createElement("option");
+                option.value = head + (head.length > 0 ? " " : "") + word;
+                return option;
+            });
+            document.getElementById("suggestions").replaceChildren(...options);
+        })
+        .catch(() => {})
 }
@@ -0,0 +1,258 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    hash::Hash,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+const NIL: usize = usize::MAX;
+
+/*
+A least-recently-used cache with a fixed capacity.
+Entries live in a Vec, and are linked together into a doubly-linked list by
+index, from most recently used (head) to least recently used (tail). The
+HashMap finds the entry for a key, so every operation is O(1).
+*/
+pub struct LruCache<K, V> {
+    map: HashMap<K, usize>,
+    entries: Vec<LruEntry<K, V>>,
+    head: usize,
+    tail: usize,
+    capacity: usize,
+}
+
+struct LruEntry<K, V> {
+    key: K,
+    value: V,
+    prev: usize,
+    next: usize,
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    /// Creates a cache that holds at most capacity entries (and at least one)
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            map: HashMap::new(),
+            entries: Vec::new(),
+            head: NIL,
+            tail: NIL,
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the value for key, marking it as the most recently used
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let i = *self.map.get(key)?;
+        self.unlink(i);
+        self.push_front(i);
+        Some(&self.entries[i].value)
+    }
+
+    /// Inserts key and value as the most recently used entry,
+    /// evicting the least recently used entry if the cache is full
+    pub fn insert(&mut self, key: K, value: V) {
+        if let Some(&i) = self.map.get(&key) {
+            self.entries[i].value = value;
+            self.unlink(i);
+            self.push_front(i);
+            return;
+        }
+        let i = if self.entries.len() < self.capacity {
+            self.entries.push(LruEntry {
+                key: key.clone(),
+                value,
+                prev: NIL,
+                next: NIL,
+            });
+            self.entries.len() - 1
+        } else {
+            // reuse the slot of the least recently used entry
+            let i = self.tail;
+            self.unlink(i);
+            let old = std::mem::replace(&mut self.entries[i].key, key.clone());
+            self.map.remove(&old);
+            self.entries[i].value = value;
+            i
+        };
+        self.map.insert(key, i);
+        self.push_front(i);
+    }
+
+    fn unlink(&mut self, i: usize) {
+        let (prev, next) = (self.entries[i].prev, self.entries[i].next);
+        match prev {
+            NIL => self.head = next,
+            p => self.entries[p].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            n => self.entries[n].prev = prev,
+        }
+    }
+
+    fn push_front(&mut self, i: usize) {
+        self.entries[i].prev = NIL;
+        self.entries[i].next = self.head;
+        match self.head {
+            NIL => self.tail = i,
+            h => self.entries[h].prev = i,
+        }
+        self.head = i;
+    }
+}
+
+/// The kinds of requests that NGordNetHandler responds to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Route {
+    History,
+    HistoryText,
+    Synonyms,
+    Hyponyms,
+    Hypernyms,
+    Autocomplete,
+    Ancestor,
+    Distance,
+}
+
+impl Route {
+    pub const ALL: [Route; 8] = [
+        Route::History,
+        Route::HistoryText,
+        Route::Synonyms,
+        Route::Hyponyms,
+        Route::Hypernyms,
+        Route::Autocomplete,
+        Route::Ancestor,
+        Route::Distance,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Route::History => "history",
+            Route::HistoryText => "historytext",
+            Route::Synonyms => "synonyms",
+            Route::Hyponyms => "hyponyms",
+            Route::Hypernyms => "hypernyms",
+            Route::Autocomplete => "autocomplete",
+            Route::Ancestor => "ancestor",
+            Route::Distance => "distance",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RouteStats {
+    pub requests: u64,
+    pub hits: u64,
+    pub total_latency: Duration,
+}
+
+impl RouteStats {
+    pub fn hit_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.hits as f64 / self.requests as f64
+        }
+    }
+
+    pub fn average_latency(&self) -> Duration {
+        if self.requests == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(self.total_latency.as_secs_f64() / self.requests as f64)
+        }
+    }
+}
+
+/*
+Caches responses by route and normalized query, and keeps statistics about
+each route. Both are behind a Mutex so that Rocket's workers can share one
+ResponseCache; the lock is not held while a response is being computed, so
+two workers that miss on the same query at the same time both compute it.
+*/
+pub struct ResponseCache {
+    responses: Mutex<LruCache<(Route, String), String>>,
+    stats: Mutex<HashMap<Route, RouteStats>>,
+}
+
+impl ResponseCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            responses: Mutex::new(LruCache::new(capacity)),
+            stats: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the cached response for (route, key), or computes and caches it
+    pub fn get_or_insert_with(
+        &self,
+        route: Route,
+        key: String,
+        compute: impl FnOnce() -> String,
+    ) -> String {
+        let start = Instant::now();
+        let key = (route, key);
+        let cached = self.responses.lock().unwrap().get(&key).cloned();
+        let hit = cached.is_some();
+        let response = match cached {
+            Some(response) => response,
+            None => {
+                let response = compute();
+                self.responses.lock().unwrap().insert(key, response.clone());
+                response
+            }
+        };
+
+        let mut stats = self.stats.lock().unwrap();
+        let route_stats = stats.entry(route).or_default();
+        route_stats.requests += 1;
+        route_stats.hits += hit as u64;
+        route_stats.total_latency += start.elapsed();
+        response
+    }
+
+    pub fn route_stats(&self, route: Route) -> RouteStats {
+        let stats = self.stats.lock().unwrap();
+        stats.get(&route).copied().unwrap_or_default()
+    }
+
+    /// Returns the cache size and each route's statistics as a JSON object
+    pub fn stats_json(&self) -> String {
+        let (entries, capacity) = {
+            let responses = self.responses.lock().unwrap();
+            (responses.len(), responses.capacity())
+        };
+        let mut json = String::from("{");
+        write!(
+            &mut json,
+            "\n  \"entries\": {entries},\n  \"capacity\": {capacity},\n  \"routes\": {{"
+        )
+        .unwrap();
+        for (i, route) in Route::ALL.into_iter().enumerate() {
+            let stats = self.route_stats(route);
+            write!(
+                &mut json,
+                "{}\n    \"{}\": {{\"requests\": {}, \"hits\": {}, \"hit_rate\": {:.3}, \"average_latency_ms\": {:.3}}}",
+                if i == 0 { "" } else { "," },
+                route.name(),
+                stats.requests,
+                stats.hits,
+                stats.hit_rate(),
+                stats.average_latency().as_secs_f64() * 1e3,
+            )
+            .unwrap();
+        }
+        json += "\n  }\n}";
+        json
+    }
+}
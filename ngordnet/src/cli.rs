@@ -0,0 +1,202 @@
+use std::io::{self, BufRead, Write};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::{
+    handler::NGordNetHandler,
+    query::{validate_smoothing, QueryError},
+    PairQuery, Query,
+};
+
+#[derive(Parser, Debug)]
+/// Explore the history of words and how they are related
+pub struct Cli {
+    #[clap(flatten)]
+    pub data: DataFiles,
+    /// what to do. Starts the web server if left out.
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// The files that NGordNetHandler is built from
+#[derive(Args, Debug)]
+pub struct DataFiles {
+    #[clap(
+        long,
+        global = true,
+        default_value = "./data/ngrams/top_49887_words.csv"
+    )]
+    pub words_file: String,
+    #[clap(long, global = true, default_value = "./data/ngrams/total_counts.csv")]
+    pub counts_file: String,
+    #[clap(long, global = true, default_value = "./data/wordnet/synsets.txt")]
+    pub synset_file: String,
+    #[clap(long, global = true, default_value = "./data/wordnet/hyponyms.txt")]
+    pub hyponym_file: String,
+    /// where to keep a binary copy of the ngram data, which loads much faster
+    /// than the csv files. Nothing is cached if left out.
+    #[clap(long, global = true)]
+    pub cache_file: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Serve public/ and the query routes over HTTP
+    Serve,
+    /// Print the response to one query
+    Query(QueryArgs),
+    /// Read queries from standard input, one per line, and print their responses.
+    /// Each line takes the same arguments as the query command, e.g.
+    /// `hyponyms --words "change, occurrence" --k 5`.
+    Repl,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct QueryArgs {
+    #[clap(value_enum)]
+    pub kind: QueryKind,
+    /// comma-separated words. History entries of the form "a + b" are summed.
+    #[clap(long)]
+    pub words: String,
+    #[clap(long, default_value_t = 1900)]
+    pub start: usize,
+    #[clap(long, default_value_t = 2020)]
+    pub end: usize,
+    /// only respond with the k most popular words, or all of them if k is 0
+    #[clap(long, default_value_t = 0)]
+    pub k: usize,
+    /// number of years that histories are averaged over
+    #[clap(long, default_value_t = 1)]
+    pub smoothing: usize,
+}
+
+/// The kinds of queries, named after the server's routes
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueryKind {
+    History,
+    #[clap(name = "historytext")]
+    HistoryText,
+    Synonyms,
+    Hyponyms,
+    Hypernyms,
+    Ancestor,
+    Distance,
+}
+
+/// Responds to a query. The REPL also understands "stats", "help" and "quit".
+#[derive(Parser, Debug)]
+#[clap(name = "query", no_binary_name = true)]
+struct ReplLine {
+    #[clap(flatten)]
+    query: QueryArgs,
+}
+
+/// Responds to a query with the same response the server would give
+pub fn respond(handler: &NGordNetHandler, args: &QueryArgs) -> Result<String, QueryError> {
+    Ok(match args.kind {
+        QueryKind::History => {
+            handler.respond_smoothed_history(word_query(handler, args)?, args.smoothing)
+        }
+        QueryKind::HistoryText => {
+            handler.respond_history_text(word_query(handler, args)?, args.smoothing)
+        }
+        QueryKind::Synonyms => handler.respond_synonyms(word_query(handler, args)?),
+        QueryKind::Hyponyms => handler.respond_hyponyms(word_query(handler, args)?),
+        QueryKind::Hypernyms => handler.respond_hypernyms(word_query(handler, args)?),
+        QueryKind::Ancestor => handler.respond_ancestor(pair_query(handler, args)?)?,
+        QueryKind::Distance => handler.respond_distance(pair_query(handler, args)?)?,
+    })
+}
+
+/// Returns the Query in args, checked the way the server checks it
+fn word_query<'a>(handler: &NGordNetHandler, args: &'a QueryArgs) -> Result<Query<'a>, QueryError> {
+    let query = Query {
+        words: &args.words,
+        start_year: args.start,
+        end_year: args.end,
+        k: args.k,
+    };
+    query.validate()?;
+    validate_smoothing(args.smoothing)?;
+    handler.check_known_words(&query)?;
+    Ok(query)
+}
+
+/// Returns the PairQuery in args, checked the way the server checks it
+fn pair_query<'a>(
+    handler: &NGordNetHandler,
+    args: &'a QueryArgs,
+) -> Result<PairQuery<'a>, QueryError> {
+    let query = PairQuery { words: &args.words };
+    query.validate()?;
+    handler.check_wordnet_words(query.words)?;
+    Ok(query)
+}
+
+/// Answers each line of input until the input ends or a line is "quit".
+/// Besides queries, "stats" prints the cache statistics and "help" prints the query syntax.
+/// With prompt, a prompt is printed before each line is read.
+pub fn repl(
+    handler: &NGordNetHandler,
+    input: impl BufRead,
+    mut output: impl Write,
+    prompt: bool,
+) -> io::Result<()> {
+    let mut lines = input.lines();
+    loop {
+        if prompt {
+            write!(output, "> ")?;
+            output.flush()?;
+        }
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let line = line?;
+        let response = match line.trim() {
+            "" => continue,
+            "quit" | "exit" => break,
+            "stats" => handler.respond_stats(),
+            "help" => ReplLine::try_parse_from(["--help"])
+                .unwrap_err()
+                .to_string(),
+            line => match split_args(line) {
+                Ok(args) => match ReplLine::try_parse_from(args) {
+                    Ok(parsed) => match respond(handler, &parsed.query) {
+                        Ok(response) => response,
+                        Err(e) => format!("error: {e}"),
+                    },
+                    Err(e) => e.to_string(),
+                },
+                Err(e) => format!("error: {e}"),
+            },
+        };
+        write!(output, "{response}")?;
+        if !response.ends_with('\n') {
+            writeln!(output)?;
+        }
+    }
+    Ok(())
+}
+
+/// Splits a line into whitespace-separated arguments.
+/// Double quotes group words into one argument, as in `--words "cat, dog"`.
+pub fn split_args(line: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                arg.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !quoted => args.extend(arg.take()),
+            c => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quote".to_string());
+    }
+    args.extend(arg);
+    Ok(args)
+}
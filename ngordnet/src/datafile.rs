@@ -0,0 +1,17 @@
+use std::{fs::File, io};
+
+/*
+Errors for the data files that NGramMap and WordNet read, which name the file
+and line that was the problem.
+*/
+
+pub fn open(file: &str) -> Result<File, std::io::Error> {
+    File::open(file).map_err(|e| io::Error::new(e.kind(), format!("could not open {file}: {e}")))
+}
+
+pub fn malformed(file: &str, line_num: usize) -> std::io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{file}: malformed line {}", line_num + 1),
+    )
+}
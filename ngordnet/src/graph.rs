@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+/// The shortest path from two sets of vertices to a common ancestor,
+/// where following an edge goes from a vertex to one of its parents
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AncestralPath {
+    pub ancestor: usize,
+    /// number of edges from the closest vertex in the first set to ancestor
+    pub from_a: usize,
+    /// number of edges from the closest vertex in the second set to ancestor
+    pub from_b: usize,
+}
+
+impl AncestralPath {
+    pub fn length(&self) -> usize {
+        self.from_a + self.from_b
+    }
+}
+
+/// A directed graph over the vertices 0..len, stored as adjacency lists
+#[derive(Clone, Debug)]
+pub struct Digraph {
+    adj: Vec<Vec<usize>>,
+}
+
+impl Digraph {
+    /// Creates a graph with num_vertices vertices and no edges
+    pub fn new(num_vertices: usize) -> Self {
+        Self {
+            adj: vec![Vec::new(); num_vertices],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.adj.len()
+    }
+
+    /// Adds the edge from -> to, growing the graph if either vertex is out of range
+    pub fn add_edge(&mut self, from: usize, to: usize) {
+        let needed = from.max(to) + 1;
+        if self.adj.len() < needed {
+            self.adj.resize(needed, Vec::new());
+        }
+        self.adj[from].push(to);
+    }
+
+    /// Returns the vertices that v has an edge to
+    pub fn neighbors(&self, v: usize) -> &[usize] {
+        &self.adj[v]
+    }
+
+    /// Returns a copy of this graph with every edge flipped
+    pub fn reverse(&self) -> Self {
+        let mut reversed = Self::new(self.len());
+        for (from, tos) in self.adj.iter().enumerate() {
+            for &to in tos {
+                reversed.adj[to].push(from);
+            }
+        }
+        reversed
+    }
+
+    /// Returns every vertex reachable from any of the starting vertices,
+    /// including the starting vertices themselves
+    pub fn reachable(&self, starts: impl IntoIterator<Item = usize>) -> Vec<usize> {
+        let mut marked = vec![false; self.len()];
+        let mut stack: Vec<usize> = Vec::new();
+        let mut reached = Vec::new();
+        for s in starts {
+            if !marked[s] {
+                marked[s] = true;
+                stack.push(s);
+            }
+        }
+        // iterative dfs, so that long chains of edges cannot overflow the stack
+        while let Some(v) = stack.pop() {
+            reached.push(v);
+            for &w in self.neighbors(v) {
+                if !marked[w] {
+                    marked[w] = true;
+                    stack.push(w);
+                }
+            }
+        }
+        reached
+    }
+
+    /// Returns the shortest ancestral path between any vertex in a and any vertex in b,
+    /// or None if they have no common ancestor. Ties go to the ancestor with the lowest id.
+    pub fn shortest_ancestral_path(
+        &self,
+        a: impl IntoIterator<Item = usize>,
+        b: impl IntoIterator<Item = usize>,
+    ) -> Option<AncestralPath> {
+        // the distance to each vertex found so far from a and from b
+        let mut found: HashMap<usize, [Option<usize>; 2]> = HashMap::new();
+        let mut frontiers: [Vec<usize>; 2] = Default::default();
+        let mut levels = [0; 2];
+        let mut best: Option<AncestralPath> = None;
+
+        let mut visit = |v: usize, side: usize, dist: usize, best: &mut Option<AncestralPath>| {
+            let dists = found.entry(v).or_default();
+            if dists[side].is_some() {
+                return false;
+            }
+            dists[side] = Some(dist);
+            if let [Some(from_a), Some(from_b)] = *dists {
+                let path = AncestralPath {
+                    ancestor: v,
+                    from_a,
+                    from_b,
+                };
+                if best.is_none_or(|b| (path.length(), v) < (b.length(), b.ancestor)) {
+                    *best = Some(path);
+                }
+            }
+            true
+        };
+        for s in a {
+            if visit(s, 0, 0, &mut best) {
+                frontiers[0].push(s);
+            }
+        }
+        for s in b {
+            if visit(s, 1, 0, &mut best) {
+                frontiers[1].push(s);
+            }
+        }
+
+        // bfs from both sides, one level at a time, always advancing the side that
+        // is less far along. Every vertex that hasn't been found from both sides
+        // is at least min(levels) + 1 edges away from one of them, so once the
+        // best path is shorter than that, no better path can be found.
+        while let Some(side) = (0..2)
+            .filter(|&side| !frontiers[side].is_empty())
+            .min_by_key(|&side| (levels[side], frontiers[side].len()))
+        {
+            if best.is_some_and(|b| b.length() <= levels[side]) {
+                break;
+            }
+            let mut next = Vec::new();
+            for v in std::mem::take(&mut frontiers[side]) {
+                for &w in self.neighbors(v) {
+                    if visit(w, side, levels[side] + 1, &mut best) {
+                        next.push(w);
+                    }
+                }
+            }
+            frontiers[side] = next;
+            levels[side] += 1;
+        }
+        best
+    }
+
+    /// Returns the number of edges from v to the closest vertex with no outgoing edges,
+    /// or None if there is no such vertex
+    pub fn distance_to_sink(&self, v: usize) -> Option<usize> {
+        let mut marked = vec![false; self.len()];
+        let mut frontier = vec![v];
+        marked[v] = true;
+        let mut dist = 0;
+        while !frontier.is_empty() {
+            let mut next = Vec::new();
+            for u in frontier {
+                if self.neighbors(u).is_empty() {
+                    return Some(dist);
+                }
+                for &w in self.neighbors(u) {
+                    if !marked[w] {
+                        marked[w] = true;
+                        next.push(w);
+                    }
+                }
+            }
+            frontier = next;
+            dist += 1;
+        }
+        None
+    }
+}
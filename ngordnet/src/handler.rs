@@ -1,39 +1,339 @@
-use crate::{ngrammap::NGramMap, timeseries::TimeSeries, Query, dummy::Dummy};
-use std::fmt::Write;
+use crate::{
+    cache::{ResponseCache, Route},
+    ngrammap::NGramMap,
+    query::{parse_words, QueryError},
+    timeseries::TimeSeries,
+    trie::Trie,
+    wordnet::WordNet,
+    AutocompleteQuery, PairQuery, Query,
+};
+use std::{collections::BTreeSet, fmt::Write};
+
+/// Number of responses kept by each NGordNetHandler
+const CACHE_CAPACITY: usize = 1024;
+
+/// Shortest prefix that autocomplete matches fuzzily. Almost every word starts
+/// with something one edit away from a shorter one, so it would score the whole vocabulary.
+const MIN_FUZZY_PREFIX: usize = 3;
 
 /// keeps server state necessary to respond to requests
 pub struct NGordNetHandler {
-    dummy: Dummy
+    ngm: NGramMap,
+    wordnet: WordNet,
+    /// maps each word in ngm to its id
+    vocabulary: Trie,
+    cache: ResponseCache,
 }
 
 impl NGordNetHandler {
+    /// Loads the data files. The ngram data takes a while to parse, so with a
+    /// cache_file, it's loaded from there if it's up to date, and saved there if not.
     pub fn new(
         words_file: &str,
         counts_file: &str,
         synset_file: &str,
         hyponym_file: &str,
+        cache_file: Option<&str>,
     ) -> Result<Self, std::io::Error> {
+        let ngm = match cache_file {
+            Some(cache_file) => NGramMap::open_cached(words_file, counts_file, cache_file)?,
+            None => NGramMap::new(words_file, counts_file)?,
+        };
+        let mut vocabulary = Trie::new();
+        for (id, word) in ngm.words().iter().enumerate() {
+            vocabulary.insert(word, id as u32);
+        }
         Ok(Self {
-            dummy: Dummy::new("quotes.txt")?
+            ngm,
+            wordnet: WordNet::new(synset_file, hyponym_file)?,
+            vocabulary,
+            cache: ResponseCache::new(CACHE_CAPACITY),
         })
     }
 
+    /// Responds with the weight history of each comma-separated entry in query.words,
+    /// formatted as a JSON object for public/graph.js.
+    /// Entries of the form "a + b" are graphed as the sum of the weight histories of a and b.
     pub fn respond_history(&self, query: Query) -> String {
-        format!("history query: {:?}\n{}", query, self.dummy.quote())
+        self.respond_smoothed_history(query, 1)
+    }
+
+    /// Like respond_history, but each year's value is the average of the values
+    /// in the window of `smoothing` years ending at that year
+    pub fn respond_smoothed_history(&self, query: Query, smoothing: usize) -> String {
+        let key = format!("{}|{smoothing}", cache_key(&query));
+        self.cache.get_or_insert_with(Route::History, key, || {
+            json_object_string(&self.histories(&query, smoothing))
+        })
+    }
+
+    /// Like respond_smoothed_history, but formatted as plain text, one entry per line
+    pub fn respond_history_text(&self, query: Query, smoothing: usize) -> String {
+        let key = format!("{}|{smoothing}", cache_key(&query));
+        self.cache.get_or_insert_with(Route::HistoryText, key, || {
+            let mut text = String::new();
+            for (entry, history) in self.histories(&query, smoothing) {
+                let points: Vec<String> = history
+                    .data
+                    .iter()
+                    .map(|(year, value)| format!("{year}={value:.3e}"))
+                    .collect();
+                writeln!(&mut text, "{entry}: {{{}}}", points.join(", ")).unwrap();
+            }
+            text
+        })
+    }
+
+    /// Returns the (smoothed) weight history of each comma-separated entry in query.words
+    fn histories(&self, query: &Query, smoothing: usize) -> Vec<(String, TimeSeries)> {
+        let smoothing = smoothing.max(1);
+        // the window for start_year reaches back before start_year
+        let first_year = query.start_year.saturating_sub(smoothing - 1);
+
+        let mut histories: Vec<(String, TimeSeries)> = Vec::new();
+        for entry in parse_words(query.words) {
+            let words = split_sum(&entry);
+            let label = words.join(" + ");
+            if words.is_empty() || histories.iter().any(|(l, _)| *l == label) {
+                continue;
+            }
+            let mut history = self
+                .ngm
+                .summed_weight_history(&words, first_year, query.end_year);
+            if smoothing > 1 {
+                history = history.rolling_mean(smoothing);
+                history.restrict(query.start_year, query.end_year);
+            }
+            histories.push((label, history));
+        }
+        histories
     }
 
+    /// Responds with the words that are synonyms of every word in query.words
     pub fn respond_synonyms(&self, query: Query) -> String {
-        format!("synonyms query: {:?}\n{}", query, self.dummy.quote())
+        self.cache
+            .get_or_insert_with(Route::Synonyms, cache_key(&query), || {
+                let words = parse_words(query.words);
+                let synonyms = self.wordnet.synonyms(&words);
+                self.most_popular(synonyms, &query).join(", ")
+            })
     }
 
+    /// Responds with the words that are hyponyms of every word in query.words
     pub fn respond_hyponyms(&self, query: Query) -> String {
-        format!("hyponyms query: {:?}\n{}", query, self.dummy.quote())
+        self.cache
+            .get_or_insert_with(Route::Hyponyms, cache_key(&query), || {
+                let words = parse_words(query.words);
+                let hyponyms = self.wordnet.hyponyms(&words);
+                self.most_popular(hyponyms, &query).join(", ")
+            })
+    }
+
+    /// Responds with the words that are hypernyms of every word in query.words
+    pub fn respond_hypernyms(&self, query: Query) -> String {
+        self.cache
+            .get_or_insert_with(Route::Hypernyms, cache_key(&query), || {
+                let words = parse_words(query.words);
+                let hypernyms = self.wordnet.hypernyms(&words);
+                self.most_popular(hypernyms, &query).join(", ")
+            })
+    }
+
+    /// Responds with a JSON array of up to query.k words starting with query.prefix,
+    /// most popular first. With query.fuzzy, words starting with a typo of the prefix
+    /// are suggested after the exact matches, if the prefix is at least
+    /// MIN_FUZZY_PREFIX characters long.
+    pub fn respond_autocomplete(&self, query: AutocompleteQuery) -> String {
+        let prefix = query.prefix.trim().to_lowercase();
+        let key = format!(
+            "{prefix}|{}|{:?}|{:?}|{}",
+            query.k, query.start_year, query.end_year, query.fuzzy
+        );
+        self.cache.get_or_insert_with(Route::Autocomplete, key, || {
+            let start_year = query.start_year.unwrap_or(0);
+            let end_year = query.end_year.unwrap_or(usize::MAX);
+            let words = self.ngm.words();
+
+            let mut exact = self.vocabulary.with_prefix(&prefix);
+            exact.sort_unstable();
+            let mut fuzzy = Vec::new();
+            if query.fuzzy && prefix.chars().count() >= MIN_FUZZY_PREFIX {
+                fuzzy = self.vocabulary.with_fuzzy_prefix(&prefix);
+                fuzzy.sort_unstable();
+                fuzzy.dedup();
+                fuzzy.retain(|id| exact.binary_search(id).is_err());
+            }
+
+            // (is fuzzy, count, word), so that exact matches sort first
+            let mut candidates: Vec<(bool, f64, &str)> = exact
+                .iter()
+                .map(|&id| (false, id))
+                .chain(fuzzy.iter().map(|&id| (true, id)))
+                .map(|(is_fuzzy, id)| (is_fuzzy, 0.0, words[id as usize].as_str()))
+                .collect();
+            for (_, count, word) in &mut candidates {
+                *count = self.ngm.total_count(word, start_year, end_year);
+            }
+            candidates.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.total_cmp(&a.1)).then(a.2.cmp(b.2)));
+
+            let suggestions: Vec<&str> = candidates
+                .iter()
+                .take(query.k)
+                .map(|&(_, _, word)| word)
+                .collect();
+            json_array(&suggestions)
+        })
+    }
+
+    /// Responds with the closest common hypernym of the two words in query.words,
+    /// and the length of the path between them through it, as a JSON object like
+    /// {"words": ["a", "b"], "ancestor": ["c", "d"], "length": 3}.
+    /// If the words have no common hypernym, ancestor and length are null.
+    /// Fails with NotAPair unless there are exactly two words.
+    pub fn respond_ancestor(&self, query: PairQuery) -> Result<String, QueryError> {
+        let words = parse_words(query.words);
+        let [a, b] = &words[..] else {
+            return Err(QueryError::NotAPair(words.len()));
+        };
+        Ok(self
+            .cache
+            .get_or_insert_with(Route::Ancestor, words.join(","), || {
+                let (ancestor, length) = match self.wordnet.ancestral_path(a, b) {
+                    Some(path) => {
+                        let synset: Vec<&str> = self
+                            .wordnet
+                            .synset(path.ancestor)
+                            .iter()
+                            .map(String::as_str)
+                            .collect();
+                        (json_array(&synset), path.length().to_string())
+                    }
+                    None => ("null".to_string(), "null".to_string()),
+                };
+                format!(
+                    "{{\"words\": {}, \"ancestor\": {ancestor}, \"length\": {length}}}",
+                    json_array(&words)
+                )
+            }))
+    }
+
+    /// Responds with how similar the two words in query.words are, as a JSON object like
+    /// {"words": ["a", "b"], "length": 3, "path_similarity": 0.25, "wu_palmer": 0.6}.
+    /// path_similarity is 1 / (1 + length), and wu_palmer is 2 * depth(ancestor) divided
+    /// by the sum of the words' depths through that ancestor, where depths count synsets
+    /// from the root of the hierarchy. Both are 1 for synonyms, and 0 if the words have
+    /// no common hypernym, in which case length is null. Fails with NotAPair
+    /// unless there are exactly two words.
+    pub fn respond_distance(&self, query: PairQuery) -> Result<String, QueryError> {
+        let words = parse_words(query.words);
+        let [a, b] = &words[..] else {
+            return Err(QueryError::NotAPair(words.len()));
+        };
+        Ok(self
+            .cache
+            .get_or_insert_with(Route::Distance, words.join(","), || {
+                let (length, path_similarity, wu_palmer) =
+                    match self.wordnet.ancestral_path(a, b) {
+                        Some(path) => {
+                            let depth = self.wordnet.depth(path.ancestor) as f64;
+                            let length = path.length() as f64;
+                            (
+                                path.length().to_string(),
+                                1.0 / (1.0 + length),
+                                2.0 * depth / (length + 2.0 * depth),
+                            )
+                        }
+                        None => ("null".to_string(), 0.0, 0.0),
+                    };
+                format!(
+                    "{{\"words\": {}, \"length\": {length}, \"path_similarity\": {path_similarity:.4}, \"wu_palmer\": {wu_palmer:.4}}}",
+                    json_array(&words)
+                )
+            }))
+    }
+
+    /// Responds with the number of cached responses, and each route's cache
+    /// hit rate and average latency, as a JSON object
+    pub fn respond_stats(&self) -> String {
+        self.cache.stats_json()
+    }
+
+    /// Returns an error if none of the words in query.words appear in either data set
+    pub fn check_known_words(&self, query: &Query) -> Result<(), QueryError> {
+        let words: Vec<String> = parse_words(query.words)
+            .iter()
+            .flat_map(|entry| split_sum(entry))
+            .map(str::to_string)
+            .collect();
+        let known = |w: &String| self.ngm.contains_word(w) || self.wordnet.contains_word(w);
+        if words.iter().any(known) {
+            Ok(())
+        } else {
+            Err(QueryError::UnknownWords(words))
+        }
+    }
+
+    /// Returns an error naming the words in words (comma-separated) that are not in WordNet
+    pub fn check_wordnet_words(&self, words: &str) -> Result<(), QueryError> {
+        let unknown: Vec<String> = parse_words(words)
+            .into_iter()
+            .filter(|w| !self.wordnet.contains_word(w))
+            .collect();
+        if unknown.is_empty() {
+            Ok(())
+        } else {
+            Err(QueryError::UnknownWords(unknown))
+        }
+    }
+
+    /// If query.k is 0, returns all of words. Otherwise, returns the query.k words
+    /// that appear most often in [query.start_year, query.end_year], skipping words
+    /// that never appear. The result is sorted alphabetically.
+    fn most_popular<'a>(&self, words: BTreeSet<&'a str>, query: &Query) -> Vec<&'a str> {
+        if query.k == 0 {
+            return words.into_iter().collect();
+        }
+        let mut counted: Vec<(f64, &str)> = words
+            .into_iter()
+            .map(|w| (self.ngm.total_count(w, query.start_year, query.end_year), w))
+            .filter(|&(count, _)| count > 0.0)
+            .collect();
+        counted.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(b.1)));
+        counted.truncate(query.k);
+
+        let mut popular: Vec<&str> = counted.into_iter().map(|(_, w)| w).collect();
+        popular.sort_unstable();
+        popular
     }
 }
 
+/// Returns a string that is the same for queries that have the same response
+fn cache_key(query: &Query) -> String {
+    let words: Vec<String> = parse_words(query.words)
+        .iter()
+        .map(|entry| split_sum(entry).join("+"))
+        .collect();
+    format!(
+        "{}|{}|{}|{}",
+        words.join(","),
+        query.start_year,
+        query.end_year,
+        query.k
+    )
+}
+
+/// Splits a history entry of the form "a + b" into its words
+fn split_sum(entry: &str) -> Vec<&str> {
+    entry
+        .split('+')
+        .map(str::trim)
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
 /// Takes a list of words and their corresponding TimeSeries and
 /// outputs a JSON string, from which the frontent can create a graph
-fn json_object_string(mapping: &Vec<(String, TimeSeries)>) -> String {
+fn json_object_string(mapping: &[(String, TimeSeries)]) -> String {
     let mut json = String::from("{");
     for (k, v) in mapping {
         json += "\n  \"";
@@ -55,8 +355,17 @@ fn json_object_string(mapping: &Vec<(String, TimeSeries)>) -> String {
     json
 }
 
+/// Formats strings as a JSON array
+fn json_array(strings: &[impl AsRef<str>]) -> String {
+    let quoted: Vec<String> = strings
+        .iter()
+        .map(|s| format!("\"{}\"", json_escape(s.as_ref())))
+        .collect();
+    format!("[{}]", quoted.join(", "))
+}
+
 /// Escapes a string for use in JSON
-fn json_escape(src: &str) -> String {
+pub fn json_escape(src: &str) -> String {
     let mut escaped = String::with_capacity(src.len());
     let mut utf16_buf = [0u16; 2];
     for c in src.chars() {
@@ -67,7 +376,7 @@ fn json_escape(src: &str) -> String {
             '\r' => escaped += "\\r",
             '\t' => escaped += "\\t",
             '"' => escaped += "\\\"",
-            '\\' => escaped += "\\",
+            '\\' => escaped += "\\\\",
             ' ' => escaped += " ",
             c if c.is_ascii_graphic() => escaped.push(c),
             c => {
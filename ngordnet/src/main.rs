@@ -1,12 +1,28 @@
+mod cache;
+mod cli;
+mod datafile;
+mod graph;
 mod handler;
 mod ngrammap;
+mod query;
 #[cfg(test)]
 mod tests;
 mod timeseries;
-mod dummy;
+mod trie;
+mod wordnet;
 
+use clap::Parser;
+use cli::{Cli, Command, DataFiles};
 use handler::NGordNetHandler;
-use rocket::{fs::FileServer, State};
+use query::{validate_smoothing, QueryError};
+use rocket::{
+    form,
+    fs::FileServer,
+    http::Status,
+    response::content::{RawJson, RawText},
+    Build, Request, Rocket, State,
+};
+use std::io::{self, IsTerminal};
 
 #[macro_use]
 extern crate rocket;
@@ -21,36 +37,218 @@ pub struct Query<'r> {
     k: usize,
 }
 
-#[get("/history?<query..>")]
-fn history(query: Query, handler: &State<NGordNetHandler>) -> String {
-    handler.respond_history(query)
+/// Parses and validates a query, so that the handler only sees answerable queries
+fn check<'r>(
+    query: form::Result<'_, Query<'r>>,
+    handler: &NGordNetHandler,
+) -> Result<Query<'r>, QueryError> {
+    let query = query?;
+    query.validate()?;
+    handler.check_known_words(&query)?;
+    Ok(query)
+}
+
+#[derive(FromForm, Debug, Clone)]
+pub struct AutocompleteQuery<'r> {
+    prefix: &'r str,
+    #[field(default = 10)]
+    k: usize,
+    /// suggestions are ranked by their total count in [start_year, end_year]
+    #[field(name = "start-year")]
+    start_year: Option<usize>,
+    #[field(name = "end-year")]
+    end_year: Option<usize>,
+    /// also suggest words whose prefix is one edit away from prefix
+    fuzzy: bool,
+}
+
+/// A query about how two words are related
+#[derive(FromForm, Debug, Clone)]
+pub struct PairQuery<'r> {
+    words: &'r str,
+}
+
+/// Parses and validates a pair query, so that the handler only sees answerable queries
+fn check_pair<'r>(
+    query: form::Result<'_, PairQuery<'r>>,
+    handler: &NGordNetHandler,
+) -> Result<PairQuery<'r>, QueryError> {
+    let query = query?;
+    query.validate()?;
+    handler.check_wordnet_words(query.words)?;
+    Ok(query)
+}
+
+#[get("/history?<smoothing>&<query..>")]
+fn history(
+    query: form::Result<Query>,
+    smoothing: Option<usize>,
+    handler: &State<NGordNetHandler>,
+) -> Result<RawJson<String>, QueryError> {
+    let query = check(query, handler)?;
+    Ok(RawJson(match smoothing {
+        Some(smoothing) => {
+            validate_smoothing(smoothing)?;
+            handler.respond_smoothed_history(query, smoothing)
+        }
+        None => handler.respond_history(query),
+    }))
+}
+
+#[get("/historytext?<smoothing>&<query..>")]
+fn history_text(
+    query: form::Result<Query>,
+    smoothing: Option<usize>,
+    handler: &State<NGordNetHandler>,
+) -> Result<RawText<String>, QueryError> {
+    let query = check(query, handler)?;
+    let smoothing = smoothing.unwrap_or(1);
+    validate_smoothing(smoothing)?;
+    Ok(RawText(handler.respond_history_text(query, smoothing)))
 }
 
 #[get("/synonyms?<query..>")]
-fn synonyms(query: Query, handler: &State<NGordNetHandler>) -> String {
-    handler.respond_synonyms(query)
+fn synonyms(
+    query: form::Result<Query>,
+    handler: &State<NGordNetHandler>,
+) -> Result<RawText<String>, QueryError> {
+    let query = check(query, handler)?;
+    Ok(RawText(handler.respond_synonyms(query)))
 }
 
 #[get("/hyponyms?<query..>")]
-fn hyponyms(query: Query, handler: &State<NGordNetHandler>) -> String {
-    handler.respond_hyponyms(query)
+fn hyponyms(
+    query: form::Result<Query>,
+    handler: &State<NGordNetHandler>,
+) -> Result<RawText<String>, QueryError> {
+    let query = check(query, handler)?;
+    Ok(RawText(handler.respond_hyponyms(query)))
 }
 
-#[launch]
-fn rocket() -> _ {
-    let time = std::time::Instant::now();
-    let handler = NGordNetHandler::new(
-        "./data/ngrams/top_49887_words.csv",
-        "./data/ngrams/total_counts.csv",
-        "./data/wordnet/synsets.txt",
-        "./data/wordnet/hyponyms.txt",
-    )
-    .expect("Error when creating handler!");
-    let duration = time.elapsed().as_secs_f64();
-    println!("Finished constructing handler in {duration} seconds");
+#[get("/hypernyms?<query..>")]
+fn hypernyms(
+    query: form::Result<Query>,
+    handler: &State<NGordNetHandler>,
+) -> Result<RawText<String>, QueryError> {
+    let query = check(query, handler)?;
+    Ok(RawText(handler.respond_hypernyms(query)))
+}
+
+#[get("/autocomplete?<query..>")]
+fn autocomplete(
+    query: form::Result<AutocompleteQuery>,
+    handler: &State<NGordNetHandler>,
+) -> Result<RawJson<String>, QueryError> {
+    let query = query?;
+    query.validate()?;
+    Ok(RawJson(handler.respond_autocomplete(query)))
+}
+
+#[get("/ancestor?<query..>")]
+fn ancestor(
+    query: form::Result<PairQuery>,
+    handler: &State<NGordNetHandler>,
+) -> Result<RawJson<String>, QueryError> {
+    let query = check_pair(query, handler)?;
+    Ok(RawJson(handler.respond_ancestor(query)?))
+}
+
+#[get("/distance?<query..>")]
+fn distance(
+    query: form::Result<PairQuery>,
+    handler: &State<NGordNetHandler>,
+) -> Result<RawJson<String>, QueryError> {
+    let query = check_pair(query, handler)?;
+    Ok(RawJson(handler.respond_distance(query)?))
+}
+
+#[get("/stats")]
+fn stats(handler: &State<NGordNetHandler>) -> RawJson<String> {
+    RawJson(handler.respond_stats())
+}
 
+/// Responds to unknown routes and other errors with JSON, like QueryError does
+#[catch(default)]
+fn default_catcher(status: Status, _request: &Request) -> (Status, RawJson<String>) {
+    let reason = status.reason().unwrap_or("unknown error");
+    let json = format!(
+        "{{\"status\": {}, \"error\": \"{}\"}}",
+        status.code,
+        handler::json_escape(reason)
+    );
+    (status, RawJson(json))
+}
+
+fn rocket(handler: NGordNetHandler) -> Rocket<Build> {
     rocket::build()
         .mount("/", FileServer::from("public"))
-        .mount("/", routes![history, synonyms, hyponyms])
+        .mount(
+            "/",
+            routes![
+                history,
+                history_text,
+                synonyms,
+                hyponyms,
+                hypernyms,
+                autocomplete,
+                ancestor,
+                distance,
+                stats
+            ],
+        )
+        .register("/", catchers![default_catcher])
         .manage(handler)
 }
+
+fn load_handler(data: &DataFiles) -> NGordNetHandler {
+    match NGordNetHandler::new(
+        &data.words_file,
+        &data.counts_file,
+        &data.synset_file,
+        &data.hyponym_file,
+        data.cache_file.as_deref(),
+    ) {
+        Ok(handler) => handler,
+        Err(e) => {
+            eprintln!("Error when creating handler: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let time = std::time::Instant::now();
+    let handler = load_handler(&cli.data);
+
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => {
+            let duration = time.elapsed().as_secs_f64();
+            println!("Finished constructing handler in {duration} seconds");
+            if let Err(e) = rocket::execute(rocket(handler).launch()) {
+                eprintln!("Error when launching server: {e}");
+                std::process::exit(1);
+            }
+        }
+        Command::Query(args) => match cli::respond(&handler, &args) {
+            Ok(response) => {
+                print!("{response}");
+                if !response.ends_with('\n') {
+                    println!();
+                }
+            }
+            Err(e) => {
+                eprintln!("error: {e}");
+                std::process::exit(1);
+            }
+        },
+        Command::Repl => {
+            let stdin = io::stdin();
+            let prompt = stdin.is_terminal();
+            if let Err(e) = cli::repl(&handler, stdin.lock(), io::stdout().lock(), prompt) {
+                eprintln!("Error in repl: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+}
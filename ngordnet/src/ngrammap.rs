@@ -1,35 +1,400 @@
-use std::{collections::HashMap, io::BufRead};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
-use crate::timeseries::TimeSeries;
+use crate::{
+    datafile::{malformed, open},
+    timeseries::TimeSeries,
+};
 
+const CACHE_MAGIC: &[u8; 8] = b"NGMCACH2";
+
+/*
+An NGramMap stores its counts in a columnar layout. Every word is interned
+to an id, and the entries for word `id` are the ranges
+
+    years[offsets[id]..offsets[id + 1]]
+    counts[offsets[id]..offsets[id + 1]]
+
+sorted by year. Compared to a HashMap<String, TimeSeries>, this uses a handful
+of large allocations instead of one BTreeMap node per (word, year) pair, and
+range queries are a binary search followed by a linear scan.
+
+The total counts are stored densely, indexed by (year - first_year). Years with
+no total count are stored as 0.0 and treated as missing.
+
+Parsing the csv files is slow for the big data sets, so the whole structure can
+be written to a binary cache file (see save_cache and open_cached), which loads
+in a fraction of the time. The cache starts with the paths of the words and
+counts files it was made from, so that open_cached doesn't load a cache of
+other files.
+*/
 pub struct NGramMap {
-    // add any struct fields you need
+    ids: HashMap<String, u32>,
+    words: Vec<String>,
+    offsets: Vec<u32>,
+    years: Vec<u16>,
+    counts: Vec<f64>,
+    first_year: u16,
+    totals: Vec<f64>,
+    /// the words file and the counts file this was read from
+    sources: [String; 2],
 }
 
 impl NGramMap {
     pub fn new(words_file: &str, counts_file: &str) -> Result<Self, std::io::Error> {
-        todo!()
+        let (first_year, totals) = read_totals(counts_file)?;
+
+        let mut ids: HashMap<String, u32> = HashMap::new();
+        let mut words = Vec::new();
+        let mut entries: Vec<(u32, u16, f64)> = Vec::new();
+        for (line_num, line) in BufReader::new(open(words_file)?).lines().enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let mut fields = line.split('\t');
+            let (Some(word), Some(year), Some(count)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(malformed(words_file, line_num));
+            };
+            let year = parse_year(year).ok_or_else(|| malformed(words_file, line_num))?;
+            let count: f64 = count.parse().map_err(|_| malformed(words_file, line_num))?;
+
+            let id = match ids.get(word) {
+                Some(&id) => id,
+                None => {
+                    let id = words.len() as u32;
+                    ids.insert(word.to_string(), id);
+                    words.push(word.to_string());
+                    id
+                }
+            };
+            entries.push((id, year, count));
+        }
+        // the data files are already grouped by word and sorted by year,
+        // so this sort is close to linear
+        entries.sort_by_key(|&(id, year, _)| (id, year));
+
+        let mut offsets = Vec::with_capacity(words.len() + 1);
+        let mut years = Vec::with_capacity(entries.len());
+        let mut counts = Vec::with_capacity(entries.len());
+        for (id, year, count) in entries {
+            while offsets.len() <= id as usize {
+                offsets.push(years.len() as u32);
+            }
+            years.push(year);
+            counts.push(count);
+        }
+        while offsets.len() <= words.len() {
+            offsets.push(years.len() as u32);
+        }
+
+        Ok(Self {
+            ids,
+            words,
+            offsets,
+            years,
+            counts,
+            first_year,
+            totals,
+            sources: [words_file.to_string(), counts_file.to_string()],
+        })
+    }
+
+    /// Loads the NGramMap from cache_file if it exists, was made from the same
+    /// data files, and is newer than both of them.
+    /// Otherwise, parses the data files and tries to write a new cache_file for next time.
+    pub fn open_cached(
+        words_file: &str,
+        counts_file: &str,
+        cache_file: &str,
+    ) -> Result<Self, std::io::Error> {
+        if is_fresh(cache_file, &[words_file, counts_file]) {
+            match Self::load_cache(cache_file) {
+                Ok(ngm) if ngm.sources == [words_file, counts_file] => return Ok(ngm),
+                _ => {}
+            }
+        }
+        let ngm = Self::new(words_file, counts_file)?;
+        if let Err(e) = ngm.save_cache(cache_file) {
+            eprintln!("Could not write ngram cache {cache_file}: {e}");
+        }
+        Ok(ngm)
+    }
+
+    /// Writes this NGramMap to a binary cache file
+    pub fn save_cache(&self, cache_file: &str) -> Result<(), std::io::Error> {
+        // write to a temporary file first, so that concurrent readers never see half a cache
+        static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let tmp_file = format!(
+            "{cache_file}.tmp{}-{}",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let result = (|| {
+            let mut w = BufWriter::new(File::create(&tmp_file)?);
+            w.write_all(CACHE_MAGIC)?;
+            for source in &self.sources {
+                write_len(&mut w, source.len())?;
+                w.write_all(source.as_bytes())?;
+            }
+            w.write_all(&self.first_year.to_le_bytes())?;
+            write_len(&mut w, self.totals.len())?;
+            for total in &self.totals {
+                w.write_all(&total.to_le_bytes())?;
+            }
+            write_len(&mut w, self.words.len())?;
+            for word in &self.words {
+                write_len(&mut w, word.len())?;
+                w.write_all(word.as_bytes())?;
+            }
+            for offset in &self.offsets {
+                w.write_all(&offset.to_le_bytes())?;
+            }
+            write_len(&mut w, self.years.len())?;
+            for year in &self.years {
+                w.write_all(&year.to_le_bytes())?;
+            }
+            for count in &self.counts {
+                w.write_all(&count.to_le_bytes())?;
+            }
+            w.flush()
+        })();
+
+        match result {
+            Ok(()) => std::fs::rename(&tmp_file, cache_file),
+            Err(e) => {
+                let _ = std::fs::remove_file(&tmp_file);
+                Err(e)
+            }
+        }
+    }
+
+    /// Reads an NGramMap from a binary cache file created by save_cache. Fails with
+    /// InvalidData if the file is corrupt, rather than panicking later.
+    pub fn load_cache(cache_file: &str) -> Result<Self, std::io::Error> {
+        let bytes = std::fs::read(cache_file)?;
+        let mut r = CacheReader { bytes: &bytes };
+        if r.take(CACHE_MAGIC.len())? != CACHE_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{cache_file} is not an ngram cache file"),
+            ));
+        }
+
+        let sources = [r.read_str()?.to_string(), r.read_str()?.to_string()];
+        let first_year = u16::from_le_bytes(r.array()?);
+        let num_totals = r.read_len()?;
+        let totals = r.read_vec(num_totals, f64::from_le_bytes)?;
+
+        let num_words = r.read_len()?;
+        // every word takes at least 8 bytes, which bounds the allocation for corrupt files
+        let capacity = num_words.min(r.bytes.len() / 8);
+        let mut words = Vec::with_capacity(capacity);
+        let mut ids = HashMap::with_capacity(capacity);
+        for id in 0..num_words {
+            let word = r.read_str()?;
+            ids.insert(word.to_string(), id as u32);
+            words.push(word.to_string());
+        }
+        let offsets = r.read_vec(num_words + 1, u32::from_le_bytes)?;
+
+        let num_entries = r.read_len()?;
+        let years = r.read_vec(num_entries, u16::from_le_bytes)?;
+        let counts = r.read_vec(num_entries, f64::from_le_bytes)?;
+
+        // entries slices years and counts with the offsets, so they have to
+        // go up from 0 to the number of entries
+        if offsets[0] != 0
+            || offsets.windows(2).any(|w| w[0] > w[1])
+            || offsets[num_words] as usize != num_entries
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{cache_file} has invalid offsets"),
+            ));
+        }
+
+        Ok(Self {
+            ids,
+            words,
+            offsets,
+            years,
+            counts,
+            first_year,
+            totals,
+            sources,
+        })
+    }
+
+    /// Returns every word in the data. A word's index in this slice is its id.
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+
+    /// Returns true if word appears in the data
+    pub fn contains_word(&self, word: &str) -> bool {
+        self.ids.contains_key(word)
     }
 
     /// Returns total the number of times word appeared in the range [start_year, end_year]
     /// If word does not appear in the data, returns 0
     pub fn total_count(&self, word: &str, start_year: usize, end_year: usize) -> f64 {
-        todo!()
+        let (_, counts) = self.entries(word, start_year, end_year);
+        counts.iter().sum()
     }
 
     /// Returns the history of the word, divided by the total counts for each year
     /// If word does not appear in the data, returns an empty TimeSeries
     pub fn weight_history(&self, word: &str, start_year: usize, end_year: usize) -> TimeSeries {
-        todo!()
+        let mut history = TimeSeries::new();
+        let (years, counts) = self.entries(word, start_year, end_year);
+        for (&year, &count) in years.iter().zip(counts) {
+            if let Some(total) = self.year_total(year) {
+                history.data.insert(year as usize, count / total);
+            }
+        }
+        history
     }
 
     /// Returns the sums of the weighted histories of the given words
     pub fn summed_weight_history(
         &self,
-        words: &Vec<&str>,
+        words: &[&str],
         start_year: usize,
         end_year: usize,
     ) -> TimeSeries {
-        todo!()
+        let mut sum = TimeSeries::new();
+        for word in words {
+            sum += &self.weight_history(word, start_year, end_year);
+        }
+        sum
+    }
+
+    /// Returns the (years, counts) columns for word, restricted to [start_year, end_year]
+    fn entries(&self, word: &str, start_year: usize, end_year: usize) -> (&[u16], &[f64]) {
+        let Some(&id) = self.ids.get(word) else {
+            return (&[], &[]);
+        };
+        let lo = self.offsets[id as usize] as usize;
+        let hi = self.offsets[id as usize + 1] as usize;
+        let years = &self.years[lo..hi];
+        let start = years.partition_point(|&y| (y as usize) < start_year);
+        let end = years.partition_point(|&y| (y as usize) <= end_year);
+        if start >= end {
+            return (&[], &[]);
+        }
+        (&years[start..end], &self.counts[lo + start..lo + end])
+    }
+
+    fn year_total(&self, year: u16) -> Option<f64> {
+        let index = year.checked_sub(self.first_year)?;
+        match self.totals.get(index as usize) {
+            Some(&total) if total > 0.0 => Some(total),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the total counts file into (first_year, dense totals)
+fn read_totals(counts_file: &str) -> Result<(u16, Vec<f64>), std::io::Error> {
+    let mut yearly = Vec::new();
+    for (line_num, line) in BufReader::new(open(counts_file)?).lines().enumerate() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let mut fields = line.split(',');
+        let (Some(year), Some(count)) = (fields.next(), fields.next()) else {
+            return Err(malformed(counts_file, line_num));
+        };
+        let year = parse_year(year).ok_or_else(|| malformed(counts_file, line_num))?;
+        let count: f64 = count
+            .parse()
+            .map_err(|_| malformed(counts_file, line_num))?;
+        yearly.push((year, count));
+    }
+
+    let Some(first_year) = yearly.iter().map(|&(y, _)| y).min() else {
+        return Ok((0, Vec::new()));
+    };
+    let last_year = yearly.iter().map(|&(y, _)| y).max().unwrap();
+    let mut totals = vec![0.0; (last_year - first_year) as usize + 1];
+    for (year, count) in yearly {
+        totals[(year - first_year) as usize] += count;
+    }
+    Ok((first_year, totals))
+}
+
+fn parse_year(s: &str) -> Option<u16> {
+    s.trim().parse().ok()
+}
+
+/// Returns true if cache_file exists and was modified after all of the sources
+fn is_fresh(cache_file: &str, sources: &[&str]) -> bool {
+    let modified = |file: &str| Path::new(file).metadata().and_then(|m| m.modified()).ok();
+    let Some(cache_time) = modified(cache_file) else {
+        return false;
+    };
+    sources
+        .iter()
+        .all(|&s| modified(s).is_some_and(|t| t <= cache_time))
+}
+
+fn write_len(w: &mut impl Write, len: usize) -> Result<(), std::io::Error> {
+    w.write_all(&(len as u64).to_le_bytes())
+}
+
+struct CacheReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> CacheReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], std::io::Error> {
+        if n > self.bytes.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "ngram cache file is truncated",
+            ));
+        }
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], std::io::Error> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn read_len(&mut self) -> Result<usize, std::io::Error> {
+        Ok(u64::from_le_bytes(self.array()?) as usize)
+    }
+
+    /// Reads a string written as its length followed by its bytes
+    fn read_str(&mut self) -> Result<&'a str, std::io::Error> {
+        let len = self.read_len()?;
+        std::str::from_utf8(self.take(len)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Reads len values of N bytes each. The bytes are all taken first, so a
+    /// corrupt len fails instead of allocating more than the file holds.
+    fn read_vec<const N: usize, T>(
+        &mut self,
+        len: usize,
+        decode: fn([u8; N]) -> T,
+    ) -> Result<Vec<T>, std::io::Error> {
+        let bytes = self.take(len.saturating_mul(N))?;
+        Ok(bytes
+            .chunks_exact(N)
+            .map(|chunk| decode(chunk.try_into().unwrap()))
+            .collect())
     }
 }
@@ -0,0 +1,160 @@
+use std::fmt;
+
+use rocket::{
+    form,
+    http::Status,
+    response::{self, content::RawJson, Responder},
+    Request,
+};
+
+use crate::{handler::json_escape, AutocompleteQuery, PairQuery, Query};
+
+/// Largest k that a query may ask for
+pub const MAX_K: usize = 1000;
+/// Largest number of years that a history may be smoothed over
+pub const MAX_SMOOTHING: usize = 100;
+
+/// Reasons why a query can't be answered. Rocket responds with these as
+/// JSON objects of the form {"status": 400, "error": "..."}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    /// the query string could not be parsed into a Query
+    Malformed(String),
+    NoWords,
+    /// the query needs exactly two words
+    NotAPair(usize),
+    YearRange {
+        start_year: usize,
+        end_year: usize,
+    },
+    KTooLarge(usize),
+    SmoothingTooLarge(usize),
+    /// none of the words appear in the data
+    UnknownWords(Vec<String>),
+}
+
+impl QueryError {
+    pub fn status(&self) -> Status {
+        match self {
+            QueryError::UnknownWords(_) => Status::NotFound,
+            _ => Status::BadRequest,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"status\": {}, \"error\": \"{}\"}}",
+            self.status().code,
+            json_escape(&self.to_string())
+        )
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::Malformed(reason) => write!(f, "malformed query: {reason}"),
+            QueryError::NoWords => write!(f, "no words were given"),
+            QueryError::NotAPair(n) => write!(f, "expected 2 different words, but got {n}"),
+            QueryError::YearRange {
+                start_year,
+                end_year,
+            } => write!(f, "start year {start_year} is after end year {end_year}"),
+            QueryError::KTooLarge(k) => write!(f, "k is {k}, but can be at most {MAX_K}"),
+            QueryError::SmoothingTooLarge(smoothing) => write!(
+                f,
+                "smoothing is {smoothing}, but can be at most {MAX_SMOOTHING}"
+            ),
+            QueryError::UnknownWords(words) => {
+                write!(f, "no data for {}", words.join(", "))
+            }
+        }
+    }
+}
+
+impl From<form::Errors<'_>> for QueryError {
+    fn from(errors: form::Errors<'_>) -> Self {
+        let reasons: Vec<String> = errors
+            .iter()
+            .map(|e| match e.name.as_ref() {
+                Some(name) => format!("{name}: {}", e.kind),
+                None => e.kind.to_string(),
+            })
+            .collect();
+        QueryError::Malformed(reasons.join("; "))
+    }
+}
+
+impl<'r> Responder<'r, 'static> for QueryError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        (self.status(), RawJson(self.to_json())).respond_to(request)
+    }
+}
+
+impl Query<'_> {
+    /// Checks that the query is answerable, without looking at the data
+    pub fn validate(&self) -> Result<(), QueryError> {
+        if parse_words(self.words).is_empty() {
+            return Err(QueryError::NoWords);
+        }
+        if self.start_year > self.end_year {
+            return Err(QueryError::YearRange {
+                start_year: self.start_year,
+                end_year: self.end_year,
+            });
+        }
+        if self.k > MAX_K {
+            return Err(QueryError::KTooLarge(self.k));
+        }
+        Ok(())
+    }
+}
+
+impl AutocompleteQuery<'_> {
+    /// Checks that the query is answerable, without looking at the data
+    pub fn validate(&self) -> Result<(), QueryError> {
+        if let (Some(start_year), Some(end_year)) = (self.start_year, self.end_year) {
+            if start_year > end_year {
+                return Err(QueryError::YearRange {
+                    start_year,
+                    end_year,
+                });
+            }
+        }
+        if self.k > MAX_K {
+            return Err(QueryError::KTooLarge(self.k));
+        }
+        Ok(())
+    }
+}
+
+impl PairQuery<'_> {
+    /// Checks that the query names two different words
+    pub fn validate(&self) -> Result<(), QueryError> {
+        match parse_words(self.words).len() {
+            2 => Ok(()),
+            n => Err(QueryError::NotAPair(n)),
+        }
+    }
+}
+
+/// Checks that a smoothing window is answerable
+pub fn validate_smoothing(smoothing: usize) -> Result<(), QueryError> {
+    if smoothing > MAX_SMOOTHING {
+        return Err(QueryError::SmoothingTooLarge(smoothing));
+    }
+    Ok(())
+}
+
+/// Splits a comma-separated list of words, trimming and lowercasing each word.
+/// Empty and repeated words are removed, otherwise the order is kept.
+pub fn parse_words(words: &str) -> Vec<String> {
+    let mut parsed: Vec<String> = Vec::new();
+    for word in words.split(',') {
+        let word = word.trim().to_lowercase();
+        if !word.is_empty() && !parsed.contains(&word) {
+            parsed.push(word);
+        }
+    }
+    parsed
+}
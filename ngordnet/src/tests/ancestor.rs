@@ -0,0 +1,254 @@
+use crate::{
+    graph::{AncestralPath, Digraph},
+    handler::NGordNetHandler,
+    query::QueryError,
+    PairQuery,
+};
+
+fn handler() -> NGordNetHandler {
+    NGordNetHandler::new(
+        "data/ngrams/very_short.csv",
+        "data/ngrams/total_counts.csv",
+        "data/wordnet/synsets16.txt",
+        "data/wordnet/hyponyms16.txt",
+        None,
+    )
+    .expect("error loading files")
+}
+
+/// The distance from starts to every vertex, found without any early stopping
+fn distances(graph: &Digraph, starts: &[usize]) -> Vec<Option<usize>> {
+    let mut dist = vec![None; graph.len()];
+    let mut frontier = starts.to_vec();
+    for &s in starts {
+        dist[s] = Some(0);
+    }
+    let mut level = 0;
+    while !frontier.is_empty() {
+        level += 1;
+        let mut next = Vec::new();
+        for v in frontier {
+            for &w in graph.neighbors(v) {
+                if dist[w].is_none() {
+                    dist[w] = Some(level);
+                    next.push(w);
+                }
+            }
+        }
+        frontier = next;
+    }
+    dist
+}
+
+#[test]
+fn test_shortest_ancestral_path() {
+    // 0 -> 1 -> 2 -> 3 -> 4
+    //           5 -> 3
+    //      6 -> 7 -> 4
+    let mut graph = Digraph::new(0);
+    for (from, to) in [(0, 1), (1, 2), (2, 3), (3, 4), (5, 3), (6, 7), (7, 4)] {
+        graph.add_edge(from, to);
+    }
+    let sap = |a: &[usize], b: &[usize]| {
+        graph.shortest_ancestral_path(a.iter().copied(), b.iter().copied())
+    };
+    assert_eq!(
+        sap(&[0], &[5]),
+        Some(AncestralPath {
+            ancestor: 3,
+            from_a: 3,
+            from_b: 1
+        })
+    );
+    assert_eq!(
+        sap(&[0], &[6]).map(|p| (p.ancestor, p.length())),
+        Some((4, 6))
+    );
+    assert_eq!(
+        sap(&[2], &[2]).map(|p| (p.ancestor, p.length())),
+        Some((2, 0))
+    );
+    assert_eq!(
+        sap(&[0], &[3]).map(|p| (p.ancestor, p.length())),
+        Some((3, 3))
+    );
+    // the closest vertex in each set is used
+    assert_eq!(
+        sap(&[0, 6], &[5, 2]).map(|p| (p.ancestor, p.length())),
+        Some((2, 2))
+    );
+    assert_eq!(
+        sap(&[4], &[0]).map(|p| (p.ancestor, p.length())),
+        Some((4, 4))
+    );
+    assert_eq!(sap(&[], &[0]), None);
+
+    let mut forest = graph.clone();
+    forest.add_edge(8, 9);
+    assert_eq!(forest.shortest_ancestral_path([0], [8]), None);
+}
+
+#[test]
+fn test_shortest_ancestral_path_ties() {
+    // 0 and 1 both have 2 and 3 as parents
+    let mut graph = Digraph::new(0);
+    for (from, to) in [(0, 3), (0, 2), (1, 3), (1, 2)] {
+        graph.add_edge(from, to);
+    }
+    let path = graph.shortest_ancestral_path([0], [1]).unwrap();
+    assert_eq!((path.ancestor, path.length()), (2, 2));
+}
+
+/// Compares the bidirectional search against full bfs from both sides,
+/// on pseudorandom graphs with many paths of different lengths
+#[test]
+fn test_shortest_ancestral_path_matches_full_bfs() {
+    let mut seed: u64 = 61;
+    let mut next = |n: u64| {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((seed >> 33) % n) as usize
+    };
+    for _ in 0..50 {
+        let n = 2 + next(40);
+        let mut graph = Digraph::new(n);
+        for _ in 0..next(3 * n as u64) {
+            // edges only go to higher vertices, like a hypernym dag
+            let from = next(n as u64 - 1);
+            let to = from + 1 + next((n - from - 1) as u64);
+            graph.add_edge(from, to);
+        }
+        for _ in 0..20 {
+            let a: Vec<usize> = (0..1 + next(3)).map(|_| next(n as u64)).collect();
+            let b: Vec<usize> = (0..1 + next(3)).map(|_| next(n as u64)).collect();
+            let (dist_a, dist_b) = (distances(&graph, &a), distances(&graph, &b));
+            let expected = (0..n)
+                .filter_map(|v| Some((dist_a[v]? + dist_b[v]?, v)))
+                .min();
+            let found = graph
+                .shortest_ancestral_path(a.iter().copied(), b.iter().copied())
+                .map(|p| (p.length(), p.ancestor));
+            assert_eq!(found, expected, "a = {a:?}, b = {b:?}, graph = {graph:?}");
+            if let Some(p) = graph.shortest_ancestral_path(a.iter().copied(), b.iter().copied()) {
+                assert_eq!(Some(p.from_a), dist_a[p.ancestor]);
+                assert_eq!(Some(p.from_b), dist_b[p.ancestor]);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_distance_to_sink() {
+    let mut graph = Digraph::new(0);
+    for (from, to) in [(0, 1), (1, 2), (0, 3), (3, 4), (4, 5)] {
+        graph.add_edge(from, to);
+    }
+    assert_eq!(graph.distance_to_sink(0), Some(2));
+    assert_eq!(graph.distance_to_sink(3), Some(2));
+    assert_eq!(graph.distance_to_sink(5), Some(0));
+    graph.add_edge(5, 4);
+    assert_eq!(graph.distance_to_sink(3), None);
+}
+
+#[test]
+fn test_ancestor() {
+    let handler = handler();
+    assert_eq!(
+        handler
+            .respond_ancestor(PairQuery {
+                words: "demotion, variation"
+            })
+            .unwrap(),
+        r#"{"words": ["demotion", "variation"], "ancestor": ["change"], "length": 2}"#
+    );
+    assert_eq!(
+        handler
+            .respond_ancestor(PairQuery {
+                words: "jump,CHANGE"
+            })
+            .unwrap(),
+        r#"{"words": ["jump", "change"], "ancestor": ["alteration", "modification", "change"], "length": 2}"#
+    );
+    assert_eq!(
+        handler
+            .respond_ancestor(PairQuery {
+                words: "demotion, jump"
+            })
+            .unwrap(),
+        r#"{"words": ["demotion", "jump"], "ancestor": null, "length": null}"#
+    );
+}
+
+#[test]
+fn test_distance() {
+    let handler = handler();
+    assert_eq!(
+        handler
+            .respond_distance(PairQuery {
+                words: "demotion, variation"
+            })
+            .unwrap(),
+        r#"{"words": ["demotion", "variation"], "length": 2, "path_similarity": 0.3333, "wu_palmer": 0.7500}"#
+    );
+    // synonyms share a synset
+    assert_eq!(
+        handler
+            .respond_distance(PairQuery {
+                words: "jump, leap"
+            })
+            .unwrap(),
+        r#"{"words": ["jump", "leap"], "length": 0, "path_similarity": 1.0000, "wu_palmer": 1.0000}"#
+    );
+    assert_eq!(
+        handler
+            .respond_distance(PairQuery {
+                words: "demotion, jump"
+            })
+            .unwrap(),
+        r#"{"words": ["demotion", "jump"], "length": null, "path_similarity": 0.0000, "wu_palmer": 0.0000}"#
+    );
+}
+
+#[test]
+fn test_pair_needs_two_words() {
+    let handler = handler();
+    for words in ["jump", "jump, leap, change"] {
+        assert!(matches!(
+            handler.respond_distance(PairQuery { words }),
+            Err(QueryError::NotAPair(_))
+        ));
+        assert!(matches!(
+            handler.respond_ancestor(PairQuery { words }),
+            Err(QueryError::NotAPair(_))
+        ));
+    }
+}
+
+#[test]
+fn test_pair_errors() {
+    let handler = handler();
+    assert_eq!(
+        PairQuery { words: "act" }.validate(),
+        Err(QueryError::NotAPair(1))
+    );
+    assert_eq!(
+        PairQuery { words: "act, act" }.validate(),
+        Err(QueryError::NotAPair(1))
+    );
+    assert_eq!(
+        PairQuery {
+            words: "act, change, jump"
+        }
+        .validate(),
+        Err(QueryError::NotAPair(3))
+    );
+    assert_eq!(PairQuery { words: "act, jump" }.validate(), Ok(()));
+    assert_eq!(
+        handler.check_wordnet_words("act, wombat, airport"),
+        Err(QueryError::UnknownWords(vec![
+            "wombat".to_string(),
+            "airport".to_string()
+        ]))
+    );
+}
@@ -0,0 +1,125 @@
+use std::cell::Cell;
+
+use crate::{
+    cache::{LruCache, ResponseCache, Route},
+    handler::NGordNetHandler,
+    tests::new_query,
+};
+
+#[test]
+fn test_lru_eviction() {
+    let mut lru = LruCache::new(2);
+    lru.insert("a", 1);
+    lru.insert("b", 2);
+    assert_eq!(lru.get(&"a"), Some(&1));
+    // b is now the least recently used
+    lru.insert("c", 3);
+    assert_eq!(lru.len(), 2);
+    assert_eq!(lru.get(&"b"), None);
+    assert_eq!(lru.get(&"a"), Some(&1));
+    assert_eq!(lru.get(&"c"), Some(&3));
+
+    // replacing a value also makes it the most recently used
+    lru.insert("a", 10);
+    lru.insert("d", 4);
+    assert_eq!(lru.get(&"c"), None);
+    assert_eq!(lru.get(&"a"), Some(&10));
+    assert_eq!(lru.get(&"d"), Some(&4));
+    assert_eq!(lru.capacity(), 2);
+}
+
+#[test]
+fn test_lru_capacity_one() {
+    let mut lru = LruCache::new(0);
+    assert_eq!(lru.capacity(), 1);
+    for i in 0..10 {
+        lru.insert(i, i * i);
+        assert_eq!(lru.get(&i), Some(&(i * i)));
+        assert_eq!(lru.len(), 1);
+    }
+}
+
+#[test]
+fn test_response_cache_stats() {
+    let cache = ResponseCache::new(10);
+    let computed = Cell::new(0);
+    let compute = || {
+        computed.set(computed.get() + 1);
+        "response".to_string()
+    };
+    for _ in 0..4 {
+        assert_eq!(
+            cache.get_or_insert_with(Route::Synonyms, "key".to_string(), compute),
+            "response"
+        );
+    }
+    cache.get_or_insert_with(Route::Hyponyms, "key".to_string(), compute);
+
+    assert_eq!(computed.get(), 2);
+    let stats = cache.route_stats(Route::Synonyms);
+    assert_eq!((stats.requests, stats.hits), (4, 3));
+    assert_eq!(stats.hit_rate(), 0.75);
+    assert_eq!(cache.route_stats(Route::History).requests, 0);
+    assert!(cache
+        .stats_json()
+        .contains("\"synonyms\": {\"requests\": 4, \"hits\": 3, \"hit_rate\": 0.750"));
+    assert!(cache.stats_json().contains("\"entries\": 2,"));
+}
+
+#[test]
+fn test_handler_caches_normalized_queries() {
+    let handler = NGordNetHandler::new(
+        "data/ngrams/very_short.csv",
+        "data/ngrams/total_counts.csv",
+        "data/wordnet/synsets16.txt",
+        "data/wordnet/hyponyms16.txt",
+        None,
+    )
+    .expect("error loading files");
+
+    let first = handler.respond_hyponyms(new_query("act", 0, 0, 0));
+    let second = handler.respond_hyponyms(new_query(" ACT ,act", 0, 0, 0));
+    assert_eq!(first, second);
+    // a different k is a different query
+    handler.respond_hyponyms(new_query("act", 0, 0, 1));
+    assert!(handler
+        .respond_stats()
+        .contains("\"hyponyms\": {\"requests\": 3, \"hits\": 1,"));
+}
+
+#[test]
+fn test_handler_concurrent_queries() {
+    let handler = NGordNetHandler::new(
+        "data/ngrams/very_short.csv",
+        "data/ngrams/total_counts.csv",
+        "data/wordnet/synsets16.txt",
+        "data/wordnet/hyponyms16.txt",
+        None,
+    )
+    .expect("error loading files");
+    let expected = [
+        handler.respond_history(new_query("airport, request", 2005, 2008, 0)),
+        handler.respond_hyponyms(new_query("change", 0, 0, 0)),
+        handler.respond_synonyms(new_query("jump", 0, 0, 0)),
+    ];
+
+    std::thread::scope(|s| {
+        for t in 0..8 {
+            let handler = &handler;
+            let expected = &expected;
+            s.spawn(move || {
+                for i in 0..100 {
+                    let response = match (t + i) % 3 {
+                        0 => handler.respond_history(new_query("airport, request", 2005, 2008, 0)),
+                        1 => handler.respond_hyponyms(new_query("change", 0, 0, 0)),
+                        _ => handler.respond_synonyms(new_query("jump", 0, 0, 0)),
+                    };
+                    assert_eq!(response, expected[(t + i) % 3]);
+                }
+            });
+        }
+    });
+    let stats = handler.respond_stats();
+    // 800 requests in the threads, plus 3 to fill the cache
+    assert!(stats.contains("\"entries\": 3,"), "{stats}");
+}
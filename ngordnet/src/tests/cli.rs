@@ -0,0 +1,138 @@
+use clap::Parser;
+
+use crate::{
+    cli::{repl, respond, split_args, Cli, Command, QueryArgs, QueryKind},
+    handler::NGordNetHandler,
+    query::QueryError,
+};
+
+fn handler() -> NGordNetHandler {
+    NGordNetHandler::new(
+        "data/ngrams/very_short.csv",
+        "data/ngrams/total_counts.csv",
+        "data/wordnet/synsets16.txt",
+        "data/wordnet/hyponyms16.txt",
+        None,
+    )
+    .expect("error loading files")
+}
+
+fn query_args(args: &[&str]) -> QueryArgs {
+    let cli = Cli::try_parse_from(["ngordnet", "query"].iter().chain(args)).unwrap();
+    match cli.command {
+        Some(Command::Query(args)) => args,
+        command => panic!("expected a query, got {command:?}"),
+    }
+}
+
+#[test]
+fn test_split_args() {
+    assert_eq!(
+        split_args("  hyponyms --words \"act, change\"  --k 5 ").unwrap(),
+        vec!["hyponyms", "--words", "act, change", "--k", "5"]
+    );
+    assert_eq!(split_args("a\"b c\"d \"\"").unwrap(), vec!["ab cd", ""]);
+    assert!(split_args("").unwrap().is_empty());
+    assert!(split_args("--words \"act").is_err());
+}
+
+#[test]
+fn test_parse_query() {
+    let args = query_args(&["synonyms", "--words", "jump", "--k", "3"]);
+    assert_eq!(args.kind, QueryKind::Synonyms);
+    assert_eq!(args.words, "jump");
+    assert_eq!(
+        (args.start, args.end, args.k, args.smoothing),
+        (1900, 2020, 3, 1)
+    );
+
+    let args = query_args(&["historytext", "--words", "a", "--start", "2000"]);
+    assert_eq!(args.kind, QueryKind::HistoryText);
+    assert_eq!(args.start, 2000);
+
+    assert!(Cli::try_parse_from(["ngordnet", "query", "synonyms"]).is_err());
+    assert!(Cli::try_parse_from(["ngordnet", "query", "antonyms", "--words", "a"]).is_err());
+    let cli = Cli::try_parse_from(["ngordnet", "--words-file", "words.csv"]).unwrap();
+    assert_eq!(cli.data.words_file, "words.csv");
+    assert!(cli.command.is_none());
+}
+
+#[test]
+fn test_respond_matches_handler() {
+    let handler = handler();
+    let args = query_args(&["hyponyms", "--words", "act", "--start", "0", "--end", "0"]);
+    assert_eq!(
+        respond(&handler, &args).unwrap(),
+        handler.respond_hyponyms(crate::tests::new_query("act", 0, 0, 0))
+    );
+
+    let args = query_args(&[
+        "history", "--words", "airport", "--start", "2005", "--end", "2008",
+    ]);
+    assert_eq!(
+        respond(&handler, &args).unwrap(),
+        "{\n  \"airport\": [2007, 6.207e-6, 2008, 6.027e-6]\n}"
+    );
+
+    let args = query_args(&[
+        "synonyms", "--words", "jump", "--start", "2000", "--end", "1999",
+    ]);
+    assert_eq!(
+        respond(&handler, &args),
+        Err(QueryError::YearRange {
+            start_year: 2000,
+            end_year: 1999
+        })
+    );
+    let args = query_args(&["synonyms", "--words", "wombat"]);
+    assert_eq!(
+        respond(&handler, &args),
+        Err(QueryError::UnknownWords(vec!["wombat".to_string()]))
+    );
+}
+
+#[test]
+fn test_repl() {
+    let handler = handler();
+    let input = "\
+        synonyms --words jump\n\
+        \n\
+        history --words \"airport, request\" --start 2008 --end 2008\n\
+        synonyms --words jump --k 1001\n\
+        antonyms --words jump\n\
+        stats\n\
+        quit\n\
+        synonyms --words jump\n";
+    let mut output = Vec::new();
+    repl(&handler, input.as_bytes(), &mut output, false).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    let mut lines = output.lines();
+    assert_eq!(lines.next(), Some("jump, leap"));
+    assert_eq!(lines.next(), Some("{"));
+    assert_eq!(lines.next(), Some("  \"airport\": [2008, 6.027e-6],"));
+    assert_eq!(lines.next(), Some("  \"request\": [2008, 2.766e-5]"));
+    assert_eq!(lines.next(), Some("}"));
+    assert_eq!(
+        lines.next(),
+        Some("error: k is 1001, but can be at most 1000")
+    );
+    assert!(lines.next().unwrap().starts_with("error:"));
+    // the response to stats, and nothing after quit
+    assert!(output.contains("\"synonyms\": {\"requests\": 1, \"hits\": 0,"));
+    assert!(output.ends_with("}\n"));
+}
+
+#[test]
+fn test_repl_prompt() {
+    let handler = handler();
+    let mut output = Vec::new();
+    repl(
+        &handler,
+        "synonyms --words jump\n".as_bytes(),
+        &mut output,
+        true,
+    )
+    .unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "> jump, leap\n> ");
+}
@@ -1,13 +1,18 @@
 use crate::Query;
 
+mod ancestor;
+mod cache;
+mod cli;
 mod ngrammap;
+mod query;
 mod timeseries;
+mod trie;
 mod wordnet_k0mw;
 mod wordnet_k0sw;
 mod wordnet_kpmw;
 mod wordnet_kpsw;
 
-fn new_query(words: &str, start_year: usize, end_year: usize, k: usize) -> Query {
+fn new_query(words: &str, start_year: usize, end_year: usize, k: usize) -> Query<'_> {
     Query {
         words,
         start_year,
@@ -45,7 +45,7 @@ fn test_on_large_file() {
     println!("getting weight for dog: {:?}", dog_weight.data.get(&1865));
     assert!((dog_weight.data.get(&1865).unwrap() - 75819.0 / 2563919231.0).abs() < 1e-7);
 
-    let both_weight = ngm.summed_weight_history(&vec!["fish", "dog"], 1850, 9001);
+    let both_weight = ngm.summed_weight_history(&["fish", "dog"], 1850, 9001);
     let expected = (136497.0 + 75819.0) / 2563919231.0;
     println!("getting weight for both: {:?}", both_weight.data.get(&1865));
     assert!((both_weight.data.get(&1865).unwrap() - expected).abs() < 1e-7);
@@ -58,6 +58,7 @@ fn test_handler_small() {
         "data/ngrams/total_counts.csv",
         "data/wordnet/synsets16.txt",
         "data/wordnet/hyponyms16.txt",
+        None,
     )
     .expect("error loading files");
 
@@ -78,3 +79,167 @@ fn test_handler_small() {
         }"
     );
 }
+
+#[test]
+fn test_cache_round_trip() {
+    let cache_file =
+        std::env::temp_dir().join(format!("ngordnet-test-{}.cache", std::process::id()));
+    let cache_file = cache_file.to_str().unwrap();
+    let ngm = NGramMap::new(
+        "./data/ngrams/very_short.csv",
+        "./data/ngrams/total_counts.csv",
+    )
+    .expect("error loading files");
+    ngm.save_cache(cache_file).expect("error writing cache");
+    let cached = NGramMap::load_cache(cache_file).expect("error reading cache");
+    std::fs::remove_file(cache_file).unwrap();
+
+    for word in ["airport", "request", "wandered", "cheese"] {
+        assert_eq!(
+            ngm.total_count(word, 2005, 2008),
+            cached.total_count(word, 2005, 2008)
+        );
+        assert_eq!(
+            ngm.weight_history(word, 2005, 2008).data,
+            cached.weight_history(word, 2005, 2008).data
+        );
+    }
+}
+
+/// Returns a path in the temporary directory for a test's file
+fn temp_file(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("ngordnet-test-{}-{name}", std::process::id()));
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn test_cache_checks_its_sources() {
+    let cache_file = temp_file("sources.cache");
+    let counts_file = temp_file("counts.csv");
+    std::fs::write(&counts_file, "2007,1000000,1,1\n").unwrap();
+    let words_file = "./data/ngrams/very_short.csv";
+    let ngm = NGramMap::open_cached(words_file, "./data/ngrams/total_counts.csv", &cache_file)
+        .expect("error loading files");
+    assert!(ngm.weight_history("airport", 2007, 2007).data[&2007] < 1e-5);
+
+    // the cache is newer than counts_file, but was made from other totals
+    let ngm =
+        NGramMap::open_cached(words_file, &counts_file, &cache_file).expect("error loading files");
+    assert_eq!(
+        ngm.weight_history("airport", 2007, 2007).data[&2007],
+        0.175702
+    );
+    std::fs::remove_file(&cache_file).unwrap();
+    std::fs::remove_file(&counts_file).unwrap();
+}
+
+// the handler only writes a cache where it's told to
+#[test]
+fn test_handler_cache_file() {
+    let cache_file = temp_file("handler.cache");
+    for _ in 0..2 {
+        let handler = NGordNetHandler::new(
+            "data/ngrams/very_short.csv",
+            "data/ngrams/total_counts.csv",
+            "data/wordnet/synsets16.txt",
+            "data/wordnet/hyponyms16.txt",
+            Some(&cache_file),
+        )
+        .expect("error loading files");
+        assert!(std::path::Path::new(&cache_file).exists());
+        let query = Query {
+            words: "request",
+            start_year: 2005,
+            end_year: 2005,
+            k: 0,
+        };
+        assert_eq!(
+            handler.respond_history(query),
+            "{\n  \"request\": [2005, 2.428e-5]\n}"
+        );
+    }
+    std::fs::remove_file(&cache_file).unwrap();
+}
+
+#[test]
+fn test_cache_with_bad_offsets() {
+    let cache_file = temp_file("offsets.cache");
+    let (words_file, counts_file) = (
+        "./data/ngrams/very_short.csv",
+        "./data/ngrams/total_counts.csv",
+    );
+    let ngm = NGramMap::new(words_file, counts_file).expect("error loading files");
+    ngm.save_cache(&cache_file).expect("error writing cache");
+
+    // walk the layout written by save_cache to the last offset, which is followed by
+    // the number of entries, and make it one more than that
+    let mut bytes = std::fs::read(&cache_file).unwrap();
+    let u64_at =
+        |bytes: &[u8], at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+    let mut at = b"NGMCACH2".len();
+    for _ in 0..2 {
+        // the words and counts file names
+        at += 8 + u64_at(&bytes, at) as usize;
+    }
+    // first_year, then the totals
+    at += 2;
+    at += 8 + 8 * u64_at(&bytes, at) as usize;
+    let num_words = u64_at(&bytes, at) as usize;
+    assert_eq!(num_words, ngm.words().len());
+    at += 8;
+    for _ in 0..num_words {
+        at += 8 + u64_at(&bytes, at) as usize;
+    }
+    let last_offset = at + 4 * num_words;
+    let num_entries = u64_at(&bytes, last_offset + 4);
+    assert_eq!(bytes.len(), last_offset + 4 + 8 + 10 * num_entries as usize);
+    bytes[last_offset..last_offset + 4].copy_from_slice(&(num_entries as u32 + 1).to_le_bytes());
+    std::fs::write(&cache_file, &bytes).unwrap();
+
+    let err = NGramMap::load_cache(&cache_file).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    // open_cached parses the data files again instead
+    let reparsed =
+        NGramMap::open_cached(words_file, counts_file, &cache_file).expect("error loading files");
+    assert_eq!(
+        reparsed.total_count("request", 2005, 2030),
+        ngm.total_count("request", 2005, 2030)
+    );
+    std::fs::remove_file(&cache_file).unwrap();
+}
+
+#[test]
+fn test_handler_history_text_and_smoothing() {
+    let handler = NGordNetHandler::new(
+        "data/ngrams/very_short.csv",
+        "data/ngrams/total_counts.csv",
+        "data/wordnet/synsets16.txt",
+        "data/wordnet/hyponyms16.txt",
+        None,
+    )
+    .expect("error loading files");
+
+    let query = Query {
+        words: "airport, request",
+        start_year: 2005,
+        end_year: 2008,
+        k: 0,
+    };
+    assert_eq!(
+        handler.respond_history_text(query.clone(), 1),
+        "airport: {2007=6.207e-6, 2008=6.027e-6}\n\
+        request: {2005=2.428e-5, 2006=2.447e-5, 2007=2.464e-5, 2008=2.766e-5}\n"
+    );
+
+    // with a window of 2 years, 2006 is the average of 2005 and 2006
+    let query = Query {
+        words: "request",
+        start_year: 2006,
+        end_year: 2007,
+        k: 0,
+    };
+    assert_eq!(
+        handler.respond_smoothed_history(query, 2),
+        "{\n  \"request\": [2006, 2.438e-5, 2007, 2.456e-5]\n}"
+    );
+}
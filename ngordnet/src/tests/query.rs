@@ -0,0 +1,114 @@
+use crate::{
+    handler::NGordNetHandler,
+    query::{parse_words, validate_smoothing, QueryError, MAX_K},
+    tests::new_query,
+};
+
+#[test]
+fn test_parse_words() {
+    assert_eq!(
+        parse_words(" Dog,cat , ,DOG,\tbird\n, cat"),
+        vec!["dog", "cat", "bird"]
+    );
+    assert_eq!(
+        parse_words("airport +  Wandered"),
+        vec!["airport +  wandered"]
+    );
+    assert!(parse_words(" , ,").is_empty());
+}
+
+#[test]
+fn test_validate() {
+    assert_eq!(new_query("dog", 2000, 2000, 0).validate(), Ok(()));
+    assert_eq!(new_query("dog", 2000, 2020, MAX_K).validate(), Ok(()));
+    assert_eq!(
+        new_query(" , ", 2000, 2020, 0).validate(),
+        Err(QueryError::NoWords)
+    );
+    assert_eq!(
+        new_query("dog", 2021, 2020, 0).validate(),
+        Err(QueryError::YearRange {
+            start_year: 2021,
+            end_year: 2020
+        })
+    );
+    assert_eq!(
+        new_query("dog", 2000, 2020, MAX_K + 1).validate(),
+        Err(QueryError::KTooLarge(MAX_K + 1))
+    );
+    assert_eq!(validate_smoothing(1), Ok(()));
+    assert_eq!(
+        validate_smoothing(1000),
+        Err(QueryError::SmoothingTooLarge(1000))
+    );
+}
+
+#[test]
+fn test_error_json() {
+    let error = QueryError::YearRange {
+        start_year: 2021,
+        end_year: 2020,
+    };
+    assert_eq!(error.status().code, 400);
+    assert_eq!(
+        error.to_json(),
+        "{\"status\": 400, \"error\": \"start year 2021 is after end year 2020\"}"
+    );
+
+    let error = QueryError::UnknownWords(vec!["cheese".to_string(), "\"quoted\"".to_string()]);
+    assert_eq!(error.status().code, 404);
+    assert_eq!(
+        error.to_json(),
+        "{\"status\": 404, \"error\": \"no data for cheese, \\\"quoted\\\"\"}"
+    );
+}
+
+#[test]
+fn test_known_words() {
+    let handler = NGordNetHandler::new(
+        "data/ngrams/very_short.csv",
+        "data/ngrams/total_counts.csv",
+        "data/wordnet/synsets16.txt",
+        "data/wordnet/hyponyms16.txt",
+        None,
+    )
+    .expect("error loading files");
+
+    // words only need to appear in one of the two data sets
+    assert_eq!(
+        handler.check_known_words(&new_query("airport", 0, 0, 0)),
+        Ok(())
+    );
+    assert_eq!(
+        handler.check_known_words(&new_query("act", 0, 0, 0)),
+        Ok(())
+    );
+    assert_eq!(
+        handler.check_known_words(&new_query("cheese, Airport", 0, 0, 0)),
+        Ok(())
+    );
+    assert_eq!(
+        handler.check_known_words(&new_query("cheese, crackers + wine", 0, 0, 0)),
+        Err(QueryError::UnknownWords(vec![
+            "cheese".to_string(),
+            "crackers".to_string(),
+            "wine".to_string()
+        ]))
+    );
+}
+
+#[test]
+fn test_missing_file_is_named() {
+    let error = NGordNetHandler::new(
+        "data/ngrams/very_short.csv",
+        "data/ngrams/total_counts.csv",
+        "data/wordnet/no_such_synsets.txt",
+        "data/wordnet/hyponyms16.txt",
+        None,
+    )
+    .err()
+    .expect("handler should not load");
+    assert!(error
+        .to_string()
+        .contains("data/wordnet/no_such_synsets.txt"));
+}
@@ -19,3 +19,140 @@ fn from_spec() {
         "TimeSeries { data: {1991: 0.0, 1992: 100.0, 1994: 600.0, 1995: 500.0} }"
     )
 }
+
+#[test]
+fn rolling_mean() {
+    let mut ts = TimeSeries::new();
+    ts.data.insert(2000, 1.0);
+    ts.data.insert(2001, 2.0);
+    ts.data.insert(2002, 6.0);
+    ts.data.insert(2005, 4.0);
+
+    assert_eq!(
+        format!("{:?}", ts.rolling_mean(2)),
+        "TimeSeries { data: {2000: 1.0, 2001: 1.5, 2002: 4.0, 2005: 4.0} }"
+    );
+    // missing years are left out of the average
+    assert_eq!(
+        format!("{:?}", ts.rolling_mean(4)),
+        "TimeSeries { data: {2000: 1.0, 2001: 1.5, 2002: 3.0, 2005: 5.0} }"
+    );
+    assert_eq!(ts.rolling_mean(1).data, ts.data);
+}
+
+fn series(points: &[(usize, f64)]) -> TimeSeries {
+    let mut ts = TimeSeries::new();
+    ts.data.extend(points.iter().copied());
+    ts
+}
+
+#[test]
+fn restrict_and_accessors() {
+    let mut ts = series(&[(1990, 1.0), (1995, 2.0), (2000, 3.0), (2005, 4.0)]);
+    ts.restrict(1995, 2000);
+    assert_eq!(ts.years(), vec![1995, 2000]);
+    assert_eq!(ts.values(), vec![2.0, 3.0]);
+    ts.restrict(2001, 2000);
+    assert!(ts.data.is_empty());
+}
+
+#[test]
+fn division() {
+    let mut counts = series(&[(1990, 10.0), (1991, 30.0), (1992, 5.0)]);
+    let totals = series(&[(1991, 60.0), (1992, 10.0), (1993, 1.0)]);
+    counts /= &totals;
+    assert_eq!(
+        format!("{counts:?}"),
+        "TimeSeries { data: {1991: 0.5, 1992: 0.5} }"
+    );
+}
+
+#[test]
+fn subtraction_and_multiplication() {
+    let a = series(&[(2000, 5.0), (2001, 4.0)]);
+    let b = series(&[(2001, 1.0), (2002, 2.0)]);
+
+    assert_eq!(
+        format!("{:?}", &a - &b),
+        "TimeSeries { data: {2000: 5.0, 2001: 3.0, 2002: -2.0} }"
+    );
+    assert_eq!(format!("{:?}", &a * &b), "TimeSeries { data: {2001: 4.0} }");
+    assert_eq!(
+        format!("{:?}", &a * 0.5),
+        "TimeSeries { data: {2000: 2.5, 2001: 2.0} }"
+    );
+
+    let mut c = a.clone();
+    c -= &a;
+    assert_eq!(c.values(), vec![0.0, 0.0]);
+    c *= &b;
+    assert_eq!(c.years(), vec![2001]);
+    c *= 3.0;
+    assert_eq!(c.values(), vec![0.0]);
+}
+
+#[test]
+fn scalar_subtraction() {
+    let a = series(&[(2000, 5.0), (2001, 4.0)]);
+    assert_eq!(
+        format!("{:?}", &a - 1.5),
+        "TimeSeries { data: {2000: 3.5, 2001: 2.5} }"
+    );
+    // a itself is left alone
+    assert_eq!(a.values(), vec![5.0, 4.0]);
+
+    let mut c = a.clone();
+    c -= 4.0;
+    assert_eq!(c.values(), vec![1.0, 0.0]);
+    c -= -1.0;
+    assert_eq!(c.values(), vec![2.0, 1.0]);
+    assert!((&TimeSeries::new() - 1.0).data.is_empty());
+}
+
+#[test]
+fn normalize_and_cumulative_sum() {
+    let mut ts = series(&[(2000, 1.0), (2001, 3.0), (2003, 4.0)]);
+    assert_eq!(
+        format!("{:?}", ts.cumulative_sum()),
+        "TimeSeries { data: {2000: 1.0, 2001: 4.0, 2003: 8.0} }"
+    );
+    ts.normalize();
+    assert_eq!(ts.values(), vec![0.125, 0.375, 0.5]);
+
+    let mut zeroes = series(&[(2000, 0.0), (2001, 0.0)]);
+    zeroes.normalize();
+    assert_eq!(zeroes.values(), vec![0.0, 0.0]);
+}
+
+#[test]
+fn growth() {
+    let ts = series(&[(2000, 0.0), (2001, 2.0), (2002, 3.0), (2004, 1.5)]);
+    // 2001 is left out, since the previous entry is 0
+    assert_eq!(
+        format!("{:?}", ts.growth()),
+        "TimeSeries { data: {2002: 0.5, 2004: -0.5} }"
+    );
+    assert!(TimeSeries::new().growth().data.is_empty());
+}
+
+#[test]
+fn peak_year() {
+    let ts = series(&[(2000, 1.0), (2001, 7.0), (2002, 3.0), (2003, 7.0)]);
+    assert_eq!(ts.peak_year(), Some((2001, 7.0)));
+    assert_eq!(TimeSeries::new().peak_year(), None);
+}
+
+#[test]
+fn linear_trend() {
+    let ts = series(&[(2000, 1.0), (2001, 3.0), (2002, 5.0), (2003, 7.0)]);
+    let (slope, intercept) = ts.linear_trend().unwrap();
+    assert!((slope - 2.0).abs() < 1e-9);
+    assert!((slope * 2010.0 + intercept - 21.0).abs() < 1e-6);
+
+    let noisy = series(&[(1, 1.0), (2, 2.0), (3, 2.0), (4, 3.0)]);
+    let (slope, intercept) = noisy.linear_trend().unwrap();
+    assert!((slope - 0.6).abs() < 1e-9);
+    assert!((intercept - 0.5).abs() < 1e-9);
+
+    assert_eq!(series(&[(2000, 1.0)]).linear_trend(), None);
+}
@@ -0,0 +1,120 @@
+use crate::{handler::NGordNetHandler, trie::Trie, AutocompleteQuery};
+
+fn sample_trie() -> (Trie, Vec<&'static str>) {
+    let words = vec!["cat", "car", "cart", "dog", "do", "cot", "scat"];
+    let mut trie = Trie::new();
+    for (i, w) in words.iter().enumerate() {
+        trie.insert(w, i as u32);
+    }
+    (trie, words)
+}
+
+fn lookup(words: &[&'static str], ids: Vec<u32>) -> Vec<&'static str> {
+    let mut found: Vec<&str> = ids.into_iter().map(|id| words[id as usize]).collect();
+    found.sort_unstable();
+    found.dedup();
+    found
+}
+
+#[test]
+fn test_prefix() {
+    let (trie, words) = sample_trie();
+    assert_eq!(
+        lookup(&words, trie.with_prefix("ca")),
+        ["car", "cart", "cat"]
+    );
+    assert_eq!(lookup(&words, trie.with_prefix("do")), ["do", "dog"]);
+    assert_eq!(lookup(&words, trie.with_prefix("cart")), ["cart"]);
+    assert!(trie.with_prefix("x").is_empty());
+    assert_eq!(trie.with_prefix("").len(), words.len());
+
+    // results come back in key order
+    let in_order: Vec<&str> = trie
+        .with_prefix("")
+        .into_iter()
+        .map(|id| words[id as usize])
+        .collect();
+    assert_eq!(in_order, ["car", "cart", "cat", "cot", "do", "dog", "scat"]);
+}
+
+#[test]
+fn test_reinsert() {
+    let mut trie = Trie::new();
+    trie.insert("cat", 1);
+    trie.insert("cat", 2);
+    assert_eq!(trie.with_prefix("cat"), [2]);
+}
+
+#[test]
+fn test_fuzzy_prefix() {
+    let (trie, words) = sample_trie();
+    // replace: "co" -> "ca"
+    assert_eq!(
+        lookup(&words, trie.with_fuzzy_prefix("cot")),
+        ["cat", "cot"]
+    );
+    // remove: "dxo" -> "do"
+    assert_eq!(lookup(&words, trie.with_fuzzy_prefix("dxo")), ["do", "dog"]);
+    // insert: "cat" -> "scat"
+    assert!(lookup(&words, trie.with_fuzzy_prefix("cat")).contains(&"scat"));
+    // two edits away
+    assert!(trie.with_fuzzy_prefix("xxg").is_empty());
+}
+
+#[test]
+fn test_autocomplete() {
+    let handler = NGordNetHandler::new(
+        "data/ngrams/very_short.csv",
+        "data/ngrams/total_counts.csv",
+        "data/wordnet/synsets16.txt",
+        "data/wordnet/hyponyms16.txt",
+        None,
+    )
+    .expect("error loading files");
+
+    let query = |prefix, k, fuzzy| AutocompleteQuery {
+        prefix,
+        k,
+        start_year: None,
+        end_year: None,
+        fuzzy,
+    };
+    assert_eq!(
+        handler.respond_autocomplete(query("Air", 10, false)),
+        "[\"airport\"]"
+    );
+    assert_eq!(handler.respond_autocomplete(query("zzz", 10, false)), "[]");
+    // request is more popular than airport, so comes first
+    assert_eq!(
+        handler.respond_autocomplete(query("", 2, false)),
+        "[\"request\", \"wandered\"]"
+    );
+    // exact matches come before fuzzy matches
+    assert_eq!(
+        handler.respond_autocomplete(query("wan", 10, true)),
+        "[\"wandered\"]"
+    );
+    assert_eq!(
+        handler.respond_autocomplete(query("rwquest", 10, true)),
+        "[\"request\"]"
+    );
+    assert_eq!(
+        handler.respond_autocomplete(query("rwquest", 10, false)),
+        "[]"
+    );
+    assert_eq!(
+        handler.respond_autocomplete(query("rwq", 10, true)),
+        "[\"request\"]"
+    );
+    // too short to match fuzzily, though "r" is one edit away
+    assert_eq!(handler.respond_autocomplete(query("rq", 10, true)), "[]");
+
+    let ranged = AutocompleteQuery {
+        prefix: "",
+        k: 1,
+        start_year: Some(2007),
+        end_year: Some(2007),
+        fuzzy: false,
+    };
+    assert_eq!(handler.respond_autocomplete(ranged), "[\"request\"]");
+}
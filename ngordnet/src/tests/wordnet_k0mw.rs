@@ -9,6 +9,7 @@ fn test_small() {
         "data/ngrams/total_counts.csv",
         "data/wordnet/synsets16.txt",
         "data/wordnet/hyponyms16.txt",
+        None,
     )
     .expect("error loading files");
 
@@ -41,6 +42,7 @@ fn test_large() {
         "data/ngrams/total_counts.csv",
         "data/wordnet/synsets.txt",
         "data/wordnet/hyponyms.txt",
+        None,
     )
     .expect("error loading files");
 
@@ -8,6 +8,7 @@ fn test_act() {
         "data/ngrams/total_counts.csv",
         "data/wordnet/synsets16.txt",
         "data/wordnet/hyponyms16.txt",
+        None,
     )
     .expect("error loading files");
 
@@ -20,3 +21,25 @@ fn test_act() {
         "act, action, change, demotion, human_action, human_activity, variation"
     );
 }
+
+/// Every hyponym of a word should have that word as a hypernym
+#[test]
+fn test_hypernyms_invert_hyponyms() {
+    let handler = NGordNetHandler::new(
+        "data/ngrams/very_short.csv",
+        "data/ngrams/total_counts.csv",
+        "data/wordnet/synsets16.txt",
+        "data/wordnet/hyponyms16.txt",
+        None,
+    )
+    .expect("error loading files");
+
+    let hyponyms = handler.respond_hyponyms(new_query("act", 0, 0, 0));
+    for hyponym in hyponyms.split(", ") {
+        let hypernyms = handler.respond_hypernyms(new_query(hyponym, 0, 0, 0));
+        assert!(
+            hypernyms.split(", ").any(|w| w == "act"),
+            "{hyponym} has hypernyms {hypernyms}"
+        );
+    }
+}
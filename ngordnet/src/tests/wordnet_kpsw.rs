@@ -8,6 +8,7 @@ fn test_big() {
         "data/ngrams/total_counts.csv",
         "data/wordnet/synsets.txt",
         "data/wordnet/hyponyms.txt",
+        None,
     )
     .expect("error loading files");
 
@@ -1,6 +1,6 @@
 use std::{
     collections::BTreeMap,
-    ops::{AddAssign, DivAssign},
+    ops::{AddAssign, DivAssign, Mul, MulAssign, Sub, SubAssign},
 };
 
 #[derive(Clone, Debug)]
@@ -11,12 +11,109 @@ pub struct TimeSeries {
 impl TimeSeries {
     /// Creates a new, empty TimeSeries
     pub fn new() -> Self {
-        todo!()
+        Self {
+            data: BTreeMap::new(),
+        }
     }
 
     /// Removes all keys that are not in the range [start_year, end_year]
     pub fn restrict(&mut self, start_year: usize, end_year: usize) {
-        todo!()
+        self.data
+            .retain(|&year, _| start_year <= year && year <= end_year);
+    }
+
+    /// Returns all years in the TimeSeries, in increasing order
+    pub fn years(&self) -> Vec<usize> {
+        self.data.keys().copied().collect()
+    }
+
+    /// Returns all values in the TimeSeries, in the same order as years()
+    pub fn values(&self) -> Vec<f64> {
+        self.data.values().copied().collect()
+    }
+
+    /// Scales the values so that they add up to 1
+    /// If the values add up to 0, the TimeSeries is left unchanged
+    pub fn normalize(&mut self) {
+        let sum: f64 = self.data.values().sum();
+        if sum != 0.0 {
+            *self *= 1.0 / sum;
+        }
+    }
+
+    /// Returns a TimeSeries where each year's value is the sum of all values up to that year
+    pub fn cumulative_sum(&self) -> TimeSeries {
+        let mut result = TimeSeries::new();
+        let mut sum = 0.0;
+        for (&year, &value) in &self.data {
+            sum += value;
+            result.data.insert(year, sum);
+        }
+        result
+    }
+
+    /// Returns a TimeSeries where each year's value is the mean of the values in
+    /// [year - window + 1, year]. Years missing from self are left out of the mean.
+    pub fn rolling_mean(&self, window: usize) -> TimeSeries {
+        let window = window.max(1);
+        let points: Vec<(usize, f64)> = self.data.iter().map(|(&y, &v)| (y, v)).collect();
+        let mut result = TimeSeries::new();
+        let mut start = 0;
+        for (end, &(year, _)) in points.iter().enumerate() {
+            while points[start].0 + window <= year {
+                start += 1;
+            }
+            let in_window = &points[start..=end];
+            let sum: f64 = in_window.iter().map(|&(_, v)| v).sum();
+            result.data.insert(year, sum / in_window.len() as f64);
+        }
+        result
+    }
+
+    /// Returns the relative change from each year's previous entry, (value - prev) / prev
+    /// The first year, and years whose previous entry is 0, are left out
+    pub fn growth(&self) -> TimeSeries {
+        let mut result = TimeSeries::new();
+        let mut prev: Option<f64> = None;
+        for (&year, &value) in &self.data {
+            if let Some(p) = prev.filter(|&p| p != 0.0) {
+                result.data.insert(year, (value - p) / p);
+            }
+            prev = Some(value);
+        }
+        result
+    }
+
+    /// Returns the year with the largest value, and that value
+    /// Ties go to the earliest year. Returns None if the TimeSeries is empty
+    pub fn peak_year(&self) -> Option<(usize, f64)> {
+        let mut peak: Option<(usize, f64)> = None;
+        for (&year, &value) in &self.data {
+            if peak.is_none_or(|(_, v)| value > v) {
+                peak = Some((year, value));
+            }
+        }
+        peak
+    }
+
+    /// Fits a least-squares line value = slope * year + intercept, returning (slope, intercept)
+    /// Returns None if there are fewer than two years
+    pub fn linear_trend(&self) -> Option<(f64, f64)> {
+        if self.data.len() < 2 {
+            return None;
+        }
+        let n = self.data.len() as f64;
+        let mean_year = self.data.keys().map(|&y| y as f64).sum::<f64>() / n;
+        let mean_value = self.data.values().sum::<f64>() / n;
+        let mut covariance = 0.0;
+        let mut variance = 0.0;
+        for (&year, &value) in &self.data {
+            let dy = year as f64 - mean_year;
+            covariance += dy * (value - mean_value);
+            variance += dy * dy;
+        }
+        let slope = covariance / variance;
+        Some((slope, mean_value - slope * mean_year))
     }
 }
 
@@ -24,7 +121,9 @@ impl AddAssign<&TimeSeries> for TimeSeries {
     /// Adds two TimeSeries together, keeping all keys that appear in either TimeSeries
     /// assumes a default value of 0 if one of the two TimeSeries does not contain a key
     fn add_assign(&mut self, rhs: &TimeSeries) {
-        todo!()
+        for (&year, &value) in &rhs.data {
+            *self.data.entry(year).or_insert(0.0) += value;
+        }
     }
 }
 
@@ -32,6 +131,97 @@ impl DivAssign<&TimeSeries> for TimeSeries {
     /// Divides self by rhs, only keeping keys that appear in both TimeSeries
     /// Assume rhs does not contain any zeroes
     fn div_assign(&mut self, rhs: &TimeSeries) {
-        todo!()
+        self.data.retain(|year, value| match rhs.data.get(year) {
+            Some(divisor) => {
+                *value /= divisor;
+                true
+            }
+            None => false,
+        });
+    }
+}
+
+impl SubAssign<&TimeSeries> for TimeSeries {
+    /// Subtracts rhs from self, keeping all keys that appear in either TimeSeries
+    /// assumes a default value of 0 if one of the two TimeSeries does not contain a key
+    fn sub_assign(&mut self, rhs: &TimeSeries) {
+        for (&year, &value) in &rhs.data {
+            *self.data.entry(year).or_insert(0.0) -= value;
+        }
+    }
+}
+
+impl MulAssign<&TimeSeries> for TimeSeries {
+    /// Multiplies self by rhs, only keeping keys that appear in both TimeSeries
+    fn mul_assign(&mut self, rhs: &TimeSeries) {
+        self.data.retain(|year, value| match rhs.data.get(year) {
+            Some(factor) => {
+                *value *= factor;
+                true
+            }
+            None => false,
+        });
+    }
+}
+
+impl MulAssign<f64> for TimeSeries {
+    /// Multiplies every value by rhs
+    fn mul_assign(&mut self, rhs: f64) {
+        for value in self.data.values_mut() {
+            *value *= rhs;
+        }
+    }
+}
+
+impl SubAssign<f64> for TimeSeries {
+    /// Subtracts rhs from every value
+    fn sub_assign(&mut self, rhs: f64) {
+        for value in self.data.values_mut() {
+            *value -= rhs;
+        }
+    }
+}
+
+impl Sub<&TimeSeries> for &TimeSeries {
+    type Output = TimeSeries;
+
+    /// See SubAssign
+    fn sub(self, rhs: &TimeSeries) -> TimeSeries {
+        let mut result = self.clone();
+        result -= rhs;
+        result
+    }
+}
+
+impl Mul<&TimeSeries> for &TimeSeries {
+    type Output = TimeSeries;
+
+    /// See MulAssign
+    fn mul(self, rhs: &TimeSeries) -> TimeSeries {
+        let mut result = self.clone();
+        result *= rhs;
+        result
+    }
+}
+
+impl Mul<f64> for &TimeSeries {
+    type Output = TimeSeries;
+
+    /// See MulAssign
+    fn mul(self, rhs: f64) -> TimeSeries {
+        let mut result = self.clone();
+        result *= rhs;
+        result
+    }
+}
+
+impl Sub<f64> for &TimeSeries {
+    type Output = TimeSeries;
+
+    /// See SubAssign
+    fn sub(self, rhs: f64) -> TimeSeries {
+        let mut result = self.clone();
+        result -= rhs;
+        result
     }
 }
@@ -0,0 +1,120 @@
+/*
+A trie mapping words to u32 values. Nodes are stored in one Vec, and refer to
+their children by index, so the whole trie is a handful of allocations.
+Children are kept sorted by character, so lookups are a binary search.
+*/
+pub struct Trie {
+    nodes: Vec<TrieNode>,
+}
+
+#[derive(Default)]
+struct TrieNode {
+    children: Vec<(char, u32)>,
+    value: Option<u32>,
+}
+
+const ROOT: u32 = 0;
+
+impl Trie {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![TrieNode::default()],
+        }
+    }
+
+    /// Associates key with value, replacing any previous value
+    pub fn insert(&mut self, key: &str, value: u32) {
+        let mut node = ROOT;
+        for c in key.chars() {
+            node = match self.child(node, c) {
+                Ok(child) => child,
+                Err(pos) => {
+                    let child = self.nodes.len() as u32;
+                    self.nodes.push(TrieNode::default());
+                    self.nodes[node as usize].children.insert(pos, (c, child));
+                    child
+                }
+            };
+        }
+        self.nodes[node as usize].value = Some(value);
+    }
+
+    /// Returns the values of all keys that start with prefix, in key order
+    pub fn with_prefix(&self, prefix: &str) -> Vec<u32> {
+        let mut values = Vec::new();
+        if let Some(node) = self.find(prefix) {
+            self.collect(node, &mut values);
+        }
+        values
+    }
+
+    /// Returns the values of all keys that start with a string within edit distance 1
+    /// of prefix, where an edit is inserting, removing or replacing one character.
+    /// Values may appear more than once.
+    pub fn with_fuzzy_prefix(&self, prefix: &str) -> Vec<u32> {
+        let prefix: Vec<char> = prefix.chars().collect();
+        let mut nodes = Vec::new();
+        self.fuzzy_find(ROOT, &prefix, 1, &mut nodes);
+        nodes.sort_unstable();
+        nodes.dedup();
+
+        let mut values = Vec::new();
+        for node in nodes {
+            self.collect(node, &mut values);
+        }
+        values
+    }
+
+    /// Returns Ok(index of the child of node for c), or Err(where that child would go)
+    fn child(&self, node: u32, c: char) -> Result<u32, usize> {
+        let children = &self.nodes[node as usize].children;
+        children
+            .binary_search_by_key(&c, |&(ch, _)| ch)
+            .map(|i| children[i].1)
+    }
+
+    fn find(&self, key: &str) -> Option<u32> {
+        let mut node = ROOT;
+        for c in key.chars() {
+            node = self.child(node, c).ok()?;
+        }
+        Some(node)
+    }
+
+    /// Pushes every node that is reached by matching rest with at most `edits` edits
+    fn fuzzy_find(&self, node: u32, rest: &[char], edits: usize, found: &mut Vec<u32>) {
+        let Some((&c, after)) = rest.split_first() else {
+            found.push(node);
+            return;
+        };
+        if let Ok(child) = self.child(node, c) {
+            self.fuzzy_find(child, after, edits, found);
+        }
+        if edits == 0 {
+            return;
+        }
+        // c was typed by mistake
+        self.fuzzy_find(node, after, edits - 1, found);
+        for &(ch, child) in &self.nodes[node as usize].children {
+            if ch != c {
+                // c should have been ch
+                self.fuzzy_find(child, after, edits - 1, found);
+            }
+            // ch was left out
+            self.fuzzy_find(child, rest, edits - 1, found);
+        }
+    }
+
+    /// Pushes the values in the subtrie rooted at node, in key order
+    fn collect(&self, node: u32, values: &mut Vec<u32>) {
+        // iterative, with children pushed in reverse so they are popped in order
+        let mut stack = vec![node];
+        while let Some(n) = stack.pop() {
+            let n = &self.nodes[n as usize];
+            if let Some(v) = n.value {
+                values.push(v);
+            }
+            stack.extend(n.children.iter().rev().map(|&(_, child)| child));
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::{BufRead, BufReader},
+};
+
+use crate::{
+    datafile::{malformed, open},
+    graph::{AncestralPath, Digraph},
+};
+
+/*
+WordNet groups words into synsets (sets of synonyms), and links synsets
+together with "is-a-kind-of" relations. For example, the synset {dog, domestic_dog}
+is a hyponym of {canine, canid}, which is in turn a hyponym of {carnivore}.
+
+Synsets are the vertices of a directed graph, with an edge from each synset to
+each of its hyponyms. Hypernyms are found by walking the reversed graph.
+*/
+pub struct WordNet {
+    synsets: Vec<Vec<String>>,
+    synset_ids: HashMap<String, Vec<usize>>,
+    hyponym_graph: Digraph,
+    hypernym_graph: Digraph,
+}
+
+impl WordNet {
+    /// Reads a synset file, with lines of the form "id,word1 word2 ...,definition",
+    /// and a hyponym file, with lines of the form "id,hyponym_id1,hyponym_id2,..."
+    pub fn new(synset_file: &str, hyponym_file: &str) -> Result<Self, std::io::Error> {
+        let mut synsets: Vec<Vec<String>> = Vec::new();
+        let mut synset_ids: HashMap<String, Vec<usize>> = HashMap::new();
+        for (line_num, line) in BufReader::new(open(synset_file)?).lines().enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let mut fields = line.splitn(3, ',');
+            let (Some(id), Some(words)) = (fields.next(), fields.next()) else {
+                return Err(malformed(synset_file, line_num));
+            };
+            let id: usize = id.parse().map_err(|_| malformed(synset_file, line_num))?;
+            if synsets.len() <= id {
+                synsets.resize(id + 1, Vec::new());
+            }
+            for word in words.split_whitespace() {
+                synsets[id].push(word.to_string());
+                synset_ids.entry(word.to_string()).or_default().push(id);
+            }
+        }
+
+        let mut hyponym_graph = Digraph::new(synsets.len());
+        for (line_num, line) in BufReader::new(open(hyponym_file)?).lines().enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let mut ids = line.split(',').map(|id| {
+                id.trim()
+                    .parse::<usize>()
+                    .map_err(|_| malformed(hyponym_file, line_num))
+            });
+            let Some(hypernym) = ids.next() else {
+                continue;
+            };
+            let hypernym = hypernym?;
+            for hyponym in ids {
+                hyponym_graph.add_edge(hypernym, hyponym?);
+            }
+        }
+        if synsets.len() < hyponym_graph.len() {
+            synsets.resize(hyponym_graph.len(), Vec::new());
+        }
+        let hypernym_graph = hyponym_graph.reverse();
+
+        Ok(Self {
+            synsets,
+            synset_ids,
+            hyponym_graph,
+            hypernym_graph,
+        })
+    }
+
+    /// Returns true if word is in any synset
+    pub fn contains_word(&self, word: &str) -> bool {
+        self.synset_ids.contains_key(word)
+    }
+
+    /// Returns the words that share a synset with every word in words
+    pub fn synonyms(&self, words: &[impl AsRef<str>]) -> BTreeSet<&str> {
+        self.common(words, |ids| ids.to_vec())
+    }
+
+    /// Returns the words that are hyponyms of every word in words.
+    /// Every word is considered to be a hyponym of itself.
+    pub fn hyponyms(&self, words: &[impl AsRef<str>]) -> BTreeSet<&str> {
+        self.common(words, |ids| {
+            self.hyponym_graph.reachable(ids.iter().copied())
+        })
+    }
+
+    /// Returns the words that are hypernyms of every word in words.
+    /// Every word is considered to be a hypernym of itself.
+    pub fn hypernyms(&self, words: &[impl AsRef<str>]) -> BTreeSet<&str> {
+        self.common(words, |ids| {
+            self.hypernym_graph.reachable(ids.iter().copied())
+        })
+    }
+
+    /// Returns the shortest path from a synset containing a, up through hypernyms to a
+    /// common ancestor, and back down to a synset containing b. This is the shortest
+    /// ancestral path between the synsets of a and b in the hypernym graph.
+    pub fn ancestral_path(&self, a: &str, b: &str) -> Option<AncestralPath> {
+        let ids = |word: &str| self.synset_ids.get(word).map_or(&[][..], Vec::as_slice);
+        self.hypernym_graph
+            .shortest_ancestral_path(ids(a).iter().copied(), ids(b).iter().copied())
+    }
+
+    /// Returns the words in synset id
+    pub fn synset(&self, id: usize) -> &[String] {
+        &self.synsets[id]
+    }
+
+    /// Returns the number of synsets on the shortest path from synset id up to a synset
+    /// with no hypernyms, including both ends, so a root synset has depth 1
+    pub fn depth(&self, id: usize) -> usize {
+        // nothing stops the hyponym file from having a cycle, and a synset
+        // whose hypernyms only lead around one never reaches a root, so it's
+        // treated as a root itself
+        self.hypernym_graph.distance_to_sink(id).unwrap_or(0) + 1
+    }
+
+    /// Returns the words in the synsets found by expand, intersected across all of words
+    fn common<'a>(
+        &'a self,
+        words: &[impl AsRef<str>],
+        expand: impl Fn(&[usize]) -> Vec<usize>,
+    ) -> BTreeSet<&'a str> {
+        let mut result: Option<BTreeSet<&str>> = None;
+        for word in words {
+            let ids = self
+                .synset_ids
+                .get(word.as_ref())
+                .map_or(&[][..], Vec::as_slice);
+            let found: BTreeSet<&str> = expand(ids)
+                .into_iter()
+                .flat_map(|id| self.synsets[id].iter().map(String::as_str))
+                .collect();
+            result = Some(match result {
+                Some(prev) => prev.intersection(&found).copied().collect(),
+                None => found,
+            });
+        }
+        result.unwrap_or_default()
+    }
+}